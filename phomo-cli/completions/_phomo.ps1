@@ -21,17 +21,44 @@ Register-ArgumentCompleter -Native -CommandName 'phomo' -ScriptBlock {
 
     $completions = @(switch ($command) {
         'phomo' {
+            [CompletionResult]::new('--tile-weights', '--tile-weights', [CompletionResultType]::ParameterName, 'CSV file mapping tile file names to weights, which are added to the distances of the tiles. Lower weights make a tile more likely to be used')
+            [CompletionResult]::new('--report', '--report', [CompletionResultType]::ParameterName, 'Save a JSON report of the assignments, with their cost, the tile usage and the time taken by the solver. A summary of the report is logged with -vv')
             [CompletionResult]::new('-g', '-g', [CompletionResultType]::ParameterName, 'Grid size, the number of tiles along the width and height')
             [CompletionResult]::new('--grid-size', '--grid-size', [CompletionResultType]::ParameterName, 'Grid size, the number of tiles along the width and height')
+            [CompletionResult]::new('--cell-size', '--cell-size', [CompletionResultType]::ParameterName, 'Cell size, the width and height of the tiles in pixels. The grid size is derived from it')
+            [CompletionResult]::new('--fit', '--fit', [CompletionResultType]::ParameterName, 'How the master image is fitted to the grid when its dimensions aren''t multiples of the grid size. The master is centre cropped, padded around or resized')
+            [CompletionResult]::new('--pad-color', '--pad-color', [CompletionResultType]::ParameterName, 'The colour of the padding of the master, its edges are extended when not provided')
+            [CompletionResult]::new('--layout', '--layout', [CompletionResultType]::ParameterName, 'The layout of the cells. The quadtree layout subdivides the detailed grid cells into quadrants, the tiles keep the size of the grid cells and are scaled down to the smaller cells. The hexagonal layout inscribes hexagons in the grid cells, in offset rows. The brick layout offsets every other row of grid cells by half a cell. The voronoi layout splits the master into as many polygons as there are grid cells, following its edges')
+            [CompletionResult]::new('--quadtree-depth', '--quadtree-depth', [CompletionResultType]::ParameterName, 'The maximum number of times the quadtree layout subdivides a grid cell')
+            [CompletionResult]::new('--quadtree-threshold', '--quadtree-threshold', [CompletionResultType]::ParameterName, 'The cells whose detail exceeds this threshold are subdivided')
+            [CompletionResult]::new('--quadtree-detail', '--quadtree-detail', [CompletionResultType]::ParameterName, 'The measure of the detail of the cells')
+            [CompletionResult]::new('--voronoi-seed', '--voronoi-seed', [CompletionResultType]::ParameterName, 'The seed of the random placement of the voronoi cells')
+            [CompletionResult]::new('--voronoi-edge-weight', '--voronoi-edge-weight', [CompletionResultType]::ParameterName, 'How much denser the voronoi cells are along the edges of the master image, 0 spreads them uniformly')
+            [CompletionResult]::new('--mask', '--mask', [CompletionResultType]::ParameterName, 'Mask image of the parts of the master to tile, the cells mostly outside of it are left untiled. Its alpha channel is used when it has one, its brightness otherwise')
+            [CompletionResult]::new('--mask-color', '--mask-color', [CompletionResultType]::ParameterName, 'Fill the untiled parts of the mosaic with this colour instead of the master image')
             [CompletionResult]::new('-n', '-n', [CompletionResultType]::ParameterName, 'The number of times a tile can appear in the mosaic')
             [CompletionResult]::new('--n-appearances', '--n-appearances', [CompletionResultType]::ParameterName, 'The number of times a tile can appear in the mosaic')
+            [CompletionResult]::new('--min-appearances', '--min-appearances', [CompletionResultType]::ParameterName, 'The minimum number of times each tile must appear in the mosaic')
+            [CompletionResult]::new('--repeat-radius', '--repeat-radius', [CompletionResultType]::ParameterName, 'Don''t repeat a tile within this many grid cells, horizontally and vertically, 0 disables it')
+            [CompletionResult]::new('--pin', '--pin', [CompletionResultType]::ParameterName, 'Pin a tile to a grid cell, the cells are indexed row by row and the tiles in the order they are read. Can be repeated')
             [CompletionResult]::new('--solver', '--solver', [CompletionResultType]::ParameterName, 'The solver to use to compute the tile to cell assignments')
+            [CompletionResult]::new('--auction-epsilon', '--auction-epsilon', [CompletionResultType]::ParameterName, 'The final epsilon of the auction solver, smaller values give assignments closer to the optimal cost. The duality gap of the final prices, an upper bound of the distance to the optimal cost, is logged')
+            [CompletionResult]::new('--auction-scaling', '--auction-scaling', [CompletionResultType]::ParameterName, 'The factor by which the auction solver divides epsilon between runs, 1 disables epsilon scaling')
+            [CompletionResult]::new('--seam-weight', '--seam-weight', [CompletionResultType]::ParameterName, 'The weight of the colour differences across the seams of neighbouring tiles in the objective of the annealing solver, 0 ignores the seams. Only used with the grid layout and without a mask')
+            [CompletionResult]::new('--repeat-penalty', '--repeat-penalty', [CompletionResultType]::ParameterName, 'The penalty of each pair of identical tiles within the repeat penalty radius in the objective of the annealing solver')
+            [CompletionResult]::new('--repeat-penalty-radius', '--repeat-penalty-radius', [CompletionResultType]::ParameterName, 'The radius, in grid cells, within which the annealing solver penalises repeated tiles')
+            [CompletionResult]::new('--annealing-moves', '--annealing-moves', [CompletionResultType]::ParameterName, 'The number of moves per grid cell tried by the annealing solver')
+            [CompletionResult]::new('--refine-budget', '--refine-budget', [CompletionResultType]::ParameterName, 'Stop refining the assignments after this many milliseconds')
+            [CompletionResult]::new('--time-budget', '--time-budget', [CompletionResultType]::ParameterName, 'Stop solving after this many milliseconds. The exact solvers which run out of time fall back to refined greedy assignments')
+            [CompletionResult]::new('--seed', '--seed', [CompletionResultType]::ParameterName, 'Break the ties between tiles of equal cost in a random order drawn from this seed, a different seed gives a different mosaic of the same cost')
             [CompletionResult]::new('--metric', '--metric', [CompletionResultType]::ParameterName, 'The distance metric to use')
             [CompletionResult]::new('--crop-tiles', '--crop-tiles', [CompletionResultType]::ParameterName, 'Crop tiles to grid cell size')
             [CompletionResult]::new('--resize-tiles', '--resize-tiles', [CompletionResultType]::ParameterName, 'Resize tiles to grid cell size')
             [CompletionResult]::new('--equalize', '--equalize', [CompletionResultType]::ParameterName, 'Equalize the master and tile image color distributions')
             [CompletionResult]::new('--transfer-master-to-tiles', '--transfer-master-to-tiles', [CompletionResultType]::ParameterName, 'Transfer the color palette of the master image to the tile images')
             [CompletionResult]::new('--transfer-tiles-to-master', '--transfer-tiles-to-master', [CompletionResultType]::ParameterName, 'Transfer the color palette of the tile images to the master image')
+            [CompletionResult]::new('--auction-jacobi', '--auction-jacobi', [CompletionResultType]::ParameterName, 'Make all the unassigned cells bid at once in the auction solver, in parallel')
+            [CompletionResult]::new('--refine', '--refine', [CompletionResultType]::ParameterName, 'Refine the solver''s assignments with local tile swaps and substitutions')
             [CompletionResult]::new('-v', '-v', [CompletionResultType]::ParameterName, 'Increase logging verbosity')
             [CompletionResult]::new('--verbose', '--verbose', [CompletionResultType]::ParameterName, 'Increase logging verbosity')
             [CompletionResult]::new('-q', '-q', [CompletionResultType]::ParameterName, 'Decrease logging verbosity')
@@ -19,12 +19,42 @@ _phomo() {
 
     case "${cmd}" in
         phomo)
-            opts="-g -n -v -q -h -V --grid-size --n-appearances --crop-tiles --resize-tiles --equalize --transfer-master-to-tiles --transfer-tiles-to-master --solver --metric --verbose --quiet --help --version <MASTER_FILE> <TILE_DIR> <OUTPUT>"
+            opts="-g -n -v -q -h -V --tile-weights --report --grid-size --cell-size --fit --pad-color --layout --quadtree-depth --quadtree-threshold --quadtree-detail --voronoi-seed --voronoi-edge-weight --mask --mask-color --n-appearances --min-appearances --repeat-radius --pin --crop-tiles --resize-tiles --equalize --transfer-master-to-tiles --transfer-tiles-to-master --solver --auction-epsilon --auction-scaling --auction-jacobi --seam-weight --repeat-penalty --repeat-penalty-radius --annealing-moves --refine --refine-budget --time-budget --seed --metric --verbose --quiet --help --version <MASTER_FILE> <TILE_DIR> <OUTPUT>"
             if [[ ${cur} == -* || ${COMP_CWORD} -eq 1 ]] ; then
                 COMPREPLY=( $(compgen -W "${opts}" -- "${cur}") )
                 return 0
             fi
             case "${prev}" in
+                --tile-weights)
+                    local oldifs
+                    if [ -n "${IFS+x}" ]; then
+                        oldifs="$IFS"
+                    fi
+                    IFS=$'\n'
+                    COMPREPLY=($(compgen -f "${cur}"))
+                    if [ -n "${oldifs+x}" ]; then
+                        IFS="$oldifs"
+                    fi
+                    if [[ "${BASH_VERSINFO[0]}" -ge 4 ]]; then
+                        compopt -o filenames
+                    fi
+                    return 0
+                    ;;
+                --report)
+                    local oldifs
+                    if [ -n "${IFS+x}" ]; then
+                        oldifs="$IFS"
+                    fi
+                    IFS=$'\n'
+                    COMPREPLY=($(compgen -f "${cur}"))
+                    if [ -n "${oldifs+x}" ]; then
+                        IFS="$oldifs"
+                    fi
+                    if [[ "${BASH_VERSINFO[0]}" -ge 4 ]]; then
+                        compopt -o filenames
+                    fi
+                    return 0
+                    ;;
                 --grid-size)
                     COMPREPLY=($(compgen -f "${cur}"))
                     return 0
@@ -33,6 +63,61 @@ _phomo() {
                     COMPREPLY=($(compgen -f "${cur}"))
                     return 0
                     ;;
+                --cell-size)
+                    COMPREPLY=($(compgen -f "${cur}"))
+                    return 0
+                    ;;
+                --fit)
+                    COMPREPLY=($(compgen -W "crop pad resize" -- "${cur}"))
+                    return 0
+                    ;;
+                --pad-color)
+                    COMPREPLY=($(compgen -f "${cur}"))
+                    return 0
+                    ;;
+                --layout)
+                    COMPREPLY=($(compgen -W "grid quadtree hexagonal brick voronoi" -- "${cur}"))
+                    return 0
+                    ;;
+                --quadtree-depth)
+                    COMPREPLY=($(compgen -f "${cur}"))
+                    return 0
+                    ;;
+                --quadtree-threshold)
+                    COMPREPLY=($(compgen -f "${cur}"))
+                    return 0
+                    ;;
+                --quadtree-detail)
+                    COMPREPLY=($(compgen -W "variance edge-energy" -- "${cur}"))
+                    return 0
+                    ;;
+                --voronoi-seed)
+                    COMPREPLY=($(compgen -f "${cur}"))
+                    return 0
+                    ;;
+                --voronoi-edge-weight)
+                    COMPREPLY=($(compgen -f "${cur}"))
+                    return 0
+                    ;;
+                --mask)
+                    local oldifs
+                    if [ -n "${IFS+x}" ]; then
+                        oldifs="$IFS"
+                    fi
+                    IFS=$'\n'
+                    COMPREPLY=($(compgen -f "${cur}"))
+                    if [ -n "${oldifs+x}" ]; then
+                        IFS="$oldifs"
+                    fi
+                    if [[ "${BASH_VERSINFO[0]}" -ge 4 ]]; then
+                        compopt -o filenames
+                    fi
+                    return 0
+                    ;;
+                --mask-color)
+                    COMPREPLY=($(compgen -f "${cur}"))
+                    return 0
+                    ;;
                 --n-appearances)
                     COMPREPLY=($(compgen -f "${cur}"))
                     return 0
@@ -41,12 +126,60 @@ _phomo() {
                     COMPREPLY=($(compgen -f "${cur}"))
                     return 0
                     ;;
+                --min-appearances)
+                    COMPREPLY=($(compgen -f "${cur}"))
+                    return 0
+                    ;;
+                --repeat-radius)
+                    COMPREPLY=($(compgen -f "${cur}"))
+                    return 0
+                    ;;
+                --pin)
+                    COMPREPLY=($(compgen -f "${cur}"))
+                    return 0
+                    ;;
                 --solver)
-                    COMPREPLY=($(compgen -W "greedy auction hungarian" -- "${cur}"))
+                    COMPREPLY=($(compgen -W "greedy auction hungarian lapjv min-cost-flow annealing" -- "${cur}"))
+                    return 0
+                    ;;
+                --auction-epsilon)
+                    COMPREPLY=($(compgen -f "${cur}"))
+                    return 0
+                    ;;
+                --auction-scaling)
+                    COMPREPLY=($(compgen -f "${cur}"))
+                    return 0
+                    ;;
+                --seam-weight)
+                    COMPREPLY=($(compgen -f "${cur}"))
+                    return 0
+                    ;;
+                --repeat-penalty)
+                    COMPREPLY=($(compgen -f "${cur}"))
+                    return 0
+                    ;;
+                --repeat-penalty-radius)
+                    COMPREPLY=($(compgen -f "${cur}"))
+                    return 0
+                    ;;
+                --annealing-moves)
+                    COMPREPLY=($(compgen -f "${cur}"))
+                    return 0
+                    ;;
+                --refine-budget)
+                    COMPREPLY=($(compgen -f "${cur}"))
+                    return 0
+                    ;;
+                --time-budget)
+                    COMPREPLY=($(compgen -f "${cur}"))
+                    return 0
+                    ;;
+                --seed)
+                    COMPREPLY=($(compgen -f "${cur}"))
                     return 0
                     ;;
                 --metric)
-                    COMPREPLY=($(compgen -W "norm-l1 norm-l2 avg-color luminance-l1 luminance-l2" -- "${cur}"))
+                    COMPREPLY=($(compgen -W "norm-l1 norm-l2 avg-color luminance-l1 luminance-l2 oklab ciede2000 avg-oklab avg-ciede2000 ssim gradient" -- "${cur}"))
                     return 0
                     ;;
                 *)
@@ -1,18 +1,61 @@
+complete -c phomo -l tile-weights -d 'CSV file mapping tile file names to weights, which are added to the distances of the tiles. Lower weights make a tile more likely to be used' -r -F
+complete -c phomo -l report -d 'Save a JSON report of the assignments, with their cost, the tile usage and the time taken by the solver. A summary of the report is logged with -vv' -r -F
 complete -c phomo -s g -l grid-size -d 'Grid size, the number of tiles along the width and height' -r
+complete -c phomo -l cell-size -d 'Cell size, the width and height of the tiles in pixels. The grid size is derived from it' -r
+complete -c phomo -l fit -d 'How the master image is fitted to the grid when its dimensions aren\'t multiples of the grid size. The master is centre cropped, padded around or resized' -r -f -a "crop\t''
+pad\t''
+resize\t''"
+complete -c phomo -l pad-color -d 'The colour of the padding of the master, its edges are extended when not provided' -r
+complete -c phomo -l layout -d 'The layout of the cells. The quadtree layout subdivides the detailed grid cells into quadrants, the tiles keep the size of the grid cells and are scaled down to the smaller cells. The hexagonal layout inscribes hexagons in the grid cells, in offset rows. The brick layout offsets every other row of grid cells by half a cell. The voronoi layout splits the master into as many polygons as there are grid cells, following its edges' -r -f -a "grid\t''
+quadtree\t''
+hexagonal\t''
+brick\t''
+voronoi\t''"
+complete -c phomo -l quadtree-depth -d 'The maximum number of times the quadtree layout subdivides a grid cell' -r
+complete -c phomo -l quadtree-threshold -d 'The cells whose detail exceeds this threshold are subdivided' -r
+complete -c phomo -l quadtree-detail -d 'The measure of the detail of the cells' -r -f -a "variance\t''
+edge-energy\t''"
+complete -c phomo -l voronoi-seed -d 'The seed of the random placement of the voronoi cells' -r
+complete -c phomo -l voronoi-edge-weight -d 'How much denser the voronoi cells are along the edges of the master image, 0 spreads them uniformly' -r
+complete -c phomo -l mask -d 'Mask image of the parts of the master to tile, the cells mostly outside of it are left untiled. Its alpha channel is used when it has one, its brightness otherwise' -r -F
+complete -c phomo -l mask-color -d 'Fill the untiled parts of the mosaic with this colour instead of the master image' -r
 complete -c phomo -s n -l n-appearances -d 'The number of times a tile can appear in the mosaic' -r
+complete -c phomo -l min-appearances -d 'The minimum number of times each tile must appear in the mosaic' -r
+complete -c phomo -l repeat-radius -d 'Don\'t repeat a tile within this many grid cells, horizontally and vertically, 0 disables it' -r
+complete -c phomo -l pin -d 'Pin a tile to a grid cell, the cells are indexed row by row and the tiles in the order they are read. Can be repeated' -r
 complete -c phomo -l solver -d 'The solver to use to compute the tile to cell assignments' -r -f -a "greedy\t''
 auction\t''
-hungarian\t''"
+hungarian\t''
+lapjv\t''
+min-cost-flow\t''
+annealing\t''"
+complete -c phomo -l auction-epsilon -d 'The final epsilon of the auction solver, smaller values give assignments closer to the optimal cost. The duality gap of the final prices, an upper bound of the distance to the optimal cost, is logged' -r
+complete -c phomo -l auction-scaling -d 'The factor by which the auction solver divides epsilon between runs, 1 disables epsilon scaling' -r
+complete -c phomo -l seam-weight -d 'The weight of the colour differences across the seams of neighbouring tiles in the objective of the annealing solver, 0 ignores the seams. Only used with the grid layout and without a mask' -r
+complete -c phomo -l repeat-penalty -d 'The penalty of each pair of identical tiles within the repeat penalty radius in the objective of the annealing solver' -r
+complete -c phomo -l repeat-penalty-radius -d 'The radius, in grid cells, within which the annealing solver penalises repeated tiles' -r
+complete -c phomo -l annealing-moves -d 'The number of moves per grid cell tried by the annealing solver' -r
+complete -c phomo -l refine-budget -d 'Stop refining the assignments after this many milliseconds' -r
+complete -c phomo -l time-budget -d 'Stop solving after this many milliseconds. The exact solvers which run out of time fall back to refined greedy assignments' -r
+complete -c phomo -l seed -d 'Break the ties between tiles of equal cost in a random order drawn from this seed, a different seed gives a different mosaic of the same cost' -r
 complete -c phomo -l metric -d 'The distance metric to use' -r -f -a "norm-l1\t''
 norm-l2\t''
 avg-color\t''
 luminance-l1\t''
-luminance-l2\t''"
+luminance-l2\t''
+oklab\t''
+ciede2000\t''
+avg-oklab\t''
+avg-ciede2000\t''
+ssim\t''
+gradient\t''"
 complete -c phomo -l crop-tiles -d 'Crop tiles to grid cell size'
 complete -c phomo -l resize-tiles -d 'Resize tiles to grid cell size'
 complete -c phomo -l equalize -d 'Equalize the master and tile image color distributions'
 complete -c phomo -l transfer-master-to-tiles -d 'Transfer the color palette of the master image to the tile images'
 complete -c phomo -l transfer-tiles-to-master -d 'Transfer the color palette of the tile images to the master image'
+complete -c phomo -l auction-jacobi -d 'Make all the unassigned cells bid at once in the auction solver, in parallel'
+complete -c phomo -l refine -d 'Refine the solver\'s assignments with local tile swaps and substitutions'
 complete -c phomo -s v -l verbose -d 'Increase logging verbosity'
 complete -c phomo -s q -l quiet -d 'Decrease logging verbosity'
 complete -c phomo -s h -l help -d 'Print help (see more with \'--help\')'
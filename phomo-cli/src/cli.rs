@@ -11,6 +11,12 @@ pub(crate) enum Metric {
     AvgColor,
     LuminanceL1,
     LuminanceL2,
+    Oklab,
+    Ciede2000,
+    AvgOklab,
+    AvgCiede2000,
+    Ssim,
+    Gradient,
 }
 
 impl Display for Metric {
@@ -21,6 +27,12 @@ impl Display for Metric {
             Metric::AvgColor => write!(f, "avg-color"),
             Metric::LuminanceL1 => write!(f, "luminance-l1"),
             Metric::LuminanceL2 => write!(f, "luminance-l2"),
+            Metric::Oklab => write!(f, "oklab"),
+            Metric::Ciede2000 => write!(f, "ciede2000"),
+            Metric::AvgOklab => write!(f, "avg-oklab"),
+            Metric::AvgCiede2000 => write!(f, "avg-ciede2000"),
+            Metric::Ssim => write!(f, "ssim"),
+            Metric::Gradient => write!(f, "gradient"),
         }
     }
 }
@@ -30,6 +42,9 @@ pub(crate) enum Solver {
     Greedy,
     Auction,
     Hungarian,
+    Lapjv,
+    MinCostFlow,
+    Annealing,
 }
 
 impl Display for Solver {
@@ -38,6 +53,62 @@ impl Display for Solver {
             Solver::Greedy => write!(f, "greedy"),
             Solver::Auction => write!(f, "auction"),
             Solver::Hungarian => write!(f, "hungarian"),
+            Solver::Lapjv => write!(f, "lapjv"),
+            Solver::MinCostFlow => write!(f, "min-cost-flow"),
+            Solver::Annealing => write!(f, "annealing"),
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Debug)]
+pub(crate) enum MasterFit {
+    Crop,
+    Pad,
+    Resize,
+}
+
+impl Display for MasterFit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MasterFit::Crop => write!(f, "crop"),
+            MasterFit::Pad => write!(f, "pad"),
+            MasterFit::Resize => write!(f, "resize"),
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Debug)]
+pub(crate) enum Layout {
+    Grid,
+    Quadtree,
+    Hexagonal,
+    Brick,
+    Voronoi,
+}
+
+impl Display for Layout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Layout::Grid => write!(f, "grid"),
+            Layout::Quadtree => write!(f, "quadtree"),
+            Layout::Hexagonal => write!(f, "hexagonal"),
+            Layout::Brick => write!(f, "brick"),
+            Layout::Voronoi => write!(f, "voronoi"),
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Debug)]
+pub(crate) enum QuadtreeDetail {
+    Variance,
+    EdgeEnergy,
+}
+
+impl Display for QuadtreeDetail {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QuadtreeDetail::Variance => write!(f, "variance"),
+            QuadtreeDetail::EdgeEnergy => write!(f, "edge-energy"),
         }
     }
 }
@@ -66,6 +137,25 @@ impl FromStr for TwoNumbers {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct Color(pub [u8; 3]);
+
+impl FromStr for Color {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s
+            .split(',')
+            .map(|part| part.trim().parse::<u8>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| "Invalid colour channel, expected a number between 0 and 255")?;
+        let channels: [u8; 3] = parts
+            .try_into()
+            .map_err(|_| "Expected three numbers separated by commas")?;
+        Ok(Color(channels))
+    }
+}
+
 #[derive(Parser, Debug)]
 #[command(version, author = "Loic Coyle")]
 #[command(group(
@@ -85,18 +175,81 @@ pub(crate) struct Arguments {
     /// Tile directory.
     #[arg(value_hint=clap::ValueHint::DirPath)]
     pub(crate) tile_dir: PathBuf,
+    /// CSV file mapping tile file names to weights, which are added to the distances of the
+    /// tiles. Lower weights make a tile more likely to be used.
+    #[arg(long, value_name = "CSV", value_hint=clap::ValueHint::FilePath)]
+    pub(crate) tile_weights: Option<PathBuf>,
     /// Output mosaic file.
     #[arg(value_hint=clap::ValueHint::FilePath)]
     pub(crate) output: PathBuf,
+    /// Save a JSON report of the assignments, with their cost, the tile usage and the time taken
+    /// by the solver. A summary of the report is logged with -vv.
+    #[arg(long, value_name = "JSON", value_hint=clap::ValueHint::FilePath)]
+    pub(crate) report: Option<PathBuf>,
 
     /// Grid size, the number of tiles along the width and height.
     ///
     /// If not provided, the grid size will be set to a sane value depending on the number of tiles images.
-    #[arg(short = 'g', long, value_name = "WIDTH,HEIGHT")]
+    #[arg(
+        short = 'g',
+        long,
+        value_name = "WIDTH,HEIGHT",
+        conflicts_with = "cell_size"
+    )]
     pub(crate) grid_size: Option<TwoNumbers>,
+    /// Cell size, the width and height of the tiles in pixels. The grid size is derived from it.
+    #[arg(long, value_name = "WIDTH,HEIGHT")]
+    pub(crate) cell_size: Option<TwoNumbers>,
+    /// How the master image is fitted to the grid when its dimensions aren't multiples of the
+    /// grid size. The master is centre cropped, padded around or resized.
+    #[arg(long, default_value_t = MasterFit::Crop)]
+    pub(crate) fit: MasterFit,
+    /// The colour of the padding of the master, its edges are extended when not provided.
+    #[arg(long, value_name = "R,G,B")]
+    pub(crate) pad_color: Option<Color>,
+    /// The layout of the cells. The quadtree layout subdivides the detailed grid cells into
+    /// quadrants, the tiles keep the size of the grid cells and are scaled down to the smaller
+    /// cells. The hexagonal layout inscribes hexagons in the grid cells, in offset rows. The
+    /// brick layout offsets every other row of grid cells by half a cell. The voronoi layout
+    /// splits the master into as many polygons as there are grid cells, following its edges.
+    #[arg(long, default_value_t = Layout::Grid)]
+    pub(crate) layout: Layout,
+    /// The maximum number of times the quadtree layout subdivides a grid cell.
+    #[arg(long, default_value_t = 2)]
+    pub(crate) quadtree_depth: u32,
+    /// The cells whose detail exceeds this threshold are subdivided.
+    #[arg(long, default_value_t = 400.0)]
+    pub(crate) quadtree_threshold: f64,
+    /// The measure of the detail of the cells.
+    #[arg(long, default_value_t = QuadtreeDetail::Variance)]
+    pub(crate) quadtree_detail: QuadtreeDetail,
+    /// The seed of the random placement of the voronoi cells.
+    #[arg(long, default_value_t = 0)]
+    pub(crate) voronoi_seed: u64,
+    /// How much denser the voronoi cells are along the edges of the master image, 0 spreads
+    /// them uniformly.
+    #[arg(long, default_value_t = 4.0)]
+    pub(crate) voronoi_edge_weight: f64,
+    /// Mask image of the parts of the master to tile, the cells mostly outside of it are left
+    /// untiled. Its alpha channel is used when it has one, its brightness otherwise.
+    #[arg(long, value_name = "FILE", value_hint=clap::ValueHint::FilePath)]
+    pub(crate) mask: Option<PathBuf>,
+    /// Fill the untiled parts of the mosaic with this colour instead of the master image.
+    #[arg(long, value_name = "R,G,B", requires = "mask")]
+    pub(crate) mask_color: Option<Color>,
     /// The number of times a tile can appear in the mosaic.
     #[arg(short = 'n', long, default_value_t = 1)]
     pub(crate) n_appearances: usize,
+    /// The minimum number of times each tile must appear in the mosaic.
+    #[arg(long, default_value_t = 0)]
+    pub(crate) min_appearances: usize,
+    /// Don't repeat a tile within this many grid cells, horizontally and vertically, 0 disables it.
+    #[arg(long, default_value_t = 0)]
+    pub(crate) repeat_radius: u32,
+    /// Pin a tile to a grid cell, the cells are indexed row by row and the tiles in the order
+    /// they are read. Can be repeated.
+    #[arg(long = "pin", value_name = "CELL,TILE")]
+    pub(crate) pins: Vec<TwoNumbers>,
     /// Crop tiles to grid cell size.
     #[arg(long)]
     pub(crate) crop_tiles: bool,
@@ -117,6 +270,44 @@ pub(crate) struct Arguments {
     /// The solver to use to compute the tile to cell assignments.
     #[arg(long, default_value_t = Solver::Hungarian)]
     pub(crate) solver: Solver,
+    /// The final epsilon of the auction solver, smaller values give assignments closer to the optimal cost. The duality gap of the final prices, an upper bound of the distance to the optimal cost, is logged.
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(i64).range(1..))]
+    pub(crate) auction_epsilon: i64,
+    /// The factor by which the auction solver divides epsilon between runs, 1 disables epsilon scaling.
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(i64).range(1..))]
+    pub(crate) auction_scaling: i64,
+    /// Make all the unassigned cells bid at once in the auction solver, in parallel.
+    #[arg(long)]
+    pub(crate) auction_jacobi: bool,
+    /// The weight of the colour differences across the seams of neighbouring tiles in the
+    /// objective of the annealing solver, 0 ignores the seams. Only used with the grid layout
+    /// and without a mask.
+    #[arg(long, default_value_t = 1)]
+    pub(crate) seam_weight: i64,
+    /// The penalty of each pair of identical tiles within the repeat penalty radius in the
+    /// objective of the annealing solver.
+    #[arg(long, default_value_t = 0)]
+    pub(crate) repeat_penalty: i64,
+    /// The radius, in grid cells, within which the annealing solver penalises repeated tiles.
+    #[arg(long, default_value_t = 1)]
+    pub(crate) repeat_penalty_radius: u32,
+    /// The number of moves per grid cell tried by the annealing solver.
+    #[arg(long, default_value_t = 200)]
+    pub(crate) annealing_moves: usize,
+    /// Refine the solver's assignments with local tile swaps and substitutions.
+    #[arg(long)]
+    pub(crate) refine: bool,
+    /// Stop refining the assignments after this many milliseconds.
+    #[arg(long, requires = "refine")]
+    pub(crate) refine_budget: Option<u64>,
+    /// Stop solving after this many milliseconds. The exact solvers which run out of time fall
+    /// back to refined greedy assignments.
+    #[arg(long)]
+    pub(crate) time_budget: Option<u64>,
+    /// Break the ties between tiles of equal cost in a random order drawn from this seed, a
+    /// different seed gives a different mosaic of the same cost.
+    #[arg(long)]
+    pub(crate) seed: Option<u64>,
     /// The distance metric to use.
     #[arg(long, default_value_t = Metric::NormL1)]
     pub(crate) metric: Metric,
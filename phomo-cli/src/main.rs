@@ -1,15 +1,42 @@
 use std::error::Error;
+use std::time::Duration;
 
 extern crate env_logger;
 use clap::Parser;
+use image::imageops::{self, FilterType};
 use log::info;
+use phomo::error::{MasterError, PhomoError};
+use phomo::solvers::auction::Bidding;
+use phomo::utils::crop_cover;
 use phomo::{
-    read_images_from_dir, read_images_from_dir_cropped, read_images_from_dir_resized, Auction,
-    ColorMatch, Greedy, Hungarian, Mosaic, SolverConfig,
+    read_named_images_from_dir, read_tile_weights, Auction, Background, ColorMatch, Detail,
+    DistanceMatrix, Fit, Greedy, Hungarian, Lapjv, Layout, LocalSearch, Master, Metric,
+    MinCostFlow, Mosaic, Objective, Padding, Quadtree, Refined, Schedule, Solve, SolveReport,
+    SolverConfig, Spaced, Spacing, TileEdges, Voronoi,
 };
 
 mod cli;
 
+/// Build the mosaic image with the `solver`, refining its assignments when a `local_search` is
+/// given, then removing the repeats within the radius of the `spacing`.
+fn build<S: Solve>(
+    mosaic: &Mosaic,
+    d_matrix: DistanceMatrix,
+    solver: S,
+    local_search: Option<LocalSearch>,
+    spacing: Spacing,
+) -> Result<(image::RgbImage, SolveReport), PhomoError> {
+    let report = match local_search {
+        Some(local_search) => d_matrix.report(&mut Spaced::new(
+            Refined::new(solver, local_search),
+            spacing,
+        )),
+        None => d_matrix.report(&mut Spaced::new(solver, spacing)),
+    }?;
+    let mosaic_img = mosaic.render(report.assignments.clone())?;
+    Ok((mosaic_img, report))
+}
+
 fn main() -> Result<(), Box<dyn Error>> {
     // Parse the CLI arguments
     let args = cli::Arguments::parse();
@@ -26,26 +53,30 @@ fn main() -> Result<(), Box<dyn Error>> {
         master_img.height()
     );
 
-    let tile_count = args
-        .tile_dir
-        .read_dir()?
-        .filter_map(|entry| {
-            let entry = entry.ok()?; // Skip invalid entries
-            let path = entry.path();
-            if path.is_file() {
-                // Try to get the image dimensions, returns None if it's not an image
-                image::image_dimensions(&path).ok()
-            } else {
-                None
-            }
-        })
-        .count();
+    // Read tile images from the directory, with their file names
+    let (tile_names, tile_imgs): (Vec<_>, Vec<_>) = read_named_images_from_dir(&args.tile_dir)
+        .map_err(|e| format!("Failed to read tile images: {}", e))?
+        .into_iter()
+        .unzip();
+    let tile_count = tile_imgs.len();
     info!("Tile count: {}", tile_count);
 
+    let fit = match args.fit {
+        cli::MasterFit::Crop => Fit::Crop,
+        cli::MasterFit::Pad => Fit::Pad(match args.pad_color {
+            Some(cli::Color(color)) => Padding::Color(color),
+            None => Padding::Edge,
+        }),
+        cli::MasterFit::Resize => Fit::Resize,
+    };
+
     // determine a resonable grid size
-    let (grid_width, grid_height) = match args.grid_size {
-        Some(cli::TwoNumbers(width, height)) => (width, height),
-        None => {
+    let (grid_width, grid_height) = match (&args.grid_size, &args.cell_size) {
+        (Some(cli::TwoNumbers(width, height)), _) => (*width, *height),
+        (None, Some(cli::TwoNumbers(width, height))) => {
+            fit.grid_size(master_img.dimensions(), (*width, *height))
+        }
+        (None, None) => {
             // we leave a few tiles to be unused so get better assignments
             let grid_dim = (tile_count as f32 * 0.8).sqrt().round() as u32;
             (grid_dim, grid_dim)
@@ -53,26 +84,31 @@ fn main() -> Result<(), Box<dyn Error>> {
     };
     info!("Grid size: {}x{}", grid_width, grid_height);
 
-    let (cell_width, cell_height) = (
-        master_img.width() / grid_width,
-        master_img.height() / grid_height,
-    );
+    let (cell_width, cell_height) = match args.cell_size {
+        Some(cli::TwoNumbers(width, height)) => (width, height),
+        None => fit.cell_size(master_img.dimensions(), (grid_width, grid_height)),
+    };
     info!("Cell size: {}x{}", cell_width, cell_height);
+    if cell_width == 0 || cell_height == 0 {
+        let e = PhomoError::from(MasterError::InvalidCellSize {
+            cell_size: (cell_width, cell_height),
+        });
+        return Err(format!("Failed to create master: {}", e).into());
+    }
 
-    // Read tile images from the directory
     let mut tile_imgs = if args.crop_tiles {
-        read_images_from_dir_cropped(&args.tile_dir, cell_width, cell_height)
+        tile_imgs
+            .iter()
+            .map(|img| crop_cover(img, cell_width, cell_height, FilterType::Nearest))
+            .collect()
     } else if args.resize_tiles {
-        read_images_from_dir_resized(
-            &args.tile_dir,
-            cell_width,
-            cell_height,
-            image::imageops::FilterType::Nearest,
-        )
+        tile_imgs
+            .iter()
+            .map(|img| imageops::resize(img, cell_width, cell_height, FilterType::Nearest))
+            .collect()
     } else {
-        read_images_from_dir(&args.tile_dir)
-    }
-    .map_err(|e| format!("Failed to crop tile images: {}", e))?;
+        tile_imgs
+    };
 
     // Handle the different transformations
     if args.equalize {
@@ -97,31 +133,170 @@ fn main() -> Result<(), Box<dyn Error>> {
         .into());
     };
 
+    let layout = match args.layout {
+        cli::Layout::Grid => Layout::Grid,
+        cli::Layout::Quadtree => Layout::Quadtree(Quadtree {
+            max_depth: args.quadtree_depth,
+            threshold: args.quadtree_threshold,
+            detail: match args.quadtree_detail {
+                cli::QuadtreeDetail::Variance => Detail::Variance,
+                cli::QuadtreeDetail::EdgeEnergy => Detail::EdgeEnergy,
+            },
+        }),
+        cli::Layout::Hexagonal => Layout::Hexagonal,
+        cli::Layout::Brick => Layout::Brick,
+        cli::Layout::Voronoi => Layout::Voronoi(Voronoi {
+            seed: args.voronoi_seed,
+            edge_weight: args.voronoi_edge_weight,
+        }),
+    };
+
     // Create the mosaic
-    let mosaic = Mosaic::from_images(master_img, tile_imgs, (grid_width, grid_height))
+    let mut master = if args.cell_size.is_some() {
+        Master::from_image_with_cell_size(master_img, (cell_width, cell_height), layout, fit)
+    } else {
+        Master::from_image_with_fit(master_img, (grid_width, grid_height), layout, fit)
+    }
+    .map_err(|e| format!("Failed to create master: {}", e))?;
+    if let Some(mask_file) = &args.mask {
+        let mask = image::open(mask_file).map_err(|e| format!("Failed to read mask: {}", e))?;
+        master = master
+            .with_mask(&mask)
+            .map_err(|e| format!("Failed to apply mask: {}", e))?;
+    }
+    let mut mosaic = Mosaic::new(master, tile_imgs, (grid_width, grid_height))
         .map_err(|e| format!("Failed to create mosaic: {}", e))?;
+    if let Some(cli::Color(color)) = args.mask_color {
+        mosaic.background = Background::Color(color);
+    }
+
+    if let Some(tile_weights_file) = args.tile_weights {
+        let weights = read_tile_weights(tile_weights_file)
+            .map_err(|e| format!("Failed to read tile weights: {}", e))?;
+        let tile_weights = tile_names
+            .iter()
+            .map(|name| weights.get(name).copied().unwrap_or(0))
+            .collect();
+        info!(
+            "Tile weights: {} of {} tiles weighted",
+            tile_names
+                .iter()
+                .filter(|name| weights.contains_key(*name))
+                .count(),
+            tile_count
+        );
+        mosaic
+            .set_tile_weights(tile_weights)
+            .map_err(|e| format!("Failed to set tile weights: {}", e))?;
+    }
 
-    let metric = match args.metric {
-        cli::Metric::NormL1 => phomo::metrics::norm_l1,
-        cli::Metric::NormL2 => phomo::metrics::norm_l2,
-        cli::Metric::AvgColor => phomo::metrics::avg_color,
-        cli::Metric::LuminanceL1 => phomo::metrics::luminance_l1,
-        cli::Metric::LuminanceL2 => phomo::metrics::luminance_l2,
+    let metric: Box<dyn Metric> = match args.metric {
+        cli::Metric::NormL1 => Box::new(phomo::metrics::norm_l1),
+        cli::Metric::NormL2 => Box::new(phomo::metrics::norm_l2),
+        cli::Metric::AvgColor => Box::new(phomo::metrics::avg_color),
+        cli::Metric::LuminanceL1 => Box::new(phomo::metrics::luminance_l1),
+        cli::Metric::LuminanceL2 => Box::new(phomo::metrics::luminance_l2),
+        cli::Metric::Oklab => Box::new(phomo::metrics::oklab),
+        cli::Metric::Ciede2000 => Box::new(phomo::metrics::ciede2000),
+        cli::Metric::AvgOklab => Box::new(phomo::metrics::avg_oklab),
+        cli::Metric::AvgCiede2000 => Box::new(phomo::metrics::avg_ciede2000),
+        cli::Metric::Ssim => Box::new(phomo::metrics::ssim),
+        cli::Metric::Gradient => Box::new(phomo::metrics::gradient),
     };
     // Compute the distance matrix
     let d_matrix = mosaic.distance_matrix_with_metric(metric);
 
     let solver_config = SolverConfig {
         max_tile_occurrences: args.n_appearances,
+        min_tile_occurrences: args.min_appearances,
+        pinned: args
+            .pins
+            .iter()
+            .map(|&cli::TwoNumbers(cell, tile)| (cell as usize, tile as usize))
+            .collect(),
+        time_budget: args.time_budget.map(Duration::from_millis),
+        seed: args.seed,
+        ..Default::default()
     };
+    let spacing = mosaic.spacing(solver_config.clone(), args.repeat_radius);
+    let local_search = args.refine.then(|| {
+        LocalSearch::new(
+            solver_config.clone(),
+            args.refine_budget.map(Duration::from_millis),
+        )
+    });
     // Build the mosaic image
-    let mosaic_img = match args.solver {
-        cli::Solver::Greedy => mosaic.build_with_solver(d_matrix, Greedy::new(solver_config)),
-        cli::Solver::Hungarian => mosaic.build_with_solver(d_matrix, Hungarian::new(solver_config)),
-        cli::Solver::Auction => mosaic.build_with_solver(d_matrix, Auction::new(1, solver_config)),
+    let (mosaic_img, report) = match args.solver {
+        cli::Solver::Greedy => build(
+            &mosaic,
+            d_matrix,
+            Greedy::new(solver_config),
+            local_search,
+            spacing,
+        ),
+        cli::Solver::Hungarian => build(
+            &mosaic,
+            d_matrix,
+            Hungarian::new(solver_config),
+            local_search,
+            spacing,
+        ),
+        cli::Solver::Auction => {
+            let auction = if args.auction_scaling > 1 {
+                let bidding = if args.auction_jacobi {
+                    Bidding::Jacobi
+                } else {
+                    Bidding::GaussSeidel
+                };
+                Auction::with_epsilon_scaling(
+                    args.auction_epsilon,
+                    args.auction_scaling,
+                    bidding,
+                    solver_config,
+                )
+            } else {
+                Auction::new(args.auction_epsilon, solver_config)
+            };
+            build(&mosaic, d_matrix, auction, local_search, spacing)
+        }
+        cli::Solver::Lapjv => build(
+            &mosaic,
+            d_matrix,
+            Lapjv::new(solver_config),
+            local_search,
+            spacing,
+        ),
+        cli::Solver::MinCostFlow => build(
+            &mosaic,
+            d_matrix,
+            MinCostFlow::new(solver_config),
+            local_search,
+            spacing,
+        ),
+        cli::Solver::Annealing => {
+            let objective = Objective {
+                edges: (args.seam_weight != 0).then(|| TileEdges::new(&mosaic.tiles)),
+                seam_weight: args.seam_weight,
+                repeat_penalty: args.repeat_penalty,
+                repeat_radius: args.repeat_penalty_radius,
+            };
+            let schedule = Schedule {
+                moves_per_cell: args.annealing_moves,
+                ..Default::default()
+            };
+            let annealing = mosaic.annealing(solver_config, objective, schedule);
+            build(&mosaic, d_matrix, annealing, local_search, spacing)
+        }
     }
     .map_err(|e| format!("Failed to build mosaic image: {}", e))?;
 
+    info!("Solve report: {}", report);
+    if let Some(report_file) = args.report {
+        std::fs::write(&report_file, serde_json::to_string_pretty(&report)?)
+            .map_err(|e| format!("Failed to save solve report: {}", e))?;
+        info!("Solve report saved: {:?}", report_file);
+    }
+
     // Save the final mosaic image to output
     mosaic_img
         .save(&args.output)
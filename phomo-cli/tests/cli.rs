@@ -121,6 +121,177 @@ fn build_mosaic_auction() {
     assert!(check_expected(output_file.path(), expected_file));
 }
 
+#[test]
+fn build_mosaic_annealing() {
+    let output_file = assert_fs::NamedTempFile::new("output.png").unwrap();
+    let expected_file = test_data_dir().join("mosaic_annealing.png");
+
+    let mut cmd = assert_cmd::Command::cargo_bin("phomo").unwrap();
+    cmd.arg(master_img_file().to_str().unwrap());
+    cmd.arg(tile_dir().to_str().unwrap());
+    cmd.arg(output_file.path().to_str().unwrap());
+    cmd.arg("--resize-tiles");
+    cmd.arg("--solver=annealing");
+    cmd.arg("--repeat-penalty=1000");
+    cmd.arg("--seed=1");
+
+    cmd.assert().success();
+    assert!(output_file.path().exists());
+    assert!(check_expected(output_file.path(), expected_file));
+}
+
+#[test]
+fn build_mosaic_quadtree() {
+    let output_file = assert_fs::NamedTempFile::new("output.png").unwrap();
+    let expected_file = test_data_dir().join("mosaic_quadtree.png");
+
+    let mut cmd = assert_cmd::Command::cargo_bin("phomo").unwrap();
+    cmd.arg(master_img_file().to_str().unwrap());
+    cmd.arg(tile_dir().to_str().unwrap());
+    cmd.arg(output_file.path().to_str().unwrap());
+    cmd.arg("--resize-tiles");
+    cmd.arg("--grid-size=8,8");
+    cmd.arg("--layout=quadtree");
+    cmd.arg("--quadtree-depth=1");
+    cmd.arg("--solver=greedy");
+
+    cmd.assert().success();
+    assert!(output_file.path().exists());
+    assert!(check_expected(output_file.path(), expected_file));
+}
+
+#[test]
+fn build_mosaic_hexagonal() {
+    let output_file = assert_fs::NamedTempFile::new("output.png").unwrap();
+    let expected_file = test_data_dir().join("mosaic_hexagonal.png");
+
+    let mut cmd = assert_cmd::Command::cargo_bin("phomo").unwrap();
+    cmd.arg(master_img_file().to_str().unwrap());
+    cmd.arg(tile_dir().to_str().unwrap());
+    cmd.arg(output_file.path().to_str().unwrap());
+    cmd.arg("--resize-tiles");
+    cmd.arg("--grid-size=12,12");
+    cmd.arg("--layout=hexagonal");
+    cmd.arg("--solver=greedy");
+
+    cmd.assert().success();
+    assert!(output_file.path().exists());
+    assert!(check_expected(output_file.path(), expected_file));
+}
+
+#[test]
+fn build_mosaic_voronoi() {
+    let output_file = assert_fs::NamedTempFile::new("output.png").unwrap();
+    let expected_file = test_data_dir().join("mosaic_voronoi.png");
+
+    let mut cmd = assert_cmd::Command::cargo_bin("phomo").unwrap();
+    cmd.arg(master_img_file().to_str().unwrap());
+    cmd.arg(tile_dir().to_str().unwrap());
+    cmd.arg(output_file.path().to_str().unwrap());
+    cmd.arg("--resize-tiles");
+    cmd.arg("--grid-size=12,12");
+    cmd.arg("--layout=voronoi");
+    cmd.arg("--voronoi-seed=1");
+    cmd.arg("--solver=greedy");
+
+    cmd.assert().success();
+    assert!(output_file.path().exists());
+    assert!(check_expected(output_file.path(), expected_file));
+}
+
+#[test]
+fn build_mosaic_mask() {
+    let output_file = assert_fs::NamedTempFile::new("output.png").unwrap();
+    let expected_file = test_data_dir().join("mosaic_mask.png");
+
+    let mut cmd = assert_cmd::Command::cargo_bin("phomo").unwrap();
+    cmd.arg(master_img_file().to_str().unwrap());
+    cmd.arg(tile_dir().to_str().unwrap());
+    cmd.arg(output_file.path().to_str().unwrap());
+    cmd.arg("--resize-tiles");
+    cmd.arg("--grid-size=12,12");
+    cmd.arg("--mask");
+    cmd.arg(test_data_dir().join("mask.png").to_str().unwrap());
+    cmd.arg("--mask-color=255,255,255");
+    cmd.arg("--solver=greedy");
+
+    cmd.assert().success();
+    assert!(output_file.path().exists());
+    assert!(check_expected(output_file.path(), expected_file));
+}
+
+#[test]
+fn build_mosaic_padded() {
+    let output_file = assert_fs::NamedTempFile::new("output.png").unwrap();
+    let expected_file = test_data_dir().join("mosaic_padded.png");
+
+    let mut cmd = assert_cmd::Command::cargo_bin("phomo").unwrap();
+    cmd.arg(master_img_file().to_str().unwrap());
+    cmd.arg(tile_dir().to_str().unwrap());
+    cmd.arg(output_file.path().to_str().unwrap());
+    cmd.arg("--resize-tiles");
+    cmd.arg("--grid-size=10,10");
+    cmd.arg("--fit=pad");
+    cmd.arg("--pad-color=0,0,0");
+    cmd.arg("--solver=greedy");
+
+    cmd.assert().success();
+    assert!(output_file.path().exists());
+    assert_eq!(
+        image::open(output_file.path())
+            .unwrap()
+            .to_rgb8()
+            .dimensions(),
+        (260, 260)
+    );
+    assert!(check_expected(output_file.path(), expected_file));
+}
+
+#[test]
+fn build_mosaic_cell_size() {
+    let output_file = assert_fs::NamedTempFile::new("output.png").unwrap();
+    let expected_file = test_data_dir().join("mosaic_cell_size.png");
+
+    let mut cmd = assert_cmd::Command::cargo_bin("phomo").unwrap();
+    cmd.arg(master_img_file().to_str().unwrap());
+    cmd.arg(tile_dir().to_str().unwrap());
+    cmd.arg(output_file.path().to_str().unwrap());
+    cmd.arg("--resize-tiles");
+    cmd.arg("--cell-size=24,24");
+    cmd.arg("--fit=resize");
+    cmd.arg("--solver=greedy");
+
+    cmd.assert().success();
+    assert!(output_file.path().exists());
+    assert_eq!(
+        image::open(output_file.path())
+            .unwrap()
+            .to_rgb8()
+            .dimensions(),
+        (264, 264)
+    );
+    assert!(check_expected(output_file.path(), expected_file));
+}
+
+#[test]
+fn build_mosaic_brick() {
+    let output_file = assert_fs::NamedTempFile::new("output.png").unwrap();
+    let expected_file = test_data_dir().join("mosaic_brick.png");
+
+    let mut cmd = assert_cmd::Command::cargo_bin("phomo").unwrap();
+    cmd.arg(master_img_file().to_str().unwrap());
+    cmd.arg(tile_dir().to_str().unwrap());
+    cmd.arg(output_file.path().to_str().unwrap());
+    cmd.arg("--resize-tiles");
+    cmd.arg("--grid-size=12,12");
+    cmd.arg("--layout=brick");
+    cmd.arg("--solver=greedy");
+
+    cmd.assert().success();
+    assert!(output_file.path().exists());
+    assert!(check_expected(output_file.path(), expected_file));
+}
+
 #[test]
 fn build_mosaic_equalized() {
     let output_file = assert_fs::NamedTempFile::new("output.png").unwrap();
@@ -202,6 +373,22 @@ fn build_mosaic_bad_grid_size() {
     cmd.assert().failure();
 }
 
+#[test]
+fn build_mosaic_zero_cell_size() {
+    let output_file = assert_fs::NamedTempFile::new("output.png").unwrap();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("phomo").unwrap();
+    cmd.arg(master_img_file().to_str().unwrap());
+    cmd.arg(tile_dir().to_str().unwrap());
+    cmd.arg(output_file.path().to_str().unwrap());
+    cmd.arg("--resize-tiles");
+    cmd.arg("--cell-size=0,10");
+
+    let assert = cmd.assert().failure();
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr);
+    assert!(stderr.contains("Invalid cell size"), "{stderr}");
+}
+
 #[test]
 fn build_mosaic_wrong_tile_size() {
     let output_file = assert_fs::NamedTempFile::new("output.png").unwrap();
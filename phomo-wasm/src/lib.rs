@@ -1,11 +1,14 @@
 use base64::{engine::general_purpose, Engine as _};
 use image::RgbImage;
+use phomo::solvers::auction::Bidding;
 use phomo::{
-    metrics, utils, Auction, Blueprint, ColorMatch, Greedy, Hungarian, Master as MasterRs,
-    Mosaic as MosaicRs,
+    metrics, utils, Annealing, Auction, Blueprint, ColorMatch, Detail, Fit, Greedy, Hungarian,
+    Lapjv, Layout, Master as MasterRs, Metric, MinCostFlow, Mosaic as MosaicRs, Objective, Padding,
+    Quadtree, Schedule, TileEdges, Voronoi,
 };
-use phomo::{DistanceMatrix, SolverConfig};
+use phomo::{CancellationToken, DistanceMatrix, Monitor, Progress, Solve, SolverConfig, Spaced};
 use std::io::Cursor;
+use std::time::Duration;
 use wasm_bindgen::prelude::*;
 extern crate wasm_logger;
 
@@ -23,6 +26,12 @@ pub enum MetricType {
     LuminanceL1,
     LuminanceL2,
     AvgColor,
+    Oklab,
+    Ciede2000,
+    AvgOklab,
+    AvgCiede2000,
+    Ssim,
+    Gradient,
 }
 
 /// Tile resizing types
@@ -32,12 +41,72 @@ pub enum ResizeType {
     Resize,
 }
 
+/// Master fitting types, when its dimensions aren't multiples of the grid size.
+#[wasm_bindgen]
+pub enum MasterFit {
+    Crop,
+    Pad,
+    Resize,
+}
+
+/// The [`Fit`] of the master, padded with the `pad_color` or by extending its edges.
+fn fit(master_fit: Option<MasterFit>, pad_color: Option<Vec<u8>>) -> Result<Fit, JsValue> {
+    Ok(match master_fit {
+        Some(MasterFit::Crop) | None => Fit::Crop,
+        Some(MasterFit::Pad) => Fit::Pad(match pad_color {
+            Some(color) => Padding::Color(
+                color
+                    .try_into()
+                    .map_err(|_| JsValue::from("The pad colour should have 3 channels"))?,
+            ),
+            None => Padding::Edge,
+        }),
+        Some(MasterFit::Resize) => Fit::Resize,
+    })
+}
+
 /// Tile to cell solvers.
 #[wasm_bindgen]
 pub enum Solver {
     Greedy,
     Hungarian,
     Auction,
+    Lapjv,
+    MinCostFlow,
+    Annealing,
+}
+
+/// Measures of the detail of the quadtree cells.
+#[wasm_bindgen]
+pub enum QuadtreeDetail {
+    Variance,
+    EdgeEnergy,
+}
+
+/// Forwards the progress to a JS callback, which cancels the mosaic generation by returning
+/// `false`.
+struct JsProgress {
+    callback: js_sys::Function,
+    cancellation: CancellationToken,
+}
+
+// SAFETY: wasm32 builds without the `parallel` feature are single threaded, so the JS callback is
+// never shared between threads.
+unsafe impl Send for JsProgress {}
+unsafe impl Sync for JsProgress {}
+
+impl Progress for JsProgress {
+    fn update(&self, step: &str, done: usize, total: usize) {
+        let result = self.callback.call3(
+            &JsValue::NULL,
+            &JsValue::from(step),
+            &JsValue::from(done),
+            &JsValue::from(total),
+        );
+        if result.is_ok_and(|value| value == JsValue::FALSE) {
+            self.cancellation.cancel();
+        }
+    }
 }
 
 /// Overlay a grid on the master image
@@ -46,12 +115,19 @@ pub fn overlay_grid(
     master_img_data: &[u8],
     grid_width: u32,
     grid_height: u32,
+    master_fit: Option<MasterFit>,
+    pad_color: Option<Vec<u8>>,
 ) -> Result<String, JsValue> {
     let master_img = image::load_from_memory(master_img_data)
         .map_err(|err| JsValue::from(err.to_string()))?
         .to_rgb8();
-    let master = MasterRs::from_image(master_img, (grid_width, grid_height))
-        .map_err(|err| JsValue::from(err.to_string()))?;
+    let master = MasterRs::from_image_with_fit(
+        master_img,
+        (grid_width, grid_height),
+        Layout::Grid,
+        fit(master_fit, pad_color)?,
+    )
+    .map_err(|err| JsValue::from(err.to_string()))?;
     let grid = master
         .overlay_grid()
         .map_err(|err| JsValue::from(err.to_string()))?;
@@ -64,6 +140,11 @@ pub fn overlay_grid(
 pub struct Mosaic {
     inner: MosaicRs,
     solver_config: SolverConfig,
+    repeat_radius: u32,
+    auction_epsilon: i64,
+    auction_scaling_factor: i64,
+    annealing_objective: Objective,
+    annealing_schedule: Schedule,
 }
 
 #[wasm_bindgen]
@@ -78,6 +159,11 @@ impl Mosaic {
     /// - `max_tile_occurrences`: The maximum number of times a tile can be used in the mosaic.
     /// - `tile_resize`: The type of tile resizing to apply.
     /// - 'master_resize`: The desired size of the master image, useful for upsacling.
+    /// - `master_fit`: How the master is fitted to the grid, cropped by default.
+    /// - `pad_color`: The RGB colour of the padding of the master, its edges are extended when
+    ///   not provided.
+    // The trailing arguments are optional on the JS side
+    #[allow(clippy::too_many_arguments)]
     #[wasm_bindgen(constructor)]
     pub fn new(
         master_img_data: &[u8],
@@ -87,6 +173,8 @@ impl Mosaic {
         max_tile_occurrences: usize,
         tile_resize: Option<ResizeType>,
         master_resize: Option<Vec<u32>>,
+        master_fit: Option<MasterFit>,
+        pad_color: Option<Vec<u8>>,
     ) -> Result<Mosaic, JsValue> {
         // Load master image
         let mut master_img = image::load_from_memory(master_img_data)
@@ -102,8 +190,13 @@ impl Mosaic {
             );
         }
 
-        let master = MasterRs::from_image(master_img, (grid_width, grid_height))
-            .map_err(|err| JsValue::from(err.to_string()))?;
+        let master = MasterRs::from_image_with_fit(
+            master_img,
+            (grid_width, grid_height),
+            Layout::Grid,
+            fit(master_fit, pad_color)?,
+        )
+        .map_err(|err| JsValue::from(err.to_string()))?;
         let (cell_width, cell_height) = master.cell_size;
         // convert js data to rust data
         let tile_data: Vec<Vec<u8>> = tile_imgs_data
@@ -144,10 +237,219 @@ impl Mosaic {
             inner: mosaic,
             solver_config: SolverConfig {
                 max_tile_occurrences,
+                ..Default::default()
             },
+            repeat_radius: 0,
+            auction_epsilon: 1,
+            auction_scaling_factor: 1,
+            annealing_objective: Objective::default(),
+            annealing_schedule: Schedule::default(),
         })
     }
 
+    /// Set the epsilon parameters of the auction solver.
+    ///
+    /// # Arguments
+    /// - `epsilon`: The final epsilon, smaller values give assignments closer to the optimal cost, see `Auction::optimality_gap` for the bound of the distance to the optimal cost.
+    /// - `scaling_factor`: The factor by which epsilon is divided between runs, 1 disables epsilon scaling.
+    #[wasm_bindgen(js_name = setAuctionEpsilon)]
+    pub fn set_auction_epsilon(&mut self, epsilon: i64, scaling_factor: i64) {
+        self.auction_epsilon = epsilon.max(1);
+        self.auction_scaling_factor = scaling_factor.max(1);
+    }
+
+    /// Set the parameters of the annealing solver.
+    ///
+    /// # Arguments
+    /// - `seam_weight`: The weight of the colour differences across the seams of neighbouring tiles, 0 ignores the seams. Only used with the grid layout.
+    /// - `repeat_penalty`: The penalty of each pair of identical tiles within the `repeat_radius`.
+    /// - `repeat_radius`: The radius, in grid cells, within which repeated tiles are penalised.
+    /// - `moves_per_cell`: The number of moves tried per grid cell.
+    #[wasm_bindgen(js_name = setAnnealing)]
+    pub fn set_annealing(
+        &mut self,
+        seam_weight: i64,
+        repeat_penalty: i64,
+        repeat_radius: u32,
+        moves_per_cell: usize,
+    ) {
+        self.annealing_objective.seam_weight = seam_weight;
+        self.annealing_objective.repeat_penalty = repeat_penalty;
+        self.annealing_objective.repeat_radius = repeat_radius;
+        self.annealing_schedule.moves_per_cell = moves_per_cell;
+    }
+
+    /// Set the radius, in grid cells, within which a tile can't be repeated, 0 disables it.
+    #[wasm_bindgen(js_name = setRepeatRadius)]
+    pub fn set_repeat_radius(&mut self, repeat_radius: u32) {
+        self.repeat_radius = repeat_radius;
+    }
+
+    /// Set the minimum number of times each tile must be used in the mosaic.
+    #[wasm_bindgen(js_name = setMinTileOccurrences)]
+    pub fn set_min_tile_occurrences(&mut self, min_tile_occurrences: usize) {
+        self.solver_config.min_tile_occurrences = min_tile_occurrences;
+    }
+
+    /// Pin the tile at `tile_index` to the cell at `cell_index`, the cells being indexed row by
+    /// row. The pinned tiles count against the maximum tile occurrences.
+    #[wasm_bindgen(js_name = pinTile)]
+    pub fn pin_tile(&mut self, cell_index: usize, tile_index: usize) {
+        self.solver_config
+            .pinned
+            .retain(|&(cell, _)| cell != cell_index);
+        self.solver_config.pinned.push((cell_index, tile_index));
+    }
+
+    /// Set the seed of the order in which the ties between tiles of equal cost are broken, a
+    /// different seed gives a different mosaic of the same cost. `undefined` breaks the ties in
+    /// the order of the tiles.
+    #[wasm_bindgen(js_name = setSeed)]
+    pub fn set_seed(&mut self, seed: Option<u64>) {
+        self.solver_config.seed = seed;
+    }
+
+    /// Set the maximum time spent by the solvers, in milliseconds, `undefined` to solve until
+    /// completion. The exact solvers which run out of time fall back to refined greedy
+    /// assignments, which makes for quick previews of large mosaics.
+    #[wasm_bindgen(js_name = setTimeBudget)]
+    pub fn set_time_budget(&mut self, milliseconds: Option<u32>) {
+        self.solver_config.time_budget = milliseconds.map(|ms| Duration::from_millis(ms as u64));
+    }
+
+    /// Remove all the pinned tiles.
+    #[wasm_bindgen(js_name = clearPins)]
+    pub fn clear_pins(&mut self) {
+        self.solver_config.pinned.clear();
+    }
+
+    /// Set the callback receiving the progress of the mosaic generation, it is called with the
+    /// step name, the number of completed units of work and the total number of units of work.
+    /// The generation is cancelled when the callback returns `false`.
+    #[wasm_bindgen(js_name = setProgressCallback)]
+    pub fn set_progress_callback(&mut self, callback: js_sys::Function) {
+        let cancellation = CancellationToken::new();
+        let progress = JsProgress {
+            callback,
+            cancellation: cancellation.clone(),
+        };
+        let monitor = Monitor::new(progress, cancellation);
+        self.inner.monitor = monitor.clone();
+        self.solver_config.monitor = monitor;
+    }
+
+    /// Subdivide the detailed grid cells of the master into quadrants, the tiles are scaled down
+    /// to the smaller cells. A `max_depth` of 0 restores the uniform grid.
+    ///
+    /// # Arguments
+    /// - `max_depth`: The maximum number of subdivisions of a grid cell.
+    /// - `threshold`: The cells whose detail exceeds the threshold are subdivided.
+    /// - `detail`: The measure of the detail of the cells.
+    #[wasm_bindgen(js_name = useQuadtree)]
+    pub fn use_quadtree(
+        &mut self,
+        max_depth: u32,
+        threshold: f64,
+        detail: QuadtreeDetail,
+    ) -> Result<(), JsValue> {
+        let layout = if max_depth > 0 {
+            Layout::Quadtree(Quadtree {
+                max_depth,
+                threshold,
+                detail: match detail {
+                    QuadtreeDetail::Variance => Detail::Variance,
+                    QuadtreeDetail::EdgeEnergy => Detail::EdgeEnergy,
+                },
+            })
+        } else {
+            Layout::Grid
+        };
+        self.set_layout(layout)
+    }
+
+    /// Lay the cells of the master out as hexagons inscribed in the grid cells, in offset rows.
+    /// The tiles are clipped to the hexagons.
+    #[wasm_bindgen(js_name = useHexagonal)]
+    pub fn use_hexagonal(&mut self) -> Result<(), JsValue> {
+        self.set_layout(Layout::Hexagonal)
+    }
+
+    /// Lay the cells of the master out as a running bond, every other row of grid cells being
+    /// offset by half a cell.
+    #[wasm_bindgen(js_name = useBrick)]
+    pub fn use_brick(&mut self) -> Result<(), JsValue> {
+        self.set_layout(Layout::Brick)
+    }
+
+    /// Split the master into polygonal cells, the Voronoi cells of seeds drawn more densely along
+    /// the edges of the master. The tiles are cropped to the polygons.
+    ///
+    /// # Arguments
+    /// - `seed`: The seed of the random placement of the cells.
+    /// - `edge_weight`: How much denser the cells are along the edges, 0 spreads them uniformly.
+    #[wasm_bindgen(js_name = useVoronoi)]
+    pub fn use_voronoi(&mut self, seed: u64, edge_weight: f64) -> Result<(), JsValue> {
+        self.set_layout(Layout::Voronoi(Voronoi { seed, edge_weight }))
+    }
+
+    /// Split the master into arbitrary polygonal cells. The tiles are cropped to the polygons.
+    ///
+    /// # Arguments
+    /// - `polygons`: An array of polygons, each an array of `[x, y]` vertices in the master
+    ///   image, in pixels.
+    #[wasm_bindgen(js_name = usePolygons)]
+    pub fn use_polygons(&mut self, polygons: JsValue) -> Result<(), JsValue> {
+        let polygons = serde_wasm_bindgen::from_value::<Vec<Vec<(f64, f64)>>>(polygons)?;
+        self.set_layout(Layout::Polygons(polygons))
+    }
+
+    /// Rebuild the master cells with the `layout`.
+    fn set_layout(&mut self, layout: Layout) -> Result<(), JsValue> {
+        self.inner.master = MasterRs::from_image_with_fit(
+            self.inner.master.img.clone(),
+            self.inner.grid_size,
+            layout,
+            self.inner.master.fit(),
+        )
+        .map_err(|err| JsValue::from(err.to_string()))?;
+        Ok(())
+    }
+
+    /// Wrap the `solver` so its assignments respect the repeat radius.
+    fn spaced<S: Solve>(&self, solver: S) -> Spaced<S> {
+        let spacing = self
+            .inner
+            .spacing(self.solver_config.clone(), self.repeat_radius);
+        Spaced::new(solver, spacing)
+    }
+
+    fn auction(&self) -> Auction {
+        if self.auction_scaling_factor > 1 {
+            Auction::with_epsilon_scaling(
+                self.auction_epsilon,
+                self.auction_scaling_factor,
+                Bidding::GaussSeidel,
+                self.solver_config.clone(),
+            )
+        } else {
+            Auction::new(self.auction_epsilon, self.solver_config.clone())
+        }
+    }
+
+    fn annealing(&self) -> Annealing {
+        // The tile edges are read at build time, after any palette transfer
+        let objective = Objective {
+            edges: (self.annealing_objective.seam_weight != 0)
+                .then(|| TileEdges::new(&self.inner.tiles)),
+            ..self.annealing_objective.clone()
+        };
+        self.inner.annealing(
+            self.solver_config.clone(),
+            objective,
+            self.annealing_schedule.clone(),
+        )
+    }
+
     /// Equalize the master and tile images
     pub fn equalize(&mut self) {
         self.inner.master.img = self.inner.master.img.equalize();
@@ -163,9 +465,11 @@ impl Mosaic {
     /// Transfer tile images palette to master image
     #[wasm_bindgen(js_name = transferTilesToMaster)]
     pub fn transfer_tiles_to_master(&mut self) -> Result<(), JsValue> {
-        self.inner.master = MasterRs::from_image(
+        self.inner.master = MasterRs::from_image_with_fit(
             self.inner.master.img.match_palette(&self.inner.tiles),
             self.inner.grid_size,
+            self.inner.master.layout().clone(),
+            self.inner.master.fit(),
         )
         .map_err(|err| JsValue::from(err.to_string()))?;
         Ok(())
@@ -175,15 +479,23 @@ impl Mosaic {
         &self,
         metric_type: MetricType,
     ) -> Result<DistanceMatrix, JsValue> {
-        let metric = match metric_type {
-            MetricType::NormL1 => metrics::norm_l1,
-            MetricType::NormL2 => metrics::norm_l2,
-            MetricType::LuminanceL1 => metrics::luminance_l1,
-            MetricType::LuminanceL2 => metrics::luminance_l2,
-            MetricType::AvgColor => metrics::avg_color,
+        let metric: Box<dyn Metric> = match metric_type {
+            MetricType::NormL1 => Box::new(metrics::norm_l1),
+            MetricType::NormL2 => Box::new(metrics::norm_l2),
+            MetricType::LuminanceL1 => Box::new(metrics::luminance_l1),
+            MetricType::LuminanceL2 => Box::new(metrics::luminance_l2),
+            MetricType::AvgColor => Box::new(metrics::avg_color),
+            MetricType::Oklab => Box::new(metrics::oklab),
+            MetricType::Ciede2000 => Box::new(metrics::ciede2000),
+            MetricType::AvgOklab => Box::new(metrics::avg_oklab),
+            MetricType::AvgCiede2000 => Box::new(metrics::avg_ciede2000),
+            MetricType::Ssim => Box::new(metrics::ssim),
+            MetricType::Gradient => Box::new(metrics::gradient),
         };
 
-        Ok(self.inner.distance_matrix_with_metric(metric))
+        self.inner
+            .try_distance_matrix_with_metric(metric)
+            .map_err(|err| JsValue::from(err.to_string()))
     }
 
     /// Build the mosaic img using the Hungarian algorithm and return it as base64 encoded PNG.
@@ -207,15 +519,28 @@ impl Mosaic {
     ) -> Result<String, JsValue> {
         let d_matrix = self.distance_matrix_with_metric(metric_type)?;
         let mosaic_img = match solver {
-            Solver::Greedy => self
-                .inner
-                .build_with_solver(d_matrix, Greedy::new(self.solver_config.clone())),
-            Solver::Hungarian => self
-                .inner
-                .build_with_solver(d_matrix, Hungarian::new(self.solver_config.clone())),
+            Solver::Greedy => self.inner.build_with_solver(
+                d_matrix,
+                self.spaced(Greedy::new(self.solver_config.clone())),
+            ),
+            Solver::Hungarian => self.inner.build_with_solver(
+                d_matrix,
+                self.spaced(Hungarian::new(self.solver_config.clone())),
+            ),
             Solver::Auction => self
                 .inner
-                .build_with_solver(d_matrix, Auction::new(1, self.solver_config.clone())),
+                .build_with_solver(d_matrix, self.spaced(self.auction())),
+            Solver::Lapjv => self.inner.build_with_solver(
+                d_matrix,
+                self.spaced(Lapjv::new(self.solver_config.clone())),
+            ),
+            Solver::MinCostFlow => self.inner.build_with_solver(
+                d_matrix,
+                self.spaced(MinCostFlow::new(self.solver_config.clone())),
+            ),
+            Solver::Annealing => self
+                .inner
+                .build_with_solver(d_matrix, self.spaced(self.annealing())),
         }
         .map_err(|err| JsValue::from(err.to_string()))?;
 
@@ -261,15 +586,28 @@ impl Mosaic {
     ) -> Result<JsValue, JsValue> {
         let d_matrix = self.distance_matrix_with_metric(metric_type)?;
         let blueprint = match solver {
-            Solver::Greedy => self
-                .inner
-                .build_blueprint_with_solver(d_matrix, Greedy::new(self.solver_config.clone())),
-            Solver::Hungarian => self
-                .inner
-                .build_blueprint_with_solver(d_matrix, Hungarian::new(self.solver_config.clone())),
+            Solver::Greedy => self.inner.build_blueprint_with_solver(
+                d_matrix,
+                self.spaced(Greedy::new(self.solver_config.clone())),
+            ),
+            Solver::Hungarian => self.inner.build_blueprint_with_solver(
+                d_matrix,
+                self.spaced(Hungarian::new(self.solver_config.clone())),
+            ),
             Solver::Auction => self
                 .inner
-                .build_blueprint_with_solver(d_matrix, Auction::new(1, self.solver_config.clone())),
+                .build_blueprint_with_solver(d_matrix, self.spaced(self.auction())),
+            Solver::Lapjv => self.inner.build_blueprint_with_solver(
+                d_matrix,
+                self.spaced(Lapjv::new(self.solver_config.clone())),
+            ),
+            Solver::MinCostFlow => self.inner.build_blueprint_with_solver(
+                d_matrix,
+                self.spaced(MinCostFlow::new(self.solver_config.clone())),
+            ),
+            Solver::Annealing => self
+                .inner
+                .build_blueprint_with_solver(d_matrix, self.spaced(self.annealing())),
         }
         .map_err(|err| JsValue::from(err.to_string()))?;
 
@@ -1,14 +1,21 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use phomo::avg_color;
+use phomo::features::{Histogram, MeanColor, Thumbnail};
 use phomo::luminance_l1;
 use phomo::luminance_l2;
 use phomo::read_images_from_dir_resized;
-use phomo::solvers::auction::Auction;
+use phomo::solvers::auction::{Auction, Bidding};
 use phomo::Greedy;
 use phomo::Hungarian;
+use phomo::Lapjv;
+use phomo::LocalSearch;
+use phomo::MinCostFlow;
 use phomo::Mosaic;
+use phomo::Refined;
 use phomo::Solve;
 use phomo::SolverConfig;
+use phomo::{avg_ciede2000, avg_oklab, ciede2000, oklab};
+use phomo::{gradient, ssim};
 use phomo::{norm_l1, norm_l2};
 use std::path::PathBuf;
 use std::time::Duration;
@@ -72,6 +79,24 @@ fn bench_metrics(c: &mut Criterion) {
     group.bench_function("avg_color", |b| {
         b.iter(|| black_box(avg_color(&img1, &img2)));
     });
+    group.bench_function("oklab", |b| {
+        b.iter(|| black_box(oklab(&img1, &img2)));
+    });
+    group.bench_function("ciede2000", |b| {
+        b.iter(|| black_box(ciede2000(&img1, &img2)));
+    });
+    group.bench_function("avg_oklab", |b| {
+        b.iter(|| black_box(avg_oklab(&img1, &img2)));
+    });
+    group.bench_function("avg_ciede2000", |b| {
+        b.iter(|| black_box(avg_ciede2000(&img1, &img2)));
+    });
+    group.bench_function("ssim", |b| {
+        b.iter(|| black_box(ssim(&img1, &img2)));
+    });
+    group.bench_function("gradient", |b| {
+        b.iter(|| black_box(gradient(&img1, &img2)));
+    });
     group.finish();
 }
 
@@ -82,6 +107,21 @@ fn bench_distance_matrix(c: &mut Criterion) {
     });
 }
 
+fn bench_distance_matrix_features(c: &mut Criterion) {
+    let mosaic = create_mosaic();
+    let mut group = c.benchmark_group("distance_matrix_features");
+    group.bench_function("mean_color", |b| {
+        b.iter(|| black_box(mosaic.distance_matrix_with_features(MeanColor)));
+    });
+    group.bench_function("thumbnail", |b| {
+        b.iter(|| black_box(mosaic.distance_matrix_with_features(Thumbnail::default())));
+    });
+    group.bench_function("histogram", |b| {
+        b.iter(|| black_box(mosaic.distance_matrix_with_features(Histogram::default())));
+    });
+    group.finish();
+}
+
 fn bench_solvers(c: &mut Criterion) {
     let mosaic = create_mosaic();
     let distance_matrix = mosaic.distance_matrix();
@@ -96,6 +136,50 @@ fn bench_solvers(c: &mut Criterion) {
     group.bench_function("auction", |b| {
         b.iter(|| black_box(Auction::default()).solve(&distance_matrix));
     });
+    group.bench_function("auction_jacobi", |b| {
+        b.iter(|| {
+            black_box(Auction::with_epsilon_scaling(
+                1,
+                4,
+                Bidding::Jacobi,
+                SolverConfig::default(),
+            ))
+            .solve(&distance_matrix)
+        });
+    });
+    group.bench_function("lapjv", |b| {
+        b.iter(|| black_box(Lapjv::default().solve(&distance_matrix)));
+    });
+    group.bench_function("min_cost_flow", |b| {
+        b.iter(|| black_box(MinCostFlow::default().solve(&distance_matrix)));
+    });
+    group.bench_function("greedy_refined", |b| {
+        b.iter(|| {
+            black_box(Refined::new(Greedy::default(), LocalSearch::default()))
+                .solve(&distance_matrix)
+        });
+    });
+    group.finish();
+}
+
+fn bench_solvers_repeats(c: &mut Criterion) {
+    let mosaic = create_mosaic();
+    let distance_matrix = mosaic.distance_matrix();
+    let config = SolverConfig {
+        max_tile_occurrences: 4,
+        ..Default::default()
+    };
+
+    let mut group = c.benchmark_group("solvers_repeats");
+    group.bench_function("hungarian", |b| {
+        b.iter(|| black_box(Hungarian::new(config.clone()).solve(&distance_matrix)));
+    });
+    group.bench_function("lapjv", |b| {
+        b.iter(|| black_box(Lapjv::new(config.clone()).solve(&distance_matrix)));
+    });
+    group.bench_function("min_cost_flow", |b| {
+        b.iter(|| black_box(MinCostFlow::new(config.clone()).solve(&distance_matrix)));
+    });
     group.finish();
 }
 
@@ -125,6 +209,6 @@ fn bench_render(c: &mut Criterion) {
 criterion_group! {
     name = benches;
     config = Criterion::default().sample_size(100).measurement_time(Duration::from_secs(10));
-    targets = bench_distance_matrix, bench_solvers, bench_build_mosaic, bench_metrics, bench_render
+    targets = bench_distance_matrix, bench_distance_matrix_features, bench_solvers, bench_solvers_repeats, bench_build_mosaic, bench_metrics, bench_render
 }
 criterion_main!(benches);
@@ -1,11 +1,14 @@
-use image::{GenericImage, RgbImage};
+use image::RgbImage;
 use log::info;
 use serde::{Deserialize, Serialize};
 
+use crate::cost_matrix::CostMatrix;
+use crate::distance_matrix::solve_timed;
 use crate::error::{MosaicError, PhomoError};
+use crate::layout::{self, Region, Shape};
 use crate::solvers::hungarian::Hungarian;
 use crate::solvers::{Solve, SolverConfig};
-use crate::{DistanceMatrix, Mosaic};
+use crate::{Background, Mosaic};
 
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
 pub struct Blueprint {
@@ -14,13 +17,22 @@ pub struct Blueprint {
     pub cell_height: u32,
     pub grid_width: u32,
     pub grid_height: u32,
+    /// The fill of the parts of the mosaic which aren't covered by a cell.
+    #[serde(default)]
+    pub background: Background,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
 pub struct Cell {
     pub tile_index: usize,
-    pub x: u32,
-    pub y: u32,
+    pub x: i32,
+    pub y: i32,
+    /// The size of the cell, when it differs from the cell size of the [`Blueprint`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<(u32, u32)>,
+    /// The shape of the cell within its rectangle, the tile is clipped to it.
+    #[serde(default, skip_serializing_if = "Shape::is_rectangle")]
+    pub shape: Shape,
 }
 
 /// A serializable struct which represents a [crate::mosaic::Mosaic] that has yet to be rendered.
@@ -35,7 +47,7 @@ impl Blueprint {
         master_img: &RgbImage,
         tiles: &[RgbImage],
     ) -> Result<RgbImage, PhomoError> {
-        let mut mosaic_img = RgbImage::new(master_img.width(), master_img.height());
+        let mut mosaic_img = self.background.fill(master_img);
         info!(
             "Building mosaic, size: {}x{}, cell size: {}x{}, grid size: {}x{}",
             mosaic_img.width(),
@@ -50,7 +62,15 @@ impl Blueprint {
             let tile = tiles
                 .get(cell.tile_index)
                 .ok_or(MosaicError::InvalidTileIndex(cell.tile_index))?;
-            mosaic_img.copy_from(tile, cell.x, cell.y)?;
+            let (width, height) = cell.size.unwrap_or((self.cell_width, self.cell_height));
+            layout::draw_tile(
+                &mut mosaic_img,
+                tile,
+                &Region {
+                    shape: cell.shape.clone(),
+                    ..Region::new(cell.x, cell.y, width, height)
+                },
+            )?;
         }
         Ok(mosaic_img)
     }
@@ -58,14 +78,16 @@ impl Blueprint {
 
 impl Mosaic {
     /// Compute the tile to master cell assignments, and construct a [`Blueprint`] of the mosaic
-    /// image.
+    /// image. Use [`Mosaic::build_blueprint_with_solver`] with a
+    /// [`Spaced`](crate::Spaced) solver to avoid the repeated tiles within a
+    /// radius.
     ///
     /// # Errors
     /// - [`PhomoError::MosaicError`]: An error occurred while building the mosaic blueprint.
     /// - [`PhomoError::SolverError`]: An error occurred while solving the tile to cell assignments.
-    pub fn build_blueprint(
+    pub fn build_blueprint<C: CostMatrix>(
         &self,
-        distance_matrix: DistanceMatrix,
+        distance_matrix: C,
         config: SolverConfig,
     ) -> Result<Blueprint, PhomoError> {
         let solver = Hungarian::new(config);
@@ -77,20 +99,22 @@ impl Mosaic {
     /// implemented solvers.
     ///
     /// # Arguments:
-    /// - `distance_matrix`: The distance matrix between the master image and the tiles.
+    /// - `distance_matrix`: The distance matrix between the master image and the tiles, either a
+    ///   dense [`DistanceMatrix`](crate::DistanceMatrix) or a
+    ///   [`SparseDistanceMatrix`](crate::SparseDistanceMatrix).
     /// - `solver`: The solver algorithm to use for the assignment problem.
     ///
     /// # Errors
     /// - [`PhomoError::MosaicError`]: An error occurred while building the mosaic.
     /// - [`PhomoError::SolverError`]: An error occurred while solving the tile to cell assignments.
-    pub fn build_blueprint_with_solver<S: Solve>(
+    pub fn build_blueprint_with_solver<C: CostMatrix, S: Solve>(
         &self,
-        distance_matrix: DistanceMatrix,
+        distance_matrix: C,
         mut solver: S,
     ) -> Result<Blueprint, PhomoError> {
         self.check_distance_matrix(&distance_matrix)?;
 
-        let assignments = distance_matrix.assignments(&mut solver)?;
+        let assignments = solve_timed(&distance_matrix, &mut solver)?;
         let (grid_width, grid_height) = self.grid_size;
         let (cell_width, cell_height) = self.master.cell_size;
         info!(
@@ -103,17 +127,18 @@ impl Mosaic {
             grid_height
         );
 
-        let cells = assignments
-            .into_iter()
-            .enumerate()
-            .map(|(cell_idx, tile_idx)| {
-                let x = (cell_idx as u32 % grid_width) * cell_width;
-                let y = (cell_idx as u32 / grid_width) * cell_height;
-                Cell {
-                    tile_index: tile_idx % self.tiles.len(),
-                    x,
-                    y,
-                }
+        let cells = self
+            .master
+            .regions
+            .iter()
+            .zip(assignments)
+            .map(|(region, tile_idx)| Cell {
+                tile_index: tile_idx % self.tiles.len(),
+                x: region.x,
+                y: region.y,
+                size: ((region.width, region.height) != (cell_width, cell_height))
+                    .then_some((region.width, region.height)),
+                shape: region.shape.clone(),
             })
             .collect::<Vec<_>>();
 
@@ -123,6 +148,7 @@ impl Mosaic {
             cell_height,
             grid_width,
             grid_height,
+            background: self.background,
         })
     }
 }
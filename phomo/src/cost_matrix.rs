@@ -0,0 +1,506 @@
+use crate::solvers::{SolverConfig, SplitMix64};
+
+/// The [`CostMatrix`] trait is implemented by the cost matrices the solvers operate on, see
+/// [`DistanceMatrix`](crate::DistanceMatrix) and
+/// [`SparseDistanceMatrix`](crate::SparseDistanceMatrix).
+///
+/// The rows are the master cells and the columns are the tiles. A cost matrix can be sparse, in
+/// which case only some of the columns of each row are candidates for the assignment.
+pub trait CostMatrix: Sync {
+    /// The number of rows of the matrix.
+    fn n_rows(&self) -> usize;
+
+    /// The number of columns of the matrix.
+    fn n_columns(&self) -> usize;
+
+    /// The cost of assigning the `column` to the `row`, or `None` if the `column` is not a
+    /// candidate for the `row`.
+    fn cost(&self, row: usize, column: usize) -> Option<i64>;
+
+    /// Iterate over the candidate columns of the `row`, and their costs, in increasing column
+    /// order.
+    fn row_costs(&self, row: usize) -> impl Iterator<Item = (usize, i64)> + '_;
+}
+
+/// A view of a [`CostMatrix`] with its columns repeated `n` times horizontally, without copying
+/// the costs. Column `j` of the view is column `j % n_columns` of the inner matrix.
+pub(crate) struct Repeated<'a, C: CostMatrix> {
+    inner: &'a C,
+    n: usize,
+}
+
+impl<'a, C: CostMatrix> Repeated<'a, C> {
+    pub(crate) fn new(inner: &'a C, n: usize) -> Self {
+        Self { inner, n }
+    }
+}
+
+impl<C: CostMatrix> CostMatrix for Repeated<'_, C> {
+    fn n_rows(&self) -> usize {
+        self.inner.n_rows()
+    }
+
+    fn n_columns(&self) -> usize {
+        self.inner.n_columns() * self.n
+    }
+
+    fn cost(&self, row: usize, column: usize) -> Option<i64> {
+        self.inner.cost(row, column % self.inner.n_columns())
+    }
+
+    fn row_costs(&self, row: usize) -> impl Iterator<Item = (usize, i64)> + '_ {
+        let n_columns = self.inner.n_columns();
+        (0..self.n).flat_map(move |copy| {
+            self.inner
+                .row_costs(row)
+                .map(move |(column, cost)| (copy * n_columns + column, cost))
+        })
+    }
+}
+
+/// A view of a [`CostMatrix`] in which the pinned rows only have their pinned column as candidate.
+/// A pinned column which isn't a candidate of the inner matrix costs 0.
+pub(crate) struct Pinned<'a, C: CostMatrix> {
+    inner: &'a C,
+    pins: Vec<Option<usize>>,
+}
+
+impl<'a, C: CostMatrix> Pinned<'a, C> {
+    pub(crate) fn new(inner: &'a C, config: &SolverConfig) -> Self {
+        Self {
+            inner,
+            pins: config.pinned_columns(inner.n_rows()),
+        }
+    }
+}
+
+impl<C: CostMatrix> CostMatrix for Pinned<'_, C> {
+    fn n_rows(&self) -> usize {
+        self.inner.n_rows()
+    }
+
+    fn n_columns(&self) -> usize {
+        self.inner.n_columns()
+    }
+
+    fn cost(&self, row: usize, column: usize) -> Option<i64> {
+        match self.pins[row] {
+            Some(pin) if pin != column => None,
+            Some(_) => Some(self.inner.cost(row, column).unwrap_or(0)),
+            None => self.inner.cost(row, column),
+        }
+    }
+
+    fn row_costs(&self, row: usize) -> impl Iterator<Item = (usize, i64)> + '_ {
+        let pin = self.pins[row];
+        self.inner
+            .row_costs(row)
+            .filter(move |&(column, _)| pin.is_none_or(|pin| pin == column))
+            .chain(
+                pin.filter(|&pin| self.inner.cost(row, pin).is_none())
+                    .map(|pin| (pin, 0)),
+            )
+    }
+}
+
+/// A square view of a [`CostMatrix`] which forces each column to be assigned at least
+/// [`SolverConfig::min_tile_occurrences`] times.
+///
+/// Each column is repeated as many times as it can be used, the first `min_tile_occurrences` copies
+/// being mandatory. A column can't be used more than its capacity, nor more than the rows left once
+/// the other columns are used their minimum number of times. Dummy rows are appended to make the
+/// view square, they can only be assigned the optional copies, at no cost. A complete assignment of
+/// the view then assigns every mandatory copy to a row of the inner matrix.
+///
+/// The view has `n_columns` times as many columns as the inner matrix, the
+/// [`MinCostFlow`](crate::MinCostFlow) solver handles the minimum usages with a [`LowerBounded`]
+/// view instead.
+pub(crate) struct MinUsage<'a, C: CostMatrix> {
+    inner: &'a C,
+    capacity: usize,
+    min_occurrences: usize,
+}
+
+impl<'a, C: CostMatrix> MinUsage<'a, C> {
+    pub(crate) fn new(inner: &'a C, config: &SolverConfig) -> Self {
+        let min_occurrences = config.min_tile_occurrences;
+        let other_rows = inner.n_columns().saturating_sub(1) * min_occurrences;
+        let capacity = config
+            .max_tile_occurrences
+            .min(inner.n_rows().saturating_sub(other_rows))
+            .max(min_occurrences);
+        Self {
+            inner,
+            capacity,
+            min_occurrences,
+        }
+    }
+
+    /// Map the `assignments` of the view back to the columns of the inner matrix, dropping the
+    /// dummy rows.
+    pub(crate) fn inner_assignments(&self, assignments: Vec<usize>) -> Vec<usize> {
+        let n_columns = self.inner.n_columns();
+        assignments
+            .into_iter()
+            .take(self.inner.n_rows())
+            .map(|column| column % n_columns)
+            .collect()
+    }
+}
+
+impl<C: CostMatrix> CostMatrix for MinUsage<'_, C> {
+    fn n_rows(&self) -> usize {
+        self.n_columns().max(self.inner.n_rows())
+    }
+
+    fn n_columns(&self) -> usize {
+        self.inner.n_columns() * self.capacity
+    }
+
+    fn cost(&self, row: usize, column: usize) -> Option<i64> {
+        let n_columns = self.inner.n_columns();
+        if row < self.inner.n_rows() {
+            self.inner.cost(row, column % n_columns)
+        } else {
+            (column / n_columns >= self.min_occurrences).then_some(0)
+        }
+    }
+
+    fn row_costs(&self, row: usize) -> impl Iterator<Item = (usize, i64)> + '_ {
+        let n_columns = self.inner.n_columns();
+        let is_dummy = row >= self.inner.n_rows();
+        let copies = (!is_dummy).then(|| {
+            (0..self.capacity).flat_map(move |copy| {
+                self.inner
+                    .row_costs(row)
+                    .map(move |(column, cost)| (copy * n_columns + column, cost))
+            })
+        });
+        let optional = is_dummy.then(|| {
+            (self.min_occurrences * n_columns..self.n_columns()).map(|column| (column, 0))
+        });
+        copies
+            .into_iter()
+            .flatten()
+            .chain(optional.into_iter().flatten())
+    }
+}
+
+/// A view of a [`CostMatrix`] which splits each column into a mandatory and an optional column,
+/// for the [`MinCostFlow`](crate::MinCostFlow) solver to use each column at least
+/// [`SolverConfig::min_tile_occurrences`] times.
+///
+/// Column `j` of the view is the mandatory part of column `j` of the inner matrix, with a capacity
+/// of `min_tile_occurrences`, and column `n_columns + j` is its optional part, with the rest of its
+/// capacity. The costs of the optional columns are raised by a bonus larger than the cost
+/// difference of any two assignments of the rows, so the assignments of minimum cost fill all the
+/// mandatory columns whenever they can.
+pub(crate) struct LowerBounded<'a, C: CostMatrix> {
+    inner: &'a C,
+    min_occurrences: usize,
+    max_occurrences: usize,
+    bonus: i64,
+}
+
+impl<'a, C: CostMatrix> LowerBounded<'a, C> {
+    pub(crate) fn new(inner: &'a C, config: &SolverConfig) -> Self {
+        let (min_cost, max_cost) = (0..inner.n_rows())
+            .flat_map(|row| inner.row_costs(row).map(|(_, cost)| cost))
+            .fold((i64::MAX, i64::MIN), |(min_cost, max_cost), cost| {
+                (min_cost.min(cost), max_cost.max(cost))
+            });
+        let bonus = (inner.n_rows() as i64)
+            .saturating_mul(max_cost.saturating_sub(min_cost).max(0))
+            .saturating_add(1);
+        Self {
+            inner,
+            min_occurrences: config.min_tile_occurrences,
+            max_occurrences: config.max_tile_occurrences,
+            bonus,
+        }
+    }
+
+    /// The capacity of each column of the view.
+    pub(crate) fn capacities(&self) -> Vec<usize> {
+        let n_columns = self.inner.n_columns();
+        let optional = self.max_occurrences.saturating_sub(self.min_occurrences);
+        [self.min_occurrences, optional]
+            .into_iter()
+            .flat_map(|capacity| std::iter::repeat_n(capacity, n_columns))
+            .collect()
+    }
+
+    /// Map the `assignments` of the view back to the columns of the inner matrix.
+    pub(crate) fn inner_assignments(&self, assignments: Vec<usize>) -> Vec<usize> {
+        let n_columns = self.inner.n_columns();
+        assignments
+            .into_iter()
+            .map(|column| column % n_columns)
+            .collect()
+    }
+
+    /// Whether the optional columns can be assigned.
+    fn has_optional(&self) -> bool {
+        self.max_occurrences > self.min_occurrences
+    }
+}
+
+impl<C: CostMatrix> CostMatrix for LowerBounded<'_, C> {
+    fn n_rows(&self) -> usize {
+        self.inner.n_rows()
+    }
+
+    fn n_columns(&self) -> usize {
+        self.inner.n_columns() * 2
+    }
+
+    fn cost(&self, row: usize, column: usize) -> Option<i64> {
+        let n_columns = self.inner.n_columns();
+        if column < n_columns {
+            self.inner.cost(row, column)
+        } else if self.has_optional() {
+            self.inner
+                .cost(row, column - n_columns)
+                .map(|cost| cost + self.bonus)
+        } else {
+            None
+        }
+    }
+
+    fn row_costs(&self, row: usize) -> impl Iterator<Item = (usize, i64)> + '_ {
+        let n_columns = self.inner.n_columns();
+        let optional = self.has_optional().then(|| {
+            self.inner
+                .row_costs(row)
+                .map(move |(column, cost)| (n_columns + column, cost + self.bonus))
+        });
+        self.inner
+            .row_costs(row)
+            .chain(optional.into_iter().flatten())
+    }
+}
+
+/// A view of a [`CostMatrix`] with its columns in a random order drawn from the
+/// [`SolverConfig::seed`], so that the solvers break the ties between equal costs differently for
+/// each seed. Column `j` of the view is column `permutation[j]` of the inner matrix. Without a seed
+/// the columns keep their order.
+pub(crate) struct Shuffled<'a, C: CostMatrix> {
+    inner: &'a C,
+    /// The inner column of each column of the view.
+    permutation: Option<Vec<usize>>,
+    /// The column of the view of each inner column.
+    inverse: Vec<usize>,
+}
+
+impl<'a, C: CostMatrix> Shuffled<'a, C> {
+    pub(crate) fn new(inner: &'a C, config: &SolverConfig) -> Self {
+        let permutation = config.seed.map(|seed| permutation(inner.n_columns(), seed));
+        let mut inverse = vec![0; permutation.as_ref().map_or(0, Vec::len)];
+        for (column, &inner_column) in permutation.iter().flatten().enumerate() {
+            inverse[inner_column] = column;
+        }
+        Self {
+            inner,
+            permutation,
+            inverse,
+        }
+    }
+
+    /// The column of the view of the `inner_column`.
+    pub(crate) fn column(&self, inner_column: usize) -> usize {
+        match self.permutation {
+            Some(_) => self.inverse[inner_column],
+            None => inner_column,
+        }
+    }
+
+    /// Map the `assignments` of the view back to the columns of the inner matrix.
+    pub(crate) fn inner_assignments(&self, assignments: Vec<usize>) -> Vec<usize> {
+        match &self.permutation {
+            Some(permutation) => assignments
+                .into_iter()
+                .map(|column| permutation[column])
+                .collect(),
+            None => assignments,
+        }
+    }
+}
+
+impl<C: CostMatrix> CostMatrix for Shuffled<'_, C> {
+    fn n_rows(&self) -> usize {
+        self.inner.n_rows()
+    }
+
+    fn n_columns(&self) -> usize {
+        self.inner.n_columns()
+    }
+
+    fn cost(&self, row: usize, column: usize) -> Option<i64> {
+        match &self.permutation {
+            Some(permutation) => self.inner.cost(row, permutation[column]),
+            None => self.inner.cost(row, column),
+        }
+    }
+
+    fn row_costs(&self, row: usize) -> impl Iterator<Item = (usize, i64)> + '_ {
+        let n_columns = self.inner.n_columns();
+        let permutation = self.permutation.as_deref();
+        let unshuffled = permutation.is_none().then(|| self.inner.row_costs(row));
+        // The candidates of the rows with many candidates are scattered in the order of the
+        // view, those of the other rows are sorted
+        let is_dense = permutation.is_some()
+            && self
+                .inner
+                .row_costs(row)
+                .size_hint()
+                .1
+                .is_none_or(|candidates| candidates * 8 >= n_columns);
+        let scattered = is_dense.then(|| {
+            let mut costs = vec![None; n_columns];
+            for (column, cost) in self.inner.row_costs(row) {
+                costs[self.inverse[column]] = Some(cost);
+            }
+            costs
+                .into_iter()
+                .enumerate()
+                .filter_map(|(column, cost)| cost.map(|cost| (column, cost)))
+        });
+        let sorted = (permutation.is_some() && !is_dense).then(|| {
+            let mut costs = self
+                .inner
+                .row_costs(row)
+                .map(|(column, cost)| (self.inverse[column], cost))
+                .collect::<Vec<_>>();
+            costs.sort_unstable();
+            costs
+        });
+        unshuffled
+            .into_iter()
+            .flatten()
+            .chain(scattered.into_iter().flatten())
+            .chain(sorted.into_iter().flatten())
+    }
+}
+
+/// A random permutation of `0..n`, drawn from the `seed` with a Fisher-Yates shuffle.
+fn permutation(n: usize, seed: u64) -> Vec<usize> {
+    let mut rng = SplitMix64::new(seed);
+    let mut permutation = (0..n).collect::<Vec<_>>();
+    for i in (1..n).rev() {
+        permutation.swap(i, rng.below(i + 1));
+    }
+    permutation
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DistanceMatrix;
+
+    #[test]
+    fn test_repeated() {
+        let d_matrix = DistanceMatrix::new(2, 3, vec![1, 2, 3, 4, 5, 6]).unwrap();
+        let repeated = Repeated::new(&d_matrix, 2);
+        assert_eq!(repeated.n_rows(), 2);
+        assert_eq!(repeated.n_columns(), 6);
+        assert_eq!(repeated.cost(1, 4), Some(5));
+        let row = repeated.row_costs(1).map(|(_, c)| c).collect::<Vec<_>>();
+        assert_eq!(row, vec![4, 5, 6, 4, 5, 6]);
+    }
+
+    #[test]
+    fn test_min_usage() {
+        let d_matrix = DistanceMatrix::new(4, 2, vec![1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+        let config = SolverConfig {
+            max_tile_occurrences: 4,
+            min_tile_occurrences: 1,
+            ..Default::default()
+        };
+        let min_usage = MinUsage::new(&d_matrix, &config);
+        // A column can't be used more than 3 times, the other column being used at least once
+        assert_eq!(min_usage.n_rows(), 6);
+        assert_eq!(min_usage.n_columns(), 6);
+        assert_eq!(min_usage.cost(2, 3), Some(6));
+        // Dummy rows can't be assigned the mandatory copies
+        assert_eq!(min_usage.cost(4, 1), None);
+        assert_eq!(min_usage.cost(4, 2), Some(0));
+        let row = min_usage.row_costs(4).map(|(c, _)| c).collect::<Vec<_>>();
+        assert_eq!(row, vec![2, 3, 4, 5]);
+        assert_eq!(
+            min_usage.inner_assignments(vec![3, 0, 5, 1, 2, 4]),
+            vec![1, 0, 1, 1]
+        );
+    }
+
+    #[test]
+    fn test_lower_bounded() {
+        let d_matrix = DistanceMatrix::new(3, 2, vec![1, 2, 3, 4, 5, 6]).unwrap();
+        let config = SolverConfig {
+            max_tile_occurrences: 3,
+            min_tile_occurrences: 1,
+            ..Default::default()
+        };
+        let lower_bounded = LowerBounded::new(&d_matrix, &config);
+        assert_eq!(lower_bounded.n_rows(), 3);
+        assert_eq!(lower_bounded.n_columns(), 4);
+        assert_eq!(lower_bounded.capacities(), vec![1, 1, 2, 2]);
+        // The bonus exceeds the cost difference of any two assignments
+        assert_eq!(lower_bounded.cost(1, 1), Some(4));
+        assert_eq!(lower_bounded.cost(1, 3), Some(4 + 3 * 5 + 1));
+        let row = lower_bounded.row_costs(0).collect::<Vec<_>>();
+        assert_eq!(row, vec![(0, 1), (1, 2), (2, 17), (3, 18)]);
+        assert_eq!(
+            lower_bounded.inner_assignments(vec![3, 0, 2]),
+            vec![1, 0, 0]
+        );
+
+        // Without optional copies, only the mandatory columns are candidates
+        let config = SolverConfig {
+            max_tile_occurrences: 1,
+            min_tile_occurrences: 1,
+            ..Default::default()
+        };
+        let lower_bounded = LowerBounded::new(&d_matrix, &config);
+        assert_eq!(lower_bounded.cost(0, 2), None);
+        assert_eq!(lower_bounded.row_costs(0).count(), 2);
+    }
+
+    #[test]
+    fn test_pinned() {
+        let d_matrix = DistanceMatrix::new(2, 3, vec![1, 2, 3, 4, 5, 6]).unwrap();
+        let config = SolverConfig {
+            pinned: vec![(1, 2)],
+            ..Default::default()
+        };
+        let pinned = Pinned::new(&d_matrix, &config);
+        assert_eq!(pinned.cost(0, 1), Some(2));
+        assert_eq!(pinned.cost(1, 1), None);
+        assert_eq!(pinned.cost(1, 2), Some(6));
+        assert_eq!(pinned.row_costs(0).count(), 3);
+        assert_eq!(pinned.row_costs(1).collect::<Vec<_>>(), vec![(2, 6)]);
+    }
+
+    #[test]
+    fn test_shuffled() {
+        let d_matrix = DistanceMatrix::new(2, 4, vec![1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+        let unshuffled = Shuffled::new(&d_matrix, &SolverConfig::default());
+        assert_eq!(unshuffled.cost(1, 2), Some(7));
+        assert_eq!(unshuffled.inner_assignments(vec![3, 1]), vec![3, 1]);
+
+        let config = SolverConfig {
+            seed: Some(42),
+            ..Default::default()
+        };
+        let shuffled = Shuffled::new(&d_matrix, &config);
+        let row = shuffled.row_costs(1).collect::<Vec<_>>();
+        assert!(row.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        for (column, cost) in row {
+            assert_eq!(shuffled.cost(1, column), Some(cost));
+            let inner_column = shuffled.inner_assignments(vec![column])[0];
+            assert_eq!(d_matrix.get(1, inner_column), cost);
+            assert_eq!(shuffled.column(inner_column), column);
+        }
+        assert_eq!(permutation(16, 42), permutation(16, 42));
+        assert_ne!(permutation(16, 42), permutation(16, 43));
+    }
+}
@@ -3,9 +3,12 @@ use log::info;
 #[cfg(not(target_family = "wasm"))]
 use std::time;
 
+use crate::cost_matrix::CostMatrix;
 use crate::error::DistanceMatrixError;
 use crate::error::PhomoError;
+use crate::solve_report::{solve_report, SolveReport};
 use crate::solvers::Solve;
+use crate::sparse_distance_matrix::SparseDistanceMatrix;
 
 #[derive(Debug, Clone, Eq, Hash, PartialEq)]
 pub struct DistanceMatrix {
@@ -14,21 +17,6 @@ pub struct DistanceMatrix {
     pub data: Vec<i64>,
 }
 
-impl DistanceMatrix {
-    /// Create a new [DistanceMatrix] which is tiled `n` times horizontally.
-    pub(crate) fn tile(&self, n: usize) -> DistanceMatrix {
-        DistanceMatrix {
-            rows: self.rows,
-            columns: self.columns * n,
-            data: self
-                .data
-                .chunks(self.columns)
-                .flat_map(|row| row.repeat(n))
-                .collect(),
-        }
-    }
-}
-
 /// Helper struct to handle the distance matrix and allow for repeated tiles.
 impl DistanceMatrix {
     pub fn new(rows: usize, columns: usize, data: Vec<i64>) -> Result<Self, PhomoError> {
@@ -51,6 +39,28 @@ impl DistanceMatrix {
     pub fn get(&self, row: usize, col: usize) -> i64 {
         self.data[row * self.columns + col]
     }
+
+    /// Convert to a [`SparseDistanceMatrix`] which only keeps the `k` lowest costs of each row.
+    ///
+    /// # Arguments
+    /// - `k`: The number of candidate columns to keep for each row.
+    pub fn to_sparse(&self, k: usize) -> SparseDistanceMatrix {
+        let candidates = self
+            .data
+            .chunks(self.columns)
+            .map(|row| {
+                let mut row_candidates = row.iter().copied().enumerate().collect::<Vec<_>>();
+                if k < row_candidates.len() {
+                    row_candidates.select_nth_unstable_by_key(k, |&(col, cost)| (cost, col));
+                    row_candidates.truncate(k);
+                }
+                row_candidates
+            })
+            .collect();
+        // The candidates come from the dense matrix so they are within bounds
+        SparseDistanceMatrix::new(self.rows, self.columns, candidates)
+            .expect("The candidates should match the matrix dimensions")
+    }
 }
 
 impl DistanceMatrix {
@@ -63,17 +73,59 @@ impl DistanceMatrix {
     /// # Errors
     /// - [`PhomoError::SolverError`]: An error occurred while solving the assignment problem.
     pub fn assignments<S: Solve>(&self, solver: &mut S) -> Result<Vec<usize>, PhomoError> {
-        #[cfg(not(target_family = "wasm"))]
-        info!("Computing assignmnent...");
-        #[cfg(not(target_family = "wasm"))]
-        let start_time = time::Instant::now();
+        solve_timed(self, solver)
+    }
 
-        let out = solver.solve(self);
+    /// Solve the linear sum assignment problem using the provided `solver`, and report the cost
+    /// of the assignments, the usage of the tiles, and the time taken by the solver.
+    ///
+    /// # Arguments
+    /// - `solver`: The solver to use to solve the assignment problem. See [`phomo::solvers`](crate::solvers)
+    ///   for structs which implement this trait.
+    ///
+    /// # Errors
+    /// - [`PhomoError::SolverError`]: An error occurred while solving the assignment problem.
+    pub fn report<S: Solve>(&self, solver: &mut S) -> Result<SolveReport, PhomoError> {
+        solve_report(self, solver)
+    }
+}
+
+/// Solve the assignment problem of the `cost_matrix` using the `solver`, logging the time taken.
+pub(crate) fn solve_timed<C: CostMatrix, S: Solve>(
+    cost_matrix: &C,
+    solver: &mut S,
+) -> Result<Vec<usize>, PhomoError> {
+    #[cfg(not(target_family = "wasm"))]
+    info!("Computing assignmnent...");
+    #[cfg(not(target_family = "wasm"))]
+    let start_time = time::Instant::now();
+
+    let out = solver.solve_cost_matrix(cost_matrix);
 
-        #[cfg(not(target_family = "wasm"))]
-        info!("Completed in {:?}", start_time.elapsed());
+    #[cfg(not(target_family = "wasm"))]
+    info!("Completed in {:?}", start_time.elapsed());
+
+    out
+}
+
+impl CostMatrix for DistanceMatrix {
+    fn n_rows(&self) -> usize {
+        self.rows
+    }
 
-        out
+    fn n_columns(&self) -> usize {
+        self.columns
+    }
+
+    fn cost(&self, row: usize, column: usize) -> Option<i64> {
+        Some(self.get(row, column))
+    }
+
+    fn row_costs(&self, row: usize) -> impl Iterator<Item = (usize, i64)> + '_ {
+        self.data[row * self.columns..(row + 1) * self.columns]
+            .iter()
+            .copied()
+            .enumerate()
     }
 }
 
@@ -94,9 +146,25 @@ mod tests {
     }
 
     #[test]
-    fn test_tile() {
+    fn test_to_sparse() {
+        let d_matrix = DistanceMatrix::new(2, 3, vec![3, 1, 2, 4, 6, 5]).unwrap();
+        let sparse = d_matrix.to_sparse(2);
+        assert_eq!(sparse.n_candidates(), 4);
+        assert_eq!(
+            sparse.row_costs(0).collect::<Vec<_>>(),
+            vec![(1, 1), (2, 2)]
+        );
+        assert_eq!(
+            sparse.row_costs(1).collect::<Vec<_>>(),
+            vec![(0, 4), (2, 5)]
+        );
+        assert_eq!(d_matrix.to_sparse(5).n_candidates(), 6);
+    }
+
+    #[test]
+    fn test_row_costs() {
         let d_matrix = DistanceMatrix::new(2, 3, vec![1, 2, 3, 4, 5, 6]).unwrap();
-        let d_matrix_repeat = d_matrix.tile(2);
-        assert!(d_matrix_repeat.data == vec![1, 2, 3, 1, 2, 3, 4, 5, 6, 4, 5, 6]);
+        let row = d_matrix.row_costs(1).collect::<Vec<_>>();
+        assert_eq!(row, vec![(0, 4), (1, 5), (2, 6)]);
     }
 }
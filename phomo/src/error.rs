@@ -21,6 +21,9 @@ pub enum PhomoError {
 
     #[error("Image error: {0}")]
     ImageError(#[from] image::ImageError),
+
+    #[error("The operation was cancelled")]
+    Cancelled,
 }
 
 #[derive(Debug, Error)]
@@ -34,6 +37,21 @@ pub enum MasterError {
         master_dimensions: (u32, u32),
         cell_size: (u32, u32),
     },
+
+    #[error("Invalid mask dimensions {mask_dimensions:?}, the mask can't be fitted to the master dimensions {master_dimensions:?}")]
+    MaskSizeMismatch {
+        mask_dimensions: (u32, u32),
+        master_dimensions: (u32, u32),
+    },
+
+    #[error("Invalid cell size {cell_size:?}, the cells must be at least one pixel wide and high")]
+    InvalidCellSize { cell_size: (u32, u32) },
+
+    #[error("Invalid cell size {cell_size:?}, no cell fits in the cropped master dimensions {master_dimensions:?}")]
+    CellSizeTooLarge {
+        cell_size: (u32, u32),
+        master_dimensions: (u32, u32),
+    },
 }
 
 #[derive(Debug, Error)]
@@ -59,6 +77,10 @@ pub enum MosaicError {
 
     #[error("Invalid tile index: {0}")]
     InvalidTileIndex(usize),
+    #[error("Tile weights length mismatch: expected {expected}, but found {found}")]
+    TileWeightsLengthMismatch { expected: usize, found: usize },
+    #[error("Invalid tile weight on line {line}: {content:?}")]
+    InvalidTileWeight { line: usize, content: String },
     #[error("{0}")]
     Custom(String),
 }
@@ -73,4 +95,7 @@ pub enum DistanceMatrixError {
 
     #[error("The number of rows must be greater than zero")]
     EmptyRow,
+
+    #[error("Column {column} of row {row} is out of bounds")]
+    ColumnOutOfBounds { row: usize, column: usize },
 }
@@ -0,0 +1,302 @@
+//! A module which contains structs which implement the [`FeatureExtractor`] trait. Feature
+//! extractors compute a descriptor of each tile and master cell once, the distances are then
+//! computed between the descriptors, see
+//! [`Mosaic::distance_matrix_with_features`](crate::Mosaic::distance_matrix_with_features).
+extern crate image;
+use image::{GrayImage, RgbImage};
+
+use crate::metrics::{norm_l1, Metric};
+
+/// The [`FeatureExtractor`] trait is implemented by structs which extract a descriptor from an
+/// image, and compute the distance between two descriptors.
+pub trait FeatureExtractor: Send + Sync {
+    /// The descriptor of an image.
+    type Feature: Send + Sync;
+
+    /// Extract the descriptor of the `img`.
+    fn extract(&self, img: &RgbImage) -> Self::Feature;
+
+    /// Extract the descriptor of the pixels of the `img` within the `mask`, where the `mask` is
+    /// non-zero. Used for the master cells which don't fill their rectangle.
+    ///
+    /// By default, the pixels outside of the `mask` are replaced by the mean color of the pixels
+    /// within it, before extracting the descriptor of the image.
+    fn extract_masked(&self, img: &RgbImage, mask: &GrayImage) -> Self::Feature {
+        match masked_mean_color(img, mask) {
+            Some(mean) => {
+                let fill = image::Rgb(mean.map(|channel| channel.round() as u8));
+                self.extract(&RgbImage::from_fn(img.width(), img.height(), |x, y| {
+                    if mask.get_pixel(x, y).0 == [0] {
+                        fill
+                    } else {
+                        *img.get_pixel(x, y)
+                    }
+                }))
+            }
+            None => self.extract(img),
+        }
+    }
+
+    /// Compute the distance between the descriptors of a tile and of a master cell.
+    fn distance(&self, tile: &Self::Feature, cell: &Self::Feature) -> i64;
+}
+
+/// The mean color of the pixels of the `img` within the `mask`, `None` if the `mask` is empty.
+fn masked_mean_color(img: &RgbImage, mask: &GrayImage) -> Option<[f64; 3]> {
+    let (sum, count) = img
+        .pixels()
+        .zip(mask.pixels())
+        .filter(|(_, m)| m.0 != [0])
+        .fold(([0.; 3], 0), |(sum, count), (p, _)| {
+            (
+                [
+                    sum[0] + p[0] as f64,
+                    sum[1] + p[1] as f64,
+                    sum[2] + p[2] as f64,
+                ],
+                count + 1,
+            )
+        });
+    (count > 0).then(|| sum.map(|channel| channel / count as f64))
+}
+
+/// Describes the images by their mean color.
+///
+/// The distance is the same as the [`avg_color`](crate::metrics::avg_color) metric, up to
+/// rounding, but the mean colors are only computed once per image. It is rounded up, so that it
+/// still satisfies the triangle inequality.
+#[derive(Debug, Default, Clone)]
+pub struct MeanColor;
+
+impl FeatureExtractor for MeanColor {
+    type Feature = [f64; 3];
+
+    fn extract(&self, img: &RgbImage) -> Self::Feature {
+        let n_pixels = (img.width() * img.height()) as f64;
+        img.pixels()
+            .fold([0.; 3], |sum, p| {
+                [
+                    sum[0] + p[0] as f64,
+                    sum[1] + p[1] as f64,
+                    sum[2] + p[2] as f64,
+                ]
+            })
+            .map(|channel| channel / n_pixels)
+    }
+
+    fn extract_masked(&self, img: &RgbImage, mask: &GrayImage) -> Self::Feature {
+        masked_mean_color(img, mask).unwrap_or_else(|| self.extract(img))
+    }
+
+    fn distance(&self, tile: &Self::Feature, cell: &Self::Feature) -> i64 {
+        let diff: f64 = tile.iter().zip(cell).map(|(t, c)| (t - c).abs()).sum();
+        // Rounding down would break the triangle inequality the nearest tile search relies on
+        (diff / 3.).ceil() as i64
+    }
+}
+
+/// Describes the images by a downsampled thumbnail, which are compared using a [`Metric`].
+///
+/// Keeps the coarse layout of the images while making the distance computation much cheaper.
+#[derive(Debug, Clone)]
+pub struct Thumbnail<M: Metric> {
+    size: (u32, u32),
+    metric: M,
+}
+
+impl Default for Thumbnail<fn(&RgbImage, &RgbImage) -> i64> {
+    fn default() -> Self {
+        Self {
+            size: (8, 8),
+            metric: norm_l1,
+        }
+    }
+}
+
+impl<M: Metric> Thumbnail<M> {
+    /// Creates a new instance of the Thumbnail struct.
+    ///
+    /// # Arguments
+    /// - `size`: The width and height of the thumbnails.
+    /// - `metric`: The metric used to compare the thumbnails.
+    pub fn new(size: (u32, u32), metric: M) -> Self {
+        Self { size, metric }
+    }
+}
+
+impl<M: Metric> FeatureExtractor for Thumbnail<M> {
+    type Feature = RgbImage;
+
+    fn extract(&self, img: &RgbImage) -> Self::Feature {
+        image::imageops::resize(
+            img,
+            self.size.0,
+            self.size.1,
+            image::imageops::FilterType::Triangle,
+        )
+    }
+
+    fn distance(&self, tile: &Self::Feature, cell: &Self::Feature) -> i64 {
+        self.metric.distance(tile, cell)
+    }
+}
+
+/// Describes the images by their color histogram.
+///
+/// Each color channel is quantized in `bins` bins, and the pixels are counted in the resulting
+/// `bins³` color bins. The distance is the L1 norm of the difference of the histograms, the
+/// spatial distribution of the colors is ignored.
+#[derive(Debug, Clone)]
+pub struct Histogram {
+    bins: u32,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self { bins: 4 }
+    }
+}
+
+impl Histogram {
+    /// Creates a new instance of the Histogram struct.
+    ///
+    /// # Arguments
+    /// - `bins`: The number of bins per color channel, between 1 and 256.
+    pub fn new(bins: u32) -> Self {
+        Self {
+            bins: bins.clamp(1, 256),
+        }
+    }
+}
+
+impl FeatureExtractor for Histogram {
+    type Feature = Vec<i64>;
+
+    fn extract(&self, img: &RgbImage) -> Self::Feature {
+        let bins = self.bins as usize;
+        let mut histogram = vec![0; bins.pow(3)];
+        for pixel in img.pixels() {
+            let [r, g, b] = pixel.0.map(|channel| channel as usize * bins / 256);
+            histogram[(r * bins + g) * bins + b] += 1;
+        }
+        histogram
+    }
+
+    /// Only counts the pixels within the `mask`, the counts are scaled to the number of pixels
+    /// of the `img` so that they are comparable to the histograms of the tiles.
+    fn extract_masked(&self, img: &RgbImage, mask: &GrayImage) -> Self::Feature {
+        let bins = self.bins as usize;
+        let mut histogram = vec![0; bins.pow(3)];
+        for (pixel, _) in img.pixels().zip(mask.pixels()).filter(|(_, m)| m.0 != [0]) {
+            let [r, g, b] = pixel.0.map(|channel| channel as usize * bins / 256);
+            histogram[(r * bins + g) * bins + b] += 1;
+        }
+        let n_masked: i64 = histogram.iter().sum();
+        if n_masked == 0 {
+            return self.extract(img);
+        }
+        let n_pixels = (img.width() * img.height()) as i64;
+        histogram
+            .into_iter()
+            .map(|count| (count * n_pixels + n_masked / 2) / n_masked)
+            .collect()
+    }
+
+    fn distance(&self, tile: &Self::Feature, cell: &Self::Feature) -> i64 {
+        tile.iter().zip(cell).map(|(t, c)| (t - c).abs()).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::avg_color;
+
+    fn test_images() -> (RgbImage, RgbImage) {
+        let img1 = RgbImage::from_fn(16, 16, |x, y| {
+            image::Rgb([(x * 16) as u8, (y * 16) as u8, 0])
+        });
+        let img2 = RgbImage::from_pixel(16, 16, image::Rgb([255, 128, 12]));
+        (img1, img2)
+    }
+
+    #[test]
+    fn test_mean_color() {
+        let (img1, img2) = test_images();
+        let extractor = MeanColor;
+        let distance = extractor.distance(&extractor.extract(&img1), &extractor.extract(&img2));
+        // The mean colour difference of 155 / 3 is rounded up
+        assert_eq!(distance, avg_color(&img1, &img2) + 1);
+        assert_eq!(
+            extractor.distance(&extractor.extract(&img1), &extractor.extract(&img1)),
+            0
+        );
+
+        // The triangle inequality holds across the rounding
+        let (a, b, c) = ([0., 0., 0.], [1.5, 0., 0.], [3., 0., 0.]);
+        assert!(
+            extractor.distance(&a, &c) <= extractor.distance(&a, &b) + extractor.distance(&b, &c)
+        );
+    }
+
+    #[test]
+    fn test_thumbnail() {
+        let (img1, img2) = test_images();
+        let extractor = Thumbnail::new((4, 4), norm_l1);
+        let thumbnail = extractor.extract(&img1);
+        assert_eq!(thumbnail.dimensions(), (4, 4));
+        assert_eq!(extractor.distance(&thumbnail, &thumbnail), 0);
+        assert!(extractor.distance(&thumbnail, &extractor.extract(&img2)) > 0);
+    }
+
+    #[test]
+    fn test_histogram() {
+        let (img1, img2) = test_images();
+        let extractor = Histogram::new(2);
+        let histogram = extractor.extract(&img2);
+        assert_eq!(histogram.len(), 8);
+        // all the pixels are in the (1, 1, 0) bin
+        assert_eq!(histogram[6], 16 * 16);
+        assert_eq!(histogram.iter().sum::<i64>(), 16 * 16);
+        assert_eq!(extractor.distance(&histogram, &histogram), 0);
+        assert!(extractor.distance(&extractor.extract(&img1), &histogram) > 0);
+    }
+
+    #[test]
+    fn test_extract_masked() {
+        let (img1, img2) = test_images();
+        // The left half of the image is img2, the right half is outside of the mask
+        let img = RgbImage::from_fn(16, 16, |x, y| {
+            if x < 8 {
+                *img2.get_pixel(x, y)
+            } else {
+                *img1.get_pixel(x, y)
+            }
+        });
+        let mask = GrayImage::from_fn(16, 16, |x, _| image::Luma([if x < 8 { 255 } else { 0 }]));
+
+        // The pixels outside of the mask are ignored
+        assert_eq!(MeanColor.extract_masked(&img, &mask), [255., 128., 12.]);
+        let histogram = Histogram::new(2);
+        assert_eq!(
+            histogram.extract_masked(&img, &mask),
+            histogram.extract(&img2)
+        );
+        let thumbnail = Thumbnail::new((4, 4), norm_l1);
+        assert_eq!(
+            thumbnail.extract_masked(&img, &mask),
+            thumbnail.extract(&img2)
+        );
+
+        // An empty mask falls back to the whole image
+        let empty = GrayImage::new(16, 16);
+        assert_eq!(
+            MeanColor.extract_masked(&img, &empty),
+            MeanColor.extract(&img)
+        );
+        assert_eq!(
+            histogram.extract_masked(&img, &empty),
+            histogram.extract(&img)
+        );
+    }
+}
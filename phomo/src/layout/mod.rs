@@ -0,0 +1,694 @@
+//! The layouts of the master cells, and their geometry in the master image.
+use image::{imageops, GenericImage, GenericImageView, GrayImage, Luma, RgbImage};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::error::PhomoError;
+use crate::metrics::luminance;
+
+mod voronoi;
+
+/// The shape of a master cell, inscribed in the rectangle of its [`Region`].
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Shape {
+    /// The whole rectangle.
+    #[default]
+    Rectangle,
+    /// A pointy topped hexagon, with its top and bottom corners at the middle of the top and
+    /// bottom sides of the rectangle, and its other corners at a quarter of the height of the
+    /// left and right sides from the top and bottom.
+    Hexagon,
+    /// A polygon, given by its vertices in the master image, in pixels. The pixels whose centre
+    /// is inside the polygon, with the even-odd rule, belong to the cell.
+    Polygon(Vec<(f64, f64)>),
+}
+
+impl Shape {
+    /// Whether the shape is the whole rectangle.
+    pub fn is_rectangle(&self) -> bool {
+        *self == Shape::Rectangle
+    }
+}
+
+/// The rectangle of the master image covered by a master cell, in pixels, and the shape of the
+/// cell within it. The rectangle can extend past the edges of the master image, only the pixels
+/// within the image belong to the cell.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Region {
+    /// The horizontal position of the top left corner.
+    pub x: i32,
+    /// The vertical position of the top left corner.
+    pub y: i32,
+    /// The width of the region.
+    pub width: u32,
+    /// The height of the region.
+    pub height: u32,
+    /// The shape of the cell within the region.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub shape: Shape,
+}
+
+impl Region {
+    /// Creates a new instance of the Region struct, with a [`Shape::Rectangle`].
+    pub fn new(x: i32, y: i32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+            shape: Shape::Rectangle,
+        }
+    }
+
+    /// Creates a new instance of the Region struct, with a [`Shape::Polygon`] of the `vertices`.
+    /// The region is the smallest rectangle with the aspect ratio of the `cell_size` which
+    /// covers the polygon, and is centred on it, so the tiles are cropped to the polygon
+    /// instead of being stretched.
+    pub fn polygon(vertices: Vec<(f64, f64)>, cell_size: (u32, u32)) -> Self {
+        let (min_x, min_y, max_x, max_y) = vertices.iter().fold(
+            (f64::MAX, f64::MAX, f64::MIN, f64::MIN),
+            |(min_x, min_y, max_x, max_y), &(x, y)| {
+                (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y))
+            },
+        );
+        let (x, y) = (min_x.floor() as i64, min_y.floor() as i64);
+        let width = (max_x.ceil() as i64 - x).max(1) as u64;
+        let height = (max_y.ceil() as i64 - y).max(1) as u64;
+        let (cell_width, cell_height) = (cell_size.0.max(1) as u64, cell_size.1.max(1) as u64);
+        let (cover_width, cover_height) = if width * cell_height >= height * cell_width {
+            (width, (width * cell_height).div_ceil(cell_width))
+        } else {
+            ((height * cell_width).div_ceil(cell_height), height)
+        };
+        Self {
+            x: (x - ((cover_width - width) / 2) as i64) as i32,
+            y: (y - ((cover_height - height) / 2) as i64) as i32,
+            width: cover_width as u32,
+            height: cover_height as u32,
+            shape: Shape::Polygon(vertices),
+        }
+    }
+
+    /// Whether the centre of the pixel at `(x, y)`, relative to the top left corner of the
+    /// region, is inside the shape of the cell.
+    ///
+    /// The hexagons are tested with integer arithmetic, and the sides of the polygons are
+    /// oriented the same way whichever polygon they belong to, so that the pixels on the sides
+    /// shared by neighbouring cells belong to exactly one of them.
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        if x >= self.width || y >= self.height {
+            return false;
+        }
+        match &self.shape {
+            Shape::Rectangle => true,
+            Shape::Hexagon => {
+                let (width, height) = (self.width as i64, self.height as i64);
+                // The centre of the pixel, in half pixels
+                let (x, y) = (2 * x as i64 + 1, 2 * y as i64 + 1);
+                let slope = height * (x - width).abs();
+                2 * width * y >= slope && 2 * width * y < 4 * width * height - slope
+            }
+            Shape::Polygon(vertices) => {
+                let (x, y) = (
+                    self.x as f64 + x as f64 + 0.5,
+                    self.y as f64 + y as f64 + 0.5,
+                );
+                let sides = vertices.iter().zip(vertices.iter().cycle().skip(1));
+                sides
+                    .filter(|&(&start, &end)| {
+                        let (top, bottom) = if (start.1, start.0) <= (end.1, end.0) {
+                            (start, end)
+                        } else {
+                            (end, start)
+                        };
+                        top.1 <= y
+                            && y < bottom.1
+                            && x < top.0 + (y - top.1) * (bottom.0 - top.0) / (bottom.1 - top.1)
+                    })
+                    .count()
+                    % 2
+                    == 1
+            }
+        }
+    }
+
+    /// Whether the whole rectangle of the region is filled by the cell, within an image of size
+    /// `bounds`.
+    pub fn is_filled(&self, bounds: (u32, u32)) -> bool {
+        self.shape.is_rectangle()
+            && self.x >= 0
+            && self.y >= 0
+            && self.x as i64 + self.width as i64 <= bounds.0 as i64
+            && self.y as i64 + self.height as i64 <= bounds.1 as i64
+    }
+
+    /// The pixels of the cell within an image of size `bounds`, relative to the top left corner
+    /// of the region.
+    pub fn pixels(&self, bounds: (u32, u32)) -> impl Iterator<Item = (u32, u32)> + '_ {
+        let in_bounds = |start: i32, size: u32, bound: u32| {
+            (start.max(0) as i64 - start as i64) as u32
+                ..(bound as i64 - start as i64).clamp(0, size as i64) as u32
+        };
+        let xs = in_bounds(self.x, self.width, bounds.0);
+        in_bounds(self.y, self.height, bounds.1)
+            .flat_map(move |y| xs.clone().map(move |x| (x, y)))
+            .filter(|&(x, y)| self.contains(x, y))
+    }
+
+    /// The position of the pixel at `(x, y)`, relative to the top left corner of the region, in
+    /// the image.
+    pub(crate) fn absolute(&self, x: u32, y: u32) -> (u32, u32) {
+        ((self.x + x as i32) as u32, (self.y + y as i32) as u32)
+    }
+
+    /// Whether the `other` region is within `radius` cells of this region. The gap between the
+    /// two regions is measured in the size of the smaller one, so on a uniform grid it is the
+    /// number of cells between them horizontally and vertically.
+    pub fn is_within(&self, other: &Region, radius: u32) -> bool {
+        let gap = |start: i32, size: u32, other_start: i32, other_size: u32| {
+            let (start, other_start) = (start as i64, other_start as i64);
+            (other_start - start - size as i64).max(start - other_start - other_size as i64)
+        };
+        let gap_x = gap(self.x, self.width, other.x, other.width);
+        let gap_y = gap(self.y, self.height, other.y, other.height);
+        gap_x < radius as i64 * self.width.min(other.width) as i64
+            && gap_y < radius as i64 * self.height.min(other.height) as i64
+    }
+}
+
+/// The measure of the detail of a region, which decides whether a [`Quadtree`] subdivides it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Detail {
+    /// The variance of the pixel values, averaged over the channels.
+    Variance,
+    /// The mean absolute difference of the luminance of horizontally and vertically adjacent
+    /// pixels.
+    EdgeEnergy,
+}
+
+/// The configuration of a quadtree layout, which subdivides the detailed cells of the grid into
+/// smaller cells, and keeps the flat cells large.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quadtree {
+    /// The maximum number of subdivisions of a grid cell, each halving the size of the cells.
+    pub max_depth: u32,
+    /// The cells whose detail exceeds the threshold are subdivided.
+    pub threshold: f64,
+    /// The measure of the detail of the cells.
+    pub detail: Detail,
+}
+
+impl Default for Quadtree {
+    fn default() -> Self {
+        Self {
+            max_depth: 2,
+            threshold: 400.0,
+            detail: Detail::Variance,
+        }
+    }
+}
+
+/// The configuration of a Voronoi layout, whose cells are the Voronoi cells of seeds drawn in
+/// the master image. The seeds are drawn more densely along the edges of the image, so the
+/// cells follow its contours, like the pieces of a stained-glass window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Voronoi {
+    /// The seed of the random number generator which draws the seeds of the cells.
+    pub seed: u64,
+    /// How much more likely the seeds are to be drawn on the edges of the image. At 0 the seeds
+    /// are drawn uniformly, at 1 a pixel with the mean edge energy of the image is twice as
+    /// likely to be drawn as a flat pixel.
+    pub edge_weight: f64,
+}
+
+impl Default for Voronoi {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            edge_weight: 4.0,
+        }
+    }
+}
+
+/// The layout of the master cells.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Layout {
+    /// A uniform grid of cells of the same size.
+    #[default]
+    Grid,
+    /// A grid whose cells are subdivided where the master image is detailed.
+    Quadtree(Quadtree),
+    /// A grid of pointy topped hexagons, inscribed in the grid cells. The rows overlap by a
+    /// quarter of the cell height and every other row is offset by half a cell, so there are
+    /// more rows than in the grid, and the hexagons at the edges are cut by the master image.
+    Hexagonal,
+    /// A running bond of grid cells, where every other row is offset by half a cell. The cells
+    /// at the ends of the offset rows are cut by the master image.
+    Brick,
+    /// The Voronoi cells of as many seeds as there are grid cells.
+    Voronoi(Voronoi),
+    /// Arbitrary polygonal cells, given by their vertices in the master image, in pixels, see
+    /// [`Shape::Polygon`]. The coordinates are those of the master image once it is cropped to
+    /// fit the grid.
+    Polygons(Vec<Vec<(f64, f64)>>),
+}
+
+impl Layout {
+    /// The regions of the master cells of the `img`, in row major order of their top left
+    /// corners. The regions of a [`Layout::Hexagonal`] have an even width and a height which is a
+    /// multiple of 4, so that the hexagons tile the image exactly. The regions of the
+    /// [`Layout::Voronoi`] cells are in row major order of their seeds, and those of the
+    /// [`Layout::Polygons`] in the order of the polygons.
+    ///
+    /// # Arguments
+    /// - `img`: The master image, whose dimensions are multiples of the `cell_size`.
+    /// - `grid_size`: The number of grid cells horizontally and vertically.
+    /// - `cell_size`: The size of the grid cells, in pixels.
+    pub(crate) fn regions(
+        &self,
+        img: &RgbImage,
+        grid_size: (u32, u32),
+        cell_size: (u32, u32),
+    ) -> Vec<Region> {
+        let (grid_width, grid_height) = grid_size;
+        let (cell_width, cell_height) = cell_size;
+        let grid = (0..grid_height).flat_map(|y| {
+            (0..grid_width).map(move |x| {
+                Region::new(
+                    (x * cell_width) as i32,
+                    (y * cell_height) as i32,
+                    cell_width,
+                    cell_height,
+                )
+            })
+        });
+        match self {
+            Layout::Grid => grid.collect(),
+            Layout::Quadtree(quadtree) => {
+                let mut regions = Vec::new();
+                grid.for_each(|region| quadtree.subdivide(img, region, 0, &mut regions));
+                regions.sort_by_key(|region| (region.y, region.x));
+                regions
+            }
+            Layout::Brick => (0..grid_height)
+                .flat_map(|y| {
+                    let offset = if y % 2 == 1 { cell_width / 2 } else { 0 };
+                    let n_cells = if offset > 0 {
+                        grid_width + 1
+                    } else {
+                        grid_width
+                    };
+                    (0..n_cells).map(move |x| {
+                        Region::new(
+                            (x * cell_width) as i32 - offset as i32,
+                            (y * cell_height) as i32,
+                            cell_width,
+                            cell_height,
+                        )
+                    })
+                })
+                .collect(),
+            Layout::Hexagonal => {
+                let width = (cell_width & !1).max(2);
+                let height = (cell_height & !3).max(4);
+                let (img_width, img_height) = (img.width() as i32, img.height() as i32);
+                // The rows start a quarter of a cell above the image, so the top corners of
+                // the first row are cut off instead of leaving gaps between them
+                let pitch = (height - height / 4) as i32;
+                let rows = (0..)
+                    .map(|row| (row, row * pitch - height as i32 / 4))
+                    .take_while(|&(_, y)| y < img_height);
+                rows.flat_map(|(row, y)| {
+                    let offset = if row % 2 == 1 { -(width as i32 / 2) } else { 0 };
+                    (0..)
+                        .map(move |column| offset + column * width as i32)
+                        .take_while(move |&x| x < img_width)
+                        .map(move |x| Region {
+                            shape: Shape::Hexagon,
+                            ..Region::new(x, y, width, height)
+                        })
+                })
+                .filter(|region| region.pixels(img.dimensions()).next().is_some())
+                .collect()
+            }
+            Layout::Voronoi(config) => {
+                let n_seeds = (grid_width * grid_height) as usize;
+                let mut seeds = voronoi::seeds(img, n_seeds, config.edge_weight, config.seed);
+                seeds.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.total_cmp(&b.0)));
+                voronoi::cells(&seeds, img.dimensions())
+                    .into_iter()
+                    .map(|vertices| Region::polygon(vertices, cell_size))
+                    .collect()
+            }
+            Layout::Polygons(polygons) => polygons
+                .iter()
+                .map(|vertices| Region::polygon(vertices.clone(), cell_size))
+                .collect(),
+        }
+    }
+}
+
+impl Quadtree {
+    /// Push the `region` to the `regions`, or its quadrants when it is detailed enough.
+    fn subdivide(&self, img: &RgbImage, region: Region, depth: u32, regions: &mut Vec<Region>) {
+        if depth >= self.max_depth
+            || region.width < 2
+            || region.height < 2
+            || self.detail(img, &region) <= self.threshold
+        {
+            regions.push(region);
+            return;
+        }
+        let (left, top) = (region.width / 2, region.height / 2);
+        let (right, bottom) = (region.width - left, region.height - top);
+        let (x, y) = (region.x + left as i32, region.y + top as i32);
+        for quadrant in [
+            Region::new(region.x, region.y, left, top),
+            Region::new(x, region.y, right, top),
+            Region::new(region.x, y, left, bottom),
+            Region::new(x, y, right, bottom),
+        ] {
+            self.subdivide(img, quadrant, depth + 1, regions);
+        }
+    }
+
+    /// The detail of the `region` of the `img`.
+    fn detail(&self, img: &RgbImage, region: &Region) -> f64 {
+        let view = img.view(
+            region.x as u32,
+            region.y as u32,
+            region.width,
+            region.height,
+        );
+        let n_pixels = (region.width * region.height) as f64;
+        match self.detail {
+            Detail::Variance => {
+                let mut sums = [0.0; 3];
+                let mut squares = [0.0; 3];
+                for (_, _, pixel) in view.pixels() {
+                    for channel in 0..3 {
+                        let value = pixel.0[channel] as f64;
+                        sums[channel] += value;
+                        squares[channel] += value * value;
+                    }
+                }
+                (0..3)
+                    .map(|channel| {
+                        let mean = sums[channel] / n_pixels;
+                        squares[channel] / n_pixels - mean * mean
+                    })
+                    .sum::<f64>()
+                    / 3.0
+            }
+            Detail::EdgeEnergy => {
+                let lum = |x, y| luminance(&view.get_pixel(x, y).0);
+                let mut energy = 0;
+                for y in 0..region.height {
+                    for x in 0..region.width {
+                        if x + 1 < region.width {
+                            energy += (lum(x + 1, y) - lum(x, y)).abs();
+                        }
+                        if y + 1 < region.height {
+                            energy += (lum(x, y + 1) - lum(x, y)).abs();
+                        }
+                    }
+                }
+                energy as f64 / n_pixels
+            }
+        }
+    }
+}
+
+/// The cells within `radius` cells of each of the `regions`, see [`Region::is_within`].
+pub(crate) fn neighbours(regions: &[Region], radius: u32) -> Vec<Vec<usize>> {
+    regions
+        .iter()
+        .enumerate()
+        .map(|(cell, region)| {
+            regions
+                .iter()
+                .enumerate()
+                .filter(|&(other_cell, other_region)| {
+                    other_cell != cell && region.is_within(other_region, radius)
+                })
+                .map(|(other_cell, _)| other_cell)
+                .collect()
+        })
+        .collect()
+}
+
+/// Draw the `tile` onto the `region` of the `img`, resizing it when it doesn't have the size of
+/// the region, and clipping it to the shape of the region and to the image.
+pub(crate) fn draw_tile(
+    img: &mut RgbImage,
+    tile: &RgbImage,
+    region: &Region,
+) -> Result<(), PhomoError> {
+    let resized;
+    let tile = if tile.dimensions() == (region.width, region.height) {
+        tile
+    } else {
+        resized = imageops::resize(
+            tile,
+            region.width,
+            region.height,
+            imageops::FilterType::Triangle,
+        );
+        &resized
+    };
+    if region.is_filled(img.dimensions()) {
+        img.copy_from(tile, region.x as u32, region.y as u32)?;
+    } else {
+        for (x, y) in region.pixels(img.dimensions()) {
+            let (img_x, img_y) = region.absolute(x, y);
+            img.put_pixel(img_x, img_y, *tile.get_pixel(x, y));
+        }
+    }
+    Ok(())
+}
+
+/// Extract the rectangle of the `region` of the `img`. The pixels outside of the image repeat
+/// the nearest pixel of the image, the pixels outside of the cell are excluded by its [`mask`].
+pub(crate) fn sample(img: &RgbImage, region: &Region) -> RgbImage {
+    let (width, height) = img.dimensions();
+    if region.x >= 0
+        && region.y >= 0
+        && region.x as i64 + region.width as i64 <= width as i64
+        && region.y as i64 + region.height as i64 <= height as i64
+    {
+        return img
+            .view(
+                region.x as u32,
+                region.y as u32,
+                region.width,
+                region.height,
+            )
+            .to_image();
+    }
+    RgbImage::from_fn(region.width, region.height, |x, y| {
+        let img_x = (region.x as i64 + x as i64).clamp(0, width as i64 - 1);
+        let img_y = (region.y as i64 + y as i64).clamp(0, height as i64 - 1);
+        *img.get_pixel(img_x as u32, img_y as u32)
+    })
+}
+
+/// The mask of the pixels of the `region` which belong to the cell, within an image of size
+/// `bounds`, or `None` when the cell fills the region.
+pub(crate) fn mask(region: &Region, bounds: (u32, u32)) -> Option<GrayImage> {
+    if region.is_filled(bounds) {
+        return None;
+    }
+    let mut mask = GrayImage::new(region.width, region.height);
+    for (x, y) in region.pixels(bounds) {
+        mask.put_pixel(x, y, Luma([255]));
+    }
+    Some(mask)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    /// A flat image with a detailed top left corner.
+    fn create_test_image() -> RgbImage {
+        RgbImage::from_fn(64, 64, |x, y| {
+            if x < 16 && y < 16 && (x + y) % 2 == 0 {
+                Rgb([255, 255, 255])
+            } else {
+                Rgb([0, 0, 0])
+            }
+        })
+    }
+
+    #[test]
+    fn test_grid_regions() {
+        let img = create_test_image();
+        let regions = Layout::Grid.regions(&img, (2, 4), (32, 16));
+        assert_eq!(regions.len(), 8);
+        assert_eq!(regions[3], Region::new(32, 16, 32, 16));
+    }
+
+    #[test]
+    fn test_quadtree_regions() {
+        let img = create_test_image();
+        for detail in [Detail::Variance, Detail::EdgeEnergy] {
+            let layout = Layout::Quadtree(Quadtree {
+                max_depth: 2,
+                threshold: 1.0,
+                detail,
+            });
+            let regions = layout.regions(&img, (2, 2), (32, 32));
+            // The detailed 16x16 corner is split into 4 cells of 8x8, its neighbours in the top
+            // left cell are 16x16, the other grid cells are kept whole
+            assert_eq!(regions.len(), 4 + 3 + 3);
+            assert_eq!(regions[0], Region::new(0, 0, 8, 8));
+            assert!(regions.contains(&Region::new(16, 16, 16, 16)));
+            assert!(regions.contains(&Region::new(32, 32, 32, 32)));
+            // The regions cover the image
+            let area: u32 = regions
+                .iter()
+                .map(|region| region.width * region.height)
+                .sum();
+            assert_eq!(area, 64 * 64);
+        }
+    }
+
+    #[test]
+    fn test_brick_regions() {
+        let img = create_test_image();
+        let regions = Layout::Brick.regions(&img, (2, 4), (32, 16));
+        // The offset rows have a partial cell at each end
+        assert_eq!(regions.len(), 2 * 2 + 2 * 3);
+        assert_eq!(regions[2], Region::new(-16, 16, 32, 16));
+        assert_eq!(regions[4], Region::new(48, 16, 32, 16));
+        assert!(!regions[2].is_filled(img.dimensions()));
+        assert!(regions[3].is_filled(img.dimensions()));
+        assert_eq!(regions[2].pixels(img.dimensions()).count(), 16 * 16);
+        let area: usize = regions
+            .iter()
+            .map(|region| region.pixels(img.dimensions()).count())
+            .sum();
+        assert_eq!(area, 64 * 64);
+    }
+
+    #[test]
+    fn test_hexagonal_regions() {
+        let img = create_test_image();
+        let regions = Layout::Hexagonal.regions(&img, (4, 4), (16, 16));
+        assert!(regions.iter().all(|region| region.shape == Shape::Hexagon));
+        // The rows start a quarter of a cell above the image, every other row is offset
+        assert_eq!(
+            regions[0],
+            Region {
+                shape: Shape::Hexagon,
+                ..Region::new(0, -4, 16, 16)
+            }
+        );
+        assert_eq!((regions[4].x, regions[4].y), (-8, 8));
+
+        // Each pixel of the image belongs to exactly one hexagon
+        let mut counts = vec![0; 64 * 64];
+        for region in regions.iter() {
+            for (x, y) in region.pixels(img.dimensions()) {
+                let (img_x, img_y) = region.absolute(x, y);
+                counts[(img_y * 64 + img_x) as usize] += 1;
+            }
+        }
+        assert!(counts.iter().all(|&count| count == 1));
+    }
+
+    #[test]
+    fn test_voronoi_regions() {
+        let img = create_test_image();
+        let layout = Layout::Voronoi(Voronoi::default());
+        let regions = layout.regions(&img, (4, 4), (16, 16));
+        assert_eq!(regions.len(), 16);
+        assert_eq!(regions, layout.regions(&img, (4, 4), (16, 16)));
+        // The regions have the aspect ratio of the cells
+        assert!(regions.iter().all(
+            |region| region.width == region.height && matches!(region.shape, Shape::Polygon(_))
+        ));
+
+        // Each pixel of the image belongs to exactly one polygon
+        let mut counts = vec![0; 64 * 64];
+        for region in regions.iter() {
+            for (x, y) in region.pixels(img.dimensions()) {
+                let (img_x, img_y) = region.absolute(x, y);
+                counts[(img_y * 64 + img_x) as usize] += 1;
+            }
+        }
+        assert!(counts.iter().all(|&count| count == 1));
+    }
+
+    #[test]
+    fn test_polygon_region() {
+        // A triangle, covered by a 2:1 rectangle centred on it
+        let region = Region::polygon(vec![(10.0, 10.0), (20.5, 10.0), (10.0, 14.0)], (32, 16));
+        assert_eq!(
+            (region.x, region.y, region.width, region.height),
+            (10, 9, 11, 6)
+        );
+        assert!(region.contains(0, 1));
+        assert!(region.contains(8, 1));
+        assert!(!region.contains(9, 1));
+        assert!(region.contains(0, 4));
+        assert!(!region.contains(1, 4));
+        assert!(!region.contains(0, 0));
+        assert!(!region.contains(0, 5));
+        assert_eq!(region.pixels((64, 64)).count(), 9 + 7 + 4 + 1);
+
+        let mask = mask(&region, (64, 64)).unwrap();
+        assert_eq!(mask.get_pixel(0, 1).0, [255]);
+        assert_eq!(mask.get_pixel(0, 0).0, [0]);
+    }
+
+    #[test]
+    fn test_draw_sample_and_mask_hexagon() {
+        let img = create_test_image();
+        let region = Region {
+            shape: Shape::Hexagon,
+            ..Region::new(-8, 0, 16, 16)
+        };
+        let cell = sample(&img, &region);
+        assert_eq!(cell.dimensions(), (16, 16));
+        // The pixels outside of the image repeat its edge
+        assert_eq!(cell.get_pixel(0, 8), img.get_pixel(0, 8));
+        assert_eq!(cell.get_pixel(9, 1), img.get_pixel(1, 1));
+
+        // Only the pixels of the hexagon within the image are in the mask
+        let mask = mask(&region, img.dimensions()).unwrap();
+        assert_eq!(mask.dimensions(), (16, 16));
+        assert_eq!(
+            mask.pixels().filter(|pixel| pixel.0 == [255]).count(),
+            region.pixels(img.dimensions()).count()
+        );
+        assert_eq!(mask.get_pixel(7, 8).0, [0]);
+        assert_eq!(mask.get_pixel(8, 8).0, [255]);
+        assert_eq!(mask.get_pixel(15, 0).0, [0]);
+        assert!(super::mask(&Region::new(0, 0, 16, 16), img.dimensions()).is_none());
+
+        let mut mosaic = RgbImage::new(64, 64);
+        let tile = RgbImage::from_pixel(16, 16, Rgb([1, 2, 3]));
+        draw_tile(&mut mosaic, &tile, &region).unwrap();
+        assert_eq!(mosaic.get_pixel(0, 8).0, [1, 2, 3]);
+        // Outside of the hexagon, and of the region
+        assert_eq!(mosaic.get_pixel(7, 1).0, [0, 0, 0]);
+        assert_eq!(mosaic.get_pixel(8, 8).0, [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_is_within() {
+        let region = Region::new(32, 32, 16, 16);
+        assert!(region.is_within(&Region::new(48, 48, 16, 16), 1));
+        assert!(!region.is_within(&Region::new(64, 32, 16, 16), 1));
+        assert!(region.is_within(&Region::new(64, 32, 16, 16), 2));
+        // The gap is measured in the size of the smaller region
+        assert!(!region.is_within(&Region::new(56, 32, 8, 8), 1));
+        assert!(region.is_within(&Region::new(48, 32, 8, 8), 1));
+    }
+}
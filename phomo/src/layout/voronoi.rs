@@ -0,0 +1,259 @@
+//! The Voronoi tessellation of the master image, seeded by sampling the edges of the image.
+use std::collections::HashSet;
+
+use image::RgbImage;
+
+use crate::metrics::luminance;
+use crate::solvers::SplitMix64;
+
+/// A side of the Voronoi cell, either a side of the image, numbered clockwise from the top, or
+/// the bisector with another seed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Side {
+    Image(u8),
+    Seed(usize),
+}
+
+/// Draw `n_seeds` distinct pixel centres of the `img`, with a probability which grows with the
+/// edge energy of the pixel. An `edge_weight` of 0 draws the pixels uniformly.
+pub(crate) fn seeds(
+    img: &RgbImage,
+    n_seeds: usize,
+    edge_weight: f64,
+    seed: u64,
+) -> Vec<(f64, f64)> {
+    let (width, height) = img.dimensions();
+    let lum = |x: u32, y: u32| luminance(&img.get_pixel(x, y).0);
+    let energy = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let dx = if x + 1 < width {
+                (lum(x + 1, y) - lum(x, y)).abs()
+            } else {
+                0
+            };
+            let dy = if y + 1 < height {
+                (lum(x, y + 1) - lum(x, y)).abs()
+            } else {
+                0
+            };
+            (dx + dy) as f64
+        })
+        .collect::<Vec<_>>();
+    let mean_energy = (energy.iter().sum::<f64>() / energy.len().max(1) as f64).max(f64::EPSILON);
+
+    // The cumulative density of the pixels, sampled by bisection
+    let mut total = 0.0;
+    let cumulative = energy
+        .iter()
+        .map(|energy| {
+            total += 1.0 + edge_weight.max(0.0) * energy / mean_energy;
+            total
+        })
+        .collect::<Vec<_>>();
+
+    let mut rng = SplitMix64::new(seed);
+    let n_seeds = n_seeds.min(cumulative.len());
+    let mut drawn = HashSet::with_capacity(n_seeds);
+    let mut seeds = Vec::with_capacity(n_seeds);
+    while seeds.len() < n_seeds {
+        let target = rng.unit() * total;
+        let pixel = cumulative
+            .partition_point(|&value| value <= target)
+            .min(cumulative.len() - 1);
+        if drawn.insert(pixel) {
+            let (x, y) = (pixel as u32 % width, pixel as u32 / width);
+            seeds.push((x as f64 + 0.5, y as f64 + 0.5));
+        }
+    }
+    seeds
+}
+
+/// The Voronoi cells of the `seeds` within an image of size `bounds`, as the vertices of convex
+/// polygons.
+///
+/// Each vertex is computed from the seeds and image sides which meet there, in an order which
+/// doesn't depend on the cell, so the cells which share a vertex have the exact same
+/// coordinates for it and the tessellation has no gaps.
+pub(crate) fn cells(seeds: &[(f64, f64)], bounds: (u32, u32)) -> Vec<Vec<(f64, f64)>> {
+    let bounds = (bounds.0 as f64, bounds.1 as f64);
+    (0..seeds.len())
+        .map(|cell| {
+            let seed = seeds[cell];
+            let mut others = (0..seeds.len())
+                .filter(|&other| other != cell)
+                .map(|other| (distance2(seed, seeds[other]), other))
+                .collect::<Vec<_>>();
+            others.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+            let mut sides = (0..4).map(Side::Image).collect::<Vec<_>>();
+            let mut vertices = polygon(seeds, cell, &sides, bounds);
+            for (other_distance2, other) in others {
+                // The bisectors of the further seeds can't cut the cell anymore
+                let radius2 = vertices
+                    .iter()
+                    .map(|&vertex| distance2(seed, vertex))
+                    .fold(0.0, f64::max);
+                if other_distance2 > 4.0 * radius2 {
+                    break;
+                }
+                sides = clip(seeds, cell, other, &sides, &vertices);
+                vertices = polygon(seeds, cell, &sides, bounds);
+            }
+            vertices.dedup();
+            if vertices.len() > 1 && vertices.first() == vertices.last() {
+                vertices.pop();
+            }
+            vertices
+        })
+        .collect()
+}
+
+fn distance2(a: (f64, f64), b: (f64, f64)) -> f64 {
+    (a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)
+}
+
+/// The vertices of the polygon of the `cell`, each one starting the side of the same index.
+fn polygon(
+    seeds: &[(f64, f64)],
+    cell: usize,
+    sides: &[Side],
+    bounds: (f64, f64),
+) -> Vec<(f64, f64)> {
+    (0..sides.len())
+        .map(|i| {
+            let previous = sides[(i + sides.len() - 1) % sides.len()];
+            vertex(seeds, cell, previous, sides[i], bounds)
+        })
+        .collect()
+}
+
+/// Cut the polygon of the `cell` with the bisector of its seed and the seed of the `other` cell,
+/// keeping the side of the `cell`.
+fn clip(
+    seeds: &[(f64, f64)],
+    cell: usize,
+    other: usize,
+    sides: &[Side],
+    vertices: &[(f64, f64)],
+) -> Vec<Side> {
+    let (a, b) = (seeds[cell], seeds[other]);
+    let inside = vertices
+        .iter()
+        .map(|&(x, y)| {
+            (b.0 - a.0) * x + (b.1 - a.1) * y
+                <= (b.0 * b.0 + b.1 * b.1 - a.0 * a.0 - a.1 * a.1) / 2.0
+        })
+        .collect::<Vec<_>>();
+    if inside.iter().all(|&inside| inside) {
+        return sides.to_vec();
+    }
+    let n_sides = sides.len();
+    let mut clipped = Vec::with_capacity(n_sides + 1);
+    for i in 0..n_sides {
+        let (start, end) = (inside[i], inside[(i + 1) % n_sides]);
+        if start || end {
+            clipped.push(sides[i]);
+        }
+        if start && !end {
+            clipped.push(Side::Seed(other));
+        }
+    }
+    clipped
+}
+
+/// The vertex of the `cell` where the `first` and `second` sides meet, computed from the seeds
+/// in increasing order.
+fn vertex(
+    seeds: &[(f64, f64)],
+    cell: usize,
+    first: Side,
+    second: Side,
+    bounds: (f64, f64),
+) -> (f64, f64) {
+    match (first, second) {
+        (Side::Image(first), Side::Image(second)) => {
+            let corner = |side: u8| match side {
+                0 => (None, Some(0.0)),
+                1 => (Some(bounds.0), None),
+                2 => (None, Some(bounds.1)),
+                _ => (Some(0.0), None),
+            };
+            let (x1, y1) = corner(first);
+            let (x2, y2) = corner(second);
+            (x1.or(x2).unwrap_or(0.0), y1.or(y2).unwrap_or(0.0))
+        }
+        (Side::Image(side), Side::Seed(other)) | (Side::Seed(other), Side::Image(side)) => {
+            let (a, b) = (seeds[cell.min(other)], seeds[cell.max(other)]);
+            let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+            let rhs = (b.0 * b.0 + b.1 * b.1 - a.0 * a.0 - a.1 * a.1) / 2.0;
+            match side {
+                0 | 2 => {
+                    let y = if side == 0 { 0.0 } else { bounds.1 };
+                    ((rhs - dy * y) / dx, y)
+                }
+                _ => {
+                    let x = if side == 1 { bounds.0 } else { 0.0 };
+                    (x, (rhs - dx * x) / dy)
+                }
+            }
+        }
+        (Side::Seed(first), Side::Seed(second)) => {
+            let mut cells = [cell, first, second];
+            cells.sort_unstable();
+            circumcentre(seeds[cells[0]], seeds[cells[1]], seeds[cells[2]])
+        }
+    }
+}
+
+/// The centre of the circle through the points `a`, `b` and `c`.
+fn circumcentre(a: (f64, f64), b: (f64, f64), c: (f64, f64)) -> (f64, f64) {
+    let d = 2.0 * (a.0 * (b.1 - c.1) + b.0 * (c.1 - a.1) + c.0 * (a.1 - b.1));
+    let (a2, b2, c2) = (
+        a.0 * a.0 + a.1 * a.1,
+        b.0 * b.0 + b.1 * b.1,
+        c.0 * c.0 + c.1 * c.1,
+    );
+    (
+        (a2 * (b.1 - c.1) + b2 * (c.1 - a.1) + c2 * (a.1 - b.1)) / d,
+        (a2 * (c.0 - b.0) + b2 * (a.0 - c.0) + c2 * (b.0 - a.0)) / d,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    #[test]
+    fn test_seeds() {
+        // The seeds are drawn where the image has edges
+        let img = RgbImage::from_fn(64, 64, |x, _| {
+            if x < 32 {
+                Rgb([0, 0, 0])
+            } else {
+                Rgb([255, 255, 255])
+            }
+        });
+        let seeds = seeds(&img, 16, 1000.0, 0);
+        assert_eq!(seeds.len(), 16);
+        let on_edge = seeds.iter().filter(|&&(x, _)| x == 31.5).count();
+        assert!(on_edge > 8);
+        assert_eq!(seeds, super::seeds(&img, 16, 1000.0, 0));
+    }
+
+    #[test]
+    fn test_cells() {
+        // Two seeds split the image in half
+        let cells = cells(&[(16.0, 32.0), (48.0, 32.0)], (64, 64));
+        assert_eq!(cells.len(), 2);
+        assert_eq!(
+            cells[0],
+            vec![(0.0, 0.0), (32.0, 0.0), (32.0, 64.0), (0.0, 64.0)]
+        );
+        assert_eq!(
+            cells[1],
+            vec![(32.0, 0.0), (64.0, 0.0), (64.0, 64.0), (32.0, 64.0)]
+        );
+    }
+}
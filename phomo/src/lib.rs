@@ -1,13 +1,20 @@
 #![cfg_attr(docsrs, feature(doc_cfg))]
 #![doc =include_str!("../README.md")]
 mod color_match;
+mod cost_matrix;
 mod distance_matrix;
 mod macros;
 mod master;
 mod mosaic;
+mod solve_report;
+mod sparse_distance_matrix;
+mod vp_tree;
 
 pub mod error;
+pub mod features;
+pub mod layout;
 pub mod metrics;
+pub mod progress;
 pub mod solvers;
 pub mod utils;
 
@@ -19,9 +26,31 @@ mod blueprint;
 pub use blueprint::Blueprint;
 
 pub use color_match::ColorMatch;
+pub use cost_matrix::CostMatrix;
 pub use distance_matrix::DistanceMatrix;
-pub use master::Master;
-pub use metrics::{avg_color, luminance_l1, luminance_l2, norm_l1, norm_l2};
-pub use mosaic::Mosaic;
-pub use solvers::{auction::Auction, greedy::Greedy, hungarian::Hungarian, Solve, SolverConfig};
-pub use utils::{read_images_from_dir, read_images_from_dir_cropped, read_images_from_dir_resized};
+pub use features::FeatureExtractor;
+pub use layout::{Detail, Layout, Quadtree, Region, Shape, Voronoi};
+pub use master::{Fit, Master, Padding};
+pub use metrics::{
+    avg_ciede2000, avg_color, avg_oklab, ciede2000, gradient, luminance_l1, luminance_l2, norm_l1,
+    norm_l2, oklab, ssim, Metric,
+};
+pub use mosaic::{Background, Mosaic};
+pub use progress::{CancellationToken, Monitor, Progress};
+pub use solve_report::SolveReport;
+pub use solvers::{
+    annealing::{Annealing, Objective, Schedule, TileEdges},
+    auction::Auction,
+    greedy::Greedy,
+    hungarian::Hungarian,
+    lapjv::Lapjv,
+    local_search::{LocalSearch, Refined},
+    min_cost_flow::MinCostFlow,
+    spacing::{Spaced, Spacing},
+    Solve, SolverConfig,
+};
+pub use sparse_distance_matrix::SparseDistanceMatrix;
+pub use utils::{
+    read_images_from_dir, read_images_from_dir_cropped, read_images_from_dir_resized,
+    read_named_images_from_dir, read_tile_weights,
+};
@@ -1,21 +1,134 @@
 use std::path::Path;
 
 extern crate image;
-use image::{GenericImage, GenericImageView, Rgb, RgbImage};
+use image::{
+    imageops, DynamicImage, GenericImage, GenericImageView, GrayImage, Luma, Rgb, RgbImage,
+};
 use log::info;
 
 use crate::error::{MasterError, PhomoError};
+use crate::layout::{self, Layout, Region};
 use crate::utils;
 
 #[derive(Debug, Clone)]
 pub struct Master {
     /// The master image buffer.
     pub img: RgbImage,
-    /// The grid cells of the master image, where the tiles will be placed.
+    /// The grid cells of the master image, where the tiles will be placed. The cells which don't
+    /// have the [`cell_size`](Master::cell_size) are resized to it, to be compared to the tiles.
     pub cells: Vec<RgbImage>,
+    /// The region of the master image covered by each cell.
+    pub regions: Vec<Region>,
+    /// The mask of the pixels of each cell which belong to its region and to the
+    /// [`mask`](Master::mask), resized to the [`cell_size`](Master::cell_size), or `None` when the
+    /// cell fills its region. Only the masked pixels are compared to the tiles.
+    pub masks: Vec<Option<GrayImage>>,
     /// The size of each grid cell, in pixels.
     pub cell_size: (u32, u32),
+    /// The parts of the master image to tile, see [`with_mask`](Master::with_mask). The other
+    /// parts are filled with the [`Background`](crate::Background) of the mosaic.
+    pub mask: Option<GrayImage>,
     grid_size: (u32, u32),
+    layout: Layout,
+    fit: Fit,
+}
+
+/// How the master image is fitted to the grid, when its dimensions aren't multiples of the
+/// grid size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Fit {
+    /// Crop the image, centred, to the largest multiples of the grid size within it.
+    #[default]
+    Crop,
+    /// Pad the image, centred, to the smallest multiples of the grid size around it.
+    Pad(Padding),
+    /// Resize the image to the nearest multiples of the grid size.
+    Resize,
+}
+
+/// The padding of a master image fitted with [`Fit::Pad`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Padding {
+    /// A flat RGB colour.
+    Color([u8; 3]),
+    /// The edge pixels of the image, extended outwards.
+    Edge,
+}
+
+impl Fit {
+    /// The size of the grid cells of an image of `dimensions` fitted to the `grid_size`.
+    pub fn cell_size(&self, dimensions: (u32, u32), grid_size: (u32, u32)) -> (u32, u32) {
+        (
+            self.divide(dimensions.0, grid_size.0),
+            self.divide(dimensions.1, grid_size.1),
+        )
+    }
+
+    /// The grid size of an image of `dimensions` fitted to cells of `cell_size`. It is 0 when
+    /// cropping an image smaller than a cell, and along the axes whose cell size is 0.
+    pub fn grid_size(&self, dimensions: (u32, u32), cell_size: (u32, u32)) -> (u32, u32) {
+        (
+            self.divide(dimensions.0, cell_size.0),
+            self.divide(dimensions.1, cell_size.1),
+        )
+    }
+
+    /// Divide the `length` of the image by the `count`, rounding down when cropping, up when
+    /// padding and to the nearest when resizing. Dividing by 0 gives 0.
+    fn divide(&self, length: u32, count: u32) -> u32 {
+        if count == 0 {
+            return 0;
+        }
+        match self {
+            Fit::Crop => length / count,
+            Fit::Pad(_) => length.div_ceil(count),
+            Fit::Resize => ((length + count / 2) / count).max(1),
+        }
+    }
+
+    /// Fit the `img` to the `dimensions`.
+    fn apply(&self, img: RgbImage, dimensions: (u32, u32)) -> RgbImage {
+        let (width, height) = dimensions;
+        if img.dimensions() == dimensions {
+            return img;
+        }
+        match self {
+            Fit::Crop => {
+                info!(
+                    "Cropping image to fit grid: {}x{} -> {}x{}",
+                    img.width(),
+                    img.height(),
+                    width,
+                    height
+                );
+                utils::crop_imm_centered(&img, width, height).to_image()
+            }
+            Fit::Pad(padding) => {
+                info!(
+                    "Padding image to fit grid: {}x{} -> {}x{}",
+                    img.width(),
+                    img.height(),
+                    width,
+                    height
+                );
+                let fill = match padding {
+                    Padding::Color(color) => Some(Rgb(*color)),
+                    Padding::Edge => None,
+                };
+                utils::pad_centered(&img, width, height, fill)
+            }
+            Fit::Resize => {
+                info!(
+                    "Resizing image to fit grid: {}x{} -> {}x{}",
+                    img.width(),
+                    img.height(),
+                    width,
+                    height
+                );
+                imageops::resize(&img, width, height, imageops::FilterType::Triangle)
+            }
+        }
+    }
 }
 
 /// Represents the master image.
@@ -32,32 +145,109 @@ impl Master {
     /// # Errors
     /// - [`PhomoError::MasterError`]: An error occurred while constructing the [`Master`].
     pub fn from_image(img: RgbImage, grid_size: (u32, u32)) -> Result<Self, PhomoError> {
-        let (img_width, img_height) = img.dimensions();
-        // the number of cells in each dimension of the grid
-        let (grid_width, grid_height) = grid_size;
-        let (cell_width, cell_height) = (img_width / grid_width, img_height / grid_height);
-        info!("Grid cell size: {}x{}", cell_width, cell_height);
-        // How much is left over around the grid
-        let (width_extra, height_extra) = (img_width % grid_width, img_height % grid_height);
-
-        let img = if width_extra > 0 || height_extra > 0 {
-            let (new_width, new_height) = (img_width - width_extra, img_height - height_extra);
-            info!(
-                "Cropping image to fit grid: {}x{} -> {}x{}",
-                img_width, img_height, new_width, new_height
-            );
-            let img_cropped = utils::crop_imm_centered(&img, new_width, new_height);
-            img_cropped.to_image()
-        } else {
-            img
-        };
+        Self::from_image_with_layout(img, grid_size, Layout::Grid)
+    }
+
+    /// Construct a [`Master`] from a [`RgbImage`] buffer, the grid size and the layout of the
+    /// cells.
+    ///
+    /// # Arguments
+    /// - `img`: The [`RgbImage`] buffer to construct the [`Master`] from.
+    /// - `grid_size`: The grid size of the [`Master`], the number of cells horizontally and vertically.
+    /// - `layout`: The [`Layout`] of the cells within the grid.
+    ///
+    /// # Errors
+    /// - [`PhomoError::MasterError`]: An error occurred while constructing the [`Master`].
+    pub fn from_image_with_layout(
+        img: RgbImage,
+        grid_size: (u32, u32),
+        layout: Layout,
+    ) -> Result<Self, PhomoError> {
+        Self::from_image_with_fit(img, grid_size, layout, Fit::Crop)
+    }
+
+    /// Construct a [`Master`] from a [`RgbImage`] buffer, the grid size, the layout of the cells
+    /// and the way the image is fitted to the grid.
+    ///
+    /// # Arguments
+    /// - `img`: The [`RgbImage`] buffer to construct the [`Master`] from.
+    /// - `grid_size`: The grid size of the [`Master`], the number of cells horizontally and vertically.
+    /// - `layout`: The [`Layout`] of the cells within the grid.
+    /// - `fit`: How the image is fitted to the grid, when its dimensions aren't multiples of the
+    ///   grid size.
+    ///
+    /// # Errors
+    /// - [`PhomoError::MasterError`]: An error occurred while constructing the [`Master`].
+    pub fn from_image_with_fit(
+        img: RgbImage,
+        grid_size: (u32, u32),
+        layout: Layout,
+        fit: Fit,
+    ) -> Result<Self, PhomoError> {
+        let cell_size = fit.cell_size(img.dimensions(), grid_size);
+        Self::fitted(img, grid_size, cell_size, layout, fit)
+    }
+
+    /// Construct a [`Master`] from a [`RgbImage`] buffer, with cells of the `cell_size`. The
+    /// grid size is derived from the cell size, and the image is fitted to the grid.
+    ///
+    /// # Arguments
+    /// - `img`: The [`RgbImage`] buffer to construct the [`Master`] from.
+    /// - `cell_size`: The size of the grid cells, in pixels.
+    /// - `layout`: The [`Layout`] of the cells within the grid.
+    /// - `fit`: How the image is fitted to the grid, see [`Fit::grid_size`].
+    ///
+    /// # Errors
+    /// - [`PhomoError::MasterError`]: The cell size is 0 along an axis, the cell size is larger
+    ///   than the image when cropping it, or an error occurred while constructing the [`Master`].
+    pub fn from_image_with_cell_size(
+        img: RgbImage,
+        cell_size: (u32, u32),
+        layout: Layout,
+        fit: Fit,
+    ) -> Result<Self, PhomoError> {
+        if cell_size.0 == 0 || cell_size.1 == 0 {
+            return Err(MasterError::InvalidCellSize { cell_size }.into());
+        }
+        let grid_size = fit.grid_size(img.dimensions(), cell_size);
+        if grid_size.0 == 0 || grid_size.1 == 0 {
+            return Err(MasterError::CellSizeTooLarge {
+                cell_size,
+                master_dimensions: img.dimensions(),
+            }
+            .into());
+        }
+        Self::fitted(img, grid_size, cell_size, layout, fit)
+    }
 
-        let cells = Self::construct_regions(&img, grid_size)?;
+    /// Fit the `img` to the grid of `grid_size` cells of `cell_size`, and split it into cells.
+    fn fitted(
+        img: RgbImage,
+        grid_size: (u32, u32),
+        cell_size: (u32, u32),
+        layout: Layout,
+        fit: Fit,
+    ) -> Result<Self, PhomoError> {
+        info!("Grid cell size: {}x{}", cell_size.0, cell_size.1);
+        let img = fit.apply(img, (grid_size.0 * cell_size.0, grid_size.1 * cell_size.1));
+
+        let regions = layout.regions(&img, grid_size, cell_size);
+        let cells = match layout {
+            Layout::Grid => Self::construct_regions(&img, grid_size)?,
+            _ => Self::sample_regions(&img, &regions, cell_size),
+        };
+        let masks = Self::mask_regions(&img, &regions, cell_size);
+        info!("Master cells: {}", cells.len());
         Ok(Master {
             img,
             cells,
-            cell_size: (cell_width, cell_height),
+            regions,
+            masks,
+            cell_size,
+            mask: None,
             grid_size,
+            layout,
+            fit,
         })
     }
 
@@ -100,11 +290,171 @@ impl Master {
         Ok(cells)
     }
 
+    /// Extract the `regions` of the `img`, resized to the `cell_size`.
+    fn sample_regions(img: &RgbImage, regions: &[Region], cell_size: (u32, u32)) -> Vec<RgbImage> {
+        regions
+            .iter()
+            .map(|region| {
+                let cell = layout::sample(img, region);
+                if cell.dimensions() == cell_size {
+                    cell
+                } else {
+                    imageops::resize(
+                        &cell,
+                        cell_size.0,
+                        cell_size.1,
+                        imageops::FilterType::Triangle,
+                    )
+                }
+            })
+            .collect()
+    }
+
+    /// The masks of the `regions` which don't fill their rectangle in the `img`, resized to the
+    /// `cell_size`.
+    fn mask_regions(
+        img: &RgbImage,
+        regions: &[Region],
+        cell_size: (u32, u32),
+    ) -> Vec<Option<GrayImage>> {
+        regions
+            .iter()
+            .map(|region| {
+                layout::mask(region, img.dimensions()).map(|mask| {
+                    if mask.dimensions() == cell_size {
+                        mask
+                    } else {
+                        imageops::resize(
+                            &mask,
+                            cell_size.0,
+                            cell_size.1,
+                            imageops::FilterType::Nearest,
+                        )
+                    }
+                })
+            })
+            .collect()
+    }
+
+    /// Only tile the parts of the master image within the `mask`, the cells which are mostly
+    /// outside of it are dropped from the [`cells`](Master::cells), so they aren't compared to
+    /// the tiles nor assigned one. The pixels of the other cells outside of the mask are left
+    /// out of their [`masks`](Master::masks).
+    ///
+    /// # Arguments
+    /// - `mask`: A binary or alpha mask, its alpha channel is used when it has one and its
+    ///   luminance otherwise, the pixels above half the range are within the mask. A mask of
+    ///   another size than the master image is fitted to it with the [`Fit`] of the master, the
+    ///   padding of a flat colour being outside of the mask.
+    ///
+    /// # Errors
+    /// - [`PhomoError::MasterError`]: The mask can't be fitted to the master image.
+    pub fn with_mask(mut self, mask: &DynamicImage) -> Result<Self, PhomoError> {
+        let (width, height) = self.img.dimensions();
+        let mask = if mask.color().has_alpha() {
+            let rgba = mask.to_rgba8();
+            GrayImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+                Luma([rgba.get_pixel(x, y).0[3]])
+            })
+        } else {
+            mask.to_luma8()
+        };
+        let (larger, smaller) = (
+            mask.width() >= width && mask.height() >= height,
+            mask.width() <= width && mask.height() <= height,
+        );
+        let mut mask = match self.fit {
+            _ if mask.dimensions() == (width, height) => mask,
+            Fit::Crop if larger => utils::crop_imm_centered(&mask, width, height).to_image(),
+            Fit::Pad(padding) if smaller => {
+                let fill = match padding {
+                    Padding::Color(_) => Some(Luma([0])),
+                    Padding::Edge => None,
+                };
+                utils::pad_centered(&mask, width, height, fill)
+            }
+            Fit::Resize => imageops::resize(&mask, width, height, imageops::FilterType::Nearest),
+            _ => {
+                return Err(MasterError::MaskSizeMismatch {
+                    mask_dimensions: mask.dimensions(),
+                    master_dimensions: (width, height),
+                }
+                .into())
+            }
+        };
+        mask.pixels_mut()
+            .for_each(|pixel| pixel.0[0] = if pixel.0[0] >= 128 { 255 } else { 0 });
+
+        let bounds = (width, height);
+        let mut kept = Vec::with_capacity(self.regions.len());
+        for (region, cell_mask) in self.regions.iter().zip(self.masks.iter_mut()) {
+            let mut region_mask = GrayImage::new(region.width, region.height);
+            let (inside, total) = region
+                .pixels(bounds)
+                .fold((0, 0), |(inside, total), (x, y)| {
+                    let (img_x, img_y) = region.absolute(x, y);
+                    let within = mask.get_pixel(img_x, img_y).0[0] > 0;
+                    if within {
+                        region_mask.put_pixel(x, y, Luma([255]));
+                    }
+                    (inside + within as usize, total + 1)
+                });
+            kept.push(2 * inside >= total);
+            // The kept cells which are partly outside of the mask are only compared to the
+            // tiles within it
+            if inside < total {
+                *cell_mask = Some(if region_mask.dimensions() == self.cell_size {
+                    region_mask
+                } else {
+                    imageops::resize(
+                        &region_mask,
+                        self.cell_size.0,
+                        self.cell_size.1,
+                        imageops::FilterType::Nearest,
+                    )
+                });
+            }
+        }
+        fn keep<T>(items: &mut Vec<T>, kept: &[bool]) {
+            let mut kept = kept.iter();
+            items.retain(|_| kept.next().is_some_and(|&kept| kept));
+        }
+        keep(&mut self.cells, &kept);
+        keep(&mut self.regions, &kept);
+        keep(&mut self.masks, &kept);
+        info!("Master cells within the mask: {}", self.cells.len());
+        self.mask = Some(mask);
+        Ok(self)
+    }
+
+    /// The [`Layout`] of the cells.
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    /// The [`Fit`] of the master image to the grid.
+    pub fn fit(&self) -> Fit {
+        self.fit
+    }
+
+    /// The grid size, the number of cells horizontally and vertically.
+    pub fn grid_size(&self) -> (u32, u32) {
+        self.grid_size
+    }
+
+    /// The cells within `radius` cells of each cell, see [`Region::is_within`].
+    pub fn neighbours(&self, radius: u32) -> Vec<Vec<usize>> {
+        layout::neighbours(&self.regions, radius)
+    }
+
     /// Overlay the grid onto the master image.
     ///
     /// # Errors
     /// - [`PhomoError::ImageError`]: An error occurred while creating the grid overlay.
     pub fn overlay_grid(&self) -> Result<RgbImage, PhomoError> {
+        if self.layout != Layout::Grid || self.mask.is_some() {
+            return Ok(self.overlay_regions());
+        }
         let (grid_width, grid_height) = self.grid_size;
         let mut grid_img = RgbImage::from_pixel(
             self.img.width() + grid_width - 1,
@@ -122,6 +472,20 @@ impl Master {
 
         Ok(grid_img)
     }
+
+    /// Draw the top and left borders of the cells onto the master image.
+    fn overlay_regions(&self) -> RgbImage {
+        let mut grid_img = self.img.clone();
+        for region in self.regions.iter() {
+            for (x, y) in region.pixels(self.img.dimensions()) {
+                if x == 0 || y == 0 || !region.contains(x - 1, y) || !region.contains(x, y - 1) {
+                    let (img_x, img_y) = region.absolute(x, y);
+                    grid_img.put_pixel(img_x, img_y, Rgb([255, 255, 255]));
+                }
+            }
+        }
+        grid_img
+    }
 }
 
 #[cfg(test)]
@@ -214,6 +578,93 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_from_image_with_fit() {
+        let img = create_test_image(250, 255);
+        let grid_size = (4, 4);
+
+        let padding = Padding::Color([0, 0, 255]);
+        let master =
+            Master::from_image_with_fit(img.clone(), grid_size, Layout::Grid, Fit::Pad(padding))
+                .unwrap();
+        assert_eq!(master.img.dimensions(), (252, 256));
+        assert_eq!(master.cell_size, (63, 64));
+        assert_eq!(master.fit(), Fit::Pad(padding));
+        assert_eq!(master.img.get_pixel(0, 0), &image::Rgb([0, 0, 255]));
+        assert_eq!(master.img.get_pixel(1, 0), img.get_pixel(0, 0));
+
+        let master = Master::from_image_with_fit(
+            img.clone(),
+            grid_size,
+            Layout::Grid,
+            Fit::Pad(Padding::Edge),
+        )
+        .unwrap();
+        assert_eq!(master.img.get_pixel(0, 0), img.get_pixel(0, 0));
+        assert_eq!(master.img.get_pixel(251, 255), img.get_pixel(249, 254));
+
+        let master =
+            Master::from_image_with_fit(img, grid_size, Layout::Grid, Fit::Resize).unwrap();
+        assert_eq!(master.img.dimensions(), (252, 256));
+        assert_eq!(master.cells.len(), 16);
+    }
+
+    #[test]
+    fn test_from_image_with_cell_size() {
+        let img = create_test_image(250, 255);
+        let master =
+            Master::from_image_with_cell_size(img.clone(), (20, 20), Layout::Grid, Fit::Crop)
+                .unwrap();
+        assert_eq!(master.grid_size(), (12, 12));
+        assert_eq!(master.cell_size, (20, 20));
+        assert_eq!(master.img.dimensions(), (240, 240));
+
+        let master = Master::from_image_with_cell_size(
+            img.clone(),
+            (20, 20),
+            Layout::Grid,
+            Fit::Pad(Padding::Edge),
+        )
+        .unwrap();
+        assert_eq!(master.grid_size(), (13, 13));
+        assert_eq!(master.img.dimensions(), (260, 260));
+
+        // A padded mask is outside of the padding
+        let mask = GrayImage::from_pixel(250, 255, Luma([255]));
+        let master = Master::from_image_with_cell_size(
+            img.clone(),
+            (20, 20),
+            Layout::Grid,
+            Fit::Pad(Padding::Color([0, 0, 0])),
+        )
+        .unwrap()
+        .with_mask(&DynamicImage::ImageLuma8(mask))
+        .unwrap();
+        assert_eq!(master.mask.as_ref().unwrap().get_pixel(0, 0).0, [0]);
+        assert_eq!(master.mask.as_ref().unwrap().get_pixel(5, 2).0, [255]);
+        assert_eq!(master.cells.len(), 13 * 13);
+
+        let result =
+            Master::from_image_with_cell_size(img.clone(), (256, 20), Layout::Grid, Fit::Crop);
+        assert!(matches!(
+            result,
+            Err(PhomoError::MasterError(
+                MasterError::CellSizeTooLarge { .. }
+            ))
+        ));
+
+        for fit in [Fit::Crop, Fit::Pad(Padding::Edge), Fit::Resize] {
+            assert_eq!(fit.grid_size((250, 255), (0, 20)).0, 0);
+            let result = Master::from_image_with_cell_size(img.clone(), (0, 20), Layout::Grid, fit);
+            assert!(matches!(
+                result,
+                Err(PhomoError::MasterError(MasterError::InvalidCellSize {
+                    cell_size: (0, 20)
+                }))
+            ));
+        }
+    }
+
     #[test]
     fn test_construct_regions() {
         // Create a 256x256 test image
@@ -246,6 +697,85 @@ mod tests {
         assert_eq!(expected_img, grid_overlay);
     }
 
+    #[test]
+    fn test_from_image_quadtree() {
+        // A flat image with a detailed top left grid cell
+        let img = RgbImage::from_fn(128, 128, |x, y| {
+            if x < 32 && y < 32 && (x + y) % 2 == 0 {
+                image::Rgb([255, 255, 255])
+            } else {
+                image::Rgb([0, 0, 0])
+            }
+        });
+        let layout = Layout::Quadtree(crate::layout::Quadtree {
+            max_depth: 1,
+            ..Default::default()
+        });
+        let master = Master::from_image_with_layout(img, (4, 4), layout.clone()).unwrap();
+        assert_eq!(master.layout(), &layout);
+        assert_eq!(master.regions.len(), 16 + 3);
+        assert_eq!(master.cells.len(), master.regions.len());
+        assert_eq!(master.regions[0], Region::new(0, 0, 16, 16));
+        // The cells are resized to the grid cell size
+        assert!(master
+            .cells
+            .iter()
+            .all(|cell| cell.dimensions() == (32, 32)));
+        // The gap between the cells is measured in the size of the smaller one
+        let neighbours = master.neighbours(1);
+        assert_eq!(master.regions[2], Region::new(32, 0, 32, 32));
+        assert!(neighbours[1].contains(&2));
+        assert!(!neighbours[0].contains(&2));
+
+        let grid_overlay = master.overlay_grid().unwrap();
+        assert_eq!(grid_overlay.dimensions(), (128, 128));
+        assert_eq!(grid_overlay.get_pixel(16, 1), &image::Rgb([255, 255, 255]));
+        assert_eq!(grid_overlay.get_pixel(48, 1), &image::Rgb([0, 0, 0]));
+    }
+
+    #[test]
+    fn test_with_mask() {
+        let img = create_test_image(128, 128);
+        // An alpha mask over the first column and a half of the grid
+        let mask = image::RgbaImage::from_fn(128, 128, |x, _| {
+            image::Rgba([0, 0, 0, if x < 48 { 255 } else { 0 }])
+        });
+        let master = Master::from_image(img.clone(), (4, 4))
+            .unwrap()
+            .with_mask(&DynamicImage::ImageRgba8(mask))
+            .unwrap();
+        // The cells at least half within the mask are kept
+        assert_eq!(master.cells.len(), 8);
+        assert_eq!(master.regions.len(), 8);
+        assert_eq!(master.masks.len(), 8);
+        assert_eq!(master.regions[1], Region::new(32, 0, 32, 32));
+        assert_eq!(master.cells[1], img.view(32, 0, 32, 32).to_image());
+        assert_eq!(master.regions[2], Region::new(0, 32, 32, 32));
+        // Only the part of the second cell within the mask is compared to the tiles
+        assert!(master.masks[0].is_none());
+        let cell_mask = master.masks[1].as_ref().unwrap();
+        assert_eq!(cell_mask.get_pixel(15, 0).0, [255]);
+        assert_eq!(cell_mask.get_pixel(16, 31).0, [0]);
+
+        // A larger mask is cropped to the master, a smaller one is rejected
+        let mask = GrayImage::from_fn(130, 130, |x, _| Luma([if x < 49 { 255 } else { 0 }]));
+        let cropped = Master::from_image(img.clone(), (4, 4))
+            .unwrap()
+            .with_mask(&DynamicImage::ImageLuma8(mask))
+            .unwrap();
+        assert_eq!(cropped.regions, master.regions);
+        assert_eq!(cropped.mask, master.mask);
+        let result = Master::from_image(img, (4, 4))
+            .unwrap()
+            .with_mask(&DynamicImage::new_luma8(64, 64));
+        assert!(matches!(
+            result,
+            Err(PhomoError::MasterError(
+                MasterError::MaskSizeMismatch { .. }
+            ))
+        ));
+    }
+
     #[test]
     fn test_invalid_grid_shape() {
         let img = create_test_image(256, 256);
@@ -1,7 +1,118 @@
 extern crate image;
-use image::RgbImage;
+use std::ops::Range;
 
-pub(crate) type MetricFn = fn(&RgbImage, &RgbImage) -> i64;
+use image::{GrayImage, Rgb, RgbImage};
+use palette::color_difference::{Ciede2000, EuclideanDistance};
+use palette::{IntoColor, Lab, Oklab, Srgb};
+
+/// Factor applied to the [`Oklab`] distances before rounding to integers.
+const OKLAB_SCALE: f32 = 1000.;
+/// Factor applied to the CIEDE2000 color differences before rounding to integers.
+const CIEDE2000_SCALE: f32 = 100.;
+/// Factor applied to the SSIM dissimilarity before rounding to integers.
+const SSIM_SCALE: f64 = 10000.;
+/// Size of the square windows over which the SSIM is computed.
+const SSIM_WINDOW: u32 = 8;
+/// SSIM stabilization constants, for a dynamic range of 255.
+const SSIM_C1: f64 = (0.01 * 255.) * (0.01 * 255.);
+const SSIM_C2: f64 = (0.03 * 255.) * (0.03 * 255.);
+
+/// The [`Metric`] trait is implemented by types which compute the distance between a tile image
+/// and a master cell.
+///
+/// It is implemented for all functions and closures with the signature
+/// `Fn(&RgbImage, &RgbImage) -> i64`, which includes the metrics of this module. Implement it
+/// directly to use a metric which carries some state.
+///
+/// # Examples
+///
+/// ```rust
+/// use image::RgbImage;
+/// use phomo::metrics::{norm_l1, Metric};
+///
+/// struct Weighted {
+///     weight: i64,
+/// }
+///
+/// impl Metric for Weighted {
+///     fn distance(&self, tile: &RgbImage, cell: &RgbImage) -> i64 {
+///         self.weight * norm_l1(tile, cell)
+///     }
+/// }
+///
+/// let img1 = image::ImageBuffer::from_pixel(2, 2, image::Rgb([0, 0, 0]));
+/// let img2 = image::ImageBuffer::from_pixel(2, 2, image::Rgb([255, 255, 255]));
+/// assert_eq!(Weighted { weight: 2 }.distance(&img1, &img2), 2 * norm_l1(&img1, &img2));
+///
+/// let offset = 10;
+/// let closure = |tile: &RgbImage, cell: &RgbImage| norm_l1(tile, cell) + offset;
+/// assert_eq!(closure.distance(&img1, &img2), norm_l1(&img1, &img2) + offset);
+/// ```
+pub trait Metric: Send + Sync {
+    /// Compute the distance between the `tile` image and the master `cell` image.
+    ///
+    /// # Arguments
+    /// - `tile`: The tile image.
+    /// - `cell`: The master cell image, of the same size as the `tile`.
+    fn distance(&self, tile: &RgbImage, cell: &RgbImage) -> i64;
+}
+
+impl<F> Metric for F
+where
+    F: Fn(&RgbImage, &RgbImage) -> i64 + Send + Sync,
+{
+    fn distance(&self, tile: &RgbImage, cell: &RgbImage) -> i64 {
+        self(tile, cell)
+    }
+}
+
+impl Metric for Box<dyn Metric> {
+    fn distance(&self, tile: &RgbImage, cell: &RgbImage) -> i64 {
+        self.as_ref().distance(tile, cell)
+    }
+}
+
+/// A master cell with a mask, compared to the tiles only within its mask.
+///
+/// The pixels of the tiles within the mask are copied over a copy of the cell, so the pixels
+/// outside of the mask are equal and don't weigh on the distance, whatever the [`Metric`].
+pub(crate) struct MaskedCell<'a> {
+    cell: &'a RgbImage,
+    /// The byte ranges of the runs of pixels within the mask.
+    runs: Vec<Range<usize>>,
+    /// The cell, with the pixels of the last tile within the mask.
+    buffer: RgbImage,
+}
+
+impl<'a> MaskedCell<'a> {
+    /// Prepare the comparison of the tiles with the `cell` within its `mask`, of the same size.
+    pub(crate) fn new(cell: &'a RgbImage, mask: &GrayImage) -> Self {
+        let mut runs: Vec<Range<usize>> = Vec::new();
+        for (i, pixel) in mask.pixels().enumerate() {
+            if pixel.0[0] == 0 {
+                continue;
+            }
+            let bytes = 3 * i..3 * (i + 1);
+            match runs.last_mut() {
+                Some(run) if run.end == bytes.start => run.end = bytes.end,
+                _ => runs.push(bytes),
+            }
+        }
+        Self {
+            cell,
+            runs,
+            buffer: cell.clone(),
+        }
+    }
+
+    /// Compute the `metric` distance between the `tile` and the cell, within the mask.
+    pub(crate) fn distance<M: Metric>(&mut self, metric: &M, tile: &RgbImage) -> i64 {
+        for run in self.runs.iter() {
+            (*self.buffer)[run.clone()].copy_from_slice(&tile.as_raw()[run.clone()]);
+        }
+        metric.distance(&self.buffer, self.cell)
+    }
+}
 
 /// L1 norm, the sum of the absolute differences of the pixels.
 ///
@@ -52,7 +163,7 @@ pub fn norm_l2(img1: &RgbImage, img2: &RgbImage) -> i64 {
 }
 
 #[inline]
-fn luminance(pixel: &[u8; 3]) -> i64 {
+pub(crate) fn luminance(pixel: &[u8; 3]) -> i64 {
     (0.299 * pixel[0] as f64 + 0.587 * pixel[1] as f64 + 0.114 * pixel[2] as f64) as i64
 }
 
@@ -128,3 +239,300 @@ pub fn avg_color(img1: &RgbImage, img2: &RgbImage) -> i64 {
     (avg1.0.abs_diff(avg2.0) + avg1.1.abs_diff(avg2.1) + avg1.2.abs_diff(avg2.2)) as i64
         / (3 * img1.width() as i64 * img1.height() as i64)
 }
+
+#[inline]
+fn to_oklab(pixel: &Rgb<u8>) -> Oklab {
+    Srgb::from(pixel.0).into_format::<f32>().into_color()
+}
+
+#[inline]
+fn to_lab(pixel: &Rgb<u8>) -> Lab {
+    Srgb::from(pixel.0).into_format::<f32>().into_color()
+}
+
+/// Sum of the euclidean distances of the pixels in the [`Oklab`] color space.
+///
+/// The [`Oklab`] color space is perceptually uniform, so the distances better match how
+/// different the colors look. The distances are scaled by 1000 before being rounded.
+///
+/// # Examples
+///
+/// ```rust
+/// use phomo::metrics::oklab;
+/// use image;
+///
+/// let img1 = image::ImageBuffer::from_pixel(2, 2, image::Rgb([0, 0, 0]));
+/// let img2 = image::ImageBuffer::from_pixel(2, 2, image::Rgb([255, 255, 255]));
+/// let norm = oklab(&img1, &img2);
+/// assert_eq!(norm, 1000 * 2 * 2);
+/// ```
+pub fn oklab(img1: &RgbImage, img2: &RgbImage) -> i64 {
+    let sum = img1.pixels().zip(img2.pixels()).fold(0., |sum, (p1, p2)| {
+        sum + to_oklab(p1).distance(to_oklab(p2))
+    });
+    (sum * OKLAB_SCALE).round() as i64
+}
+
+/// Sum of the CIEDE2000 color differences of the pixels.
+///
+/// The CIEDE2000 color difference, computed in the CIELAB color space, corrects the remaining
+/// perceptual non uniformities of the color space, notably in the blues and for skin tones.
+/// It is slower to compute than the other metrics. The differences are scaled by 100 before
+/// being rounded.
+///
+/// # Examples
+///
+/// ```rust
+/// use phomo::metrics::ciede2000;
+/// use image;
+///
+/// let img1 = image::ImageBuffer::from_pixel(2, 2, image::Rgb([0, 0, 0]));
+/// let img2 = image::ImageBuffer::from_pixel(2, 2, image::Rgb([255, 255, 255]));
+/// let norm = ciede2000(&img1, &img2);
+/// assert_eq!(norm, 100 * 100 * 2 * 2);
+/// ```
+pub fn ciede2000(img1: &RgbImage, img2: &RgbImage) -> i64 {
+    let sum = img1
+        .pixels()
+        .zip(img2.pixels())
+        .fold(0., |sum, (p1, p2)| sum + to_lab(p1).difference(to_lab(p2)));
+    (sum * CIEDE2000_SCALE).round() as i64
+}
+
+/// Average of the colors of the pixels, converted with `convert`.
+fn mean_color<C, F>(img: &RgbImage, convert: F) -> [f32; 3]
+where
+    F: Fn(&Rgb<u8>) -> C,
+    C: Into<[f32; 3]>,
+{
+    let n_pixels = (img.width() * img.height()) as f32;
+    let sum = img.pixels().fold([0.; 3], |sum, pixel| {
+        let color: [f32; 3] = convert(pixel).into();
+        [sum[0] + color[0], sum[1] + color[1], sum[2] + color[2]]
+    });
+    sum.map(|channel| channel / n_pixels)
+}
+
+/// Euclidean distance of the average colors of the images in the [`Oklab`] color space.
+///
+/// Like [`avg_color`], the distribution of the color is ignored, but the average colors are
+/// compared in a perceptually uniform color space. The distance is scaled by 1000 before being
+/// rounded.
+///
+/// # Examples
+///
+/// ```rust
+/// use phomo::metrics::avg_oklab;
+/// use image;
+///
+/// let img1 = image::ImageBuffer::from_pixel(2, 2, image::Rgb([0, 0, 0]));
+/// let img2 = image::ImageBuffer::from_pixel(2, 2, image::Rgb([255, 255, 255]));
+/// let norm = avg_oklab(&img1, &img2);
+/// assert_eq!(norm, 1000);
+/// ```
+pub fn avg_oklab(img1: &RgbImage, img2: &RgbImage) -> i64 {
+    let avg1: Oklab = mean_color(img1, to_oklab).into();
+    let avg2: Oklab = mean_color(img2, to_oklab).into();
+    (avg1.distance(avg2) * OKLAB_SCALE).round() as i64
+}
+
+/// CIEDE2000 color difference of the average colors of the images.
+///
+/// Like [`avg_color`], the distribution of the color is ignored, but the average colors are
+/// compared using the CIEDE2000 color difference. The difference is scaled by 100 before being
+/// rounded.
+///
+/// # Examples
+///
+/// ```rust
+/// use phomo::metrics::avg_ciede2000;
+/// use image;
+///
+/// let img1 = image::ImageBuffer::from_pixel(2, 2, image::Rgb([0, 0, 0]));
+/// let img2 = image::ImageBuffer::from_pixel(2, 2, image::Rgb([255, 255, 255]));
+/// let norm = avg_ciede2000(&img1, &img2);
+/// assert_eq!(norm, 100 * 100);
+/// ```
+pub fn avg_ciede2000(img1: &RgbImage, img2: &RgbImage) -> i64 {
+    let avg1: Lab = mean_color(img1, to_lab).into();
+    let avg2: Lab = mean_color(img2, to_lab).into();
+    (avg1.difference(avg2) * CIEDE2000_SCALE).round() as i64
+}
+
+/// Luminance of each pixel of the image, in row major order.
+fn luminance_buffer(img: &RgbImage) -> Vec<i64> {
+    img.pixels().map(|pixel| luminance(&pixel.0)).collect()
+}
+
+/// SSIM of the window of the two luminance buffers, starting at `(x0, y0)`.
+fn ssim_window(
+    lum1: &[i64],
+    lum2: &[i64],
+    width: u32,
+    (x0, y0): (u32, u32),
+    (window_width, window_height): (u32, u32),
+) -> f64 {
+    let n = (window_width * window_height) as f64;
+    let indices = (y0..y0 + window_height)
+        .flat_map(|y| (x0..x0 + window_width).map(move |x| (y * width + x) as usize));
+
+    let (sum1, sum2) = indices.clone().fold((0., 0.), |(s1, s2), i| {
+        (s1 + lum1[i] as f64, s2 + lum2[i] as f64)
+    });
+    let (mean1, mean2) = (sum1 / n, sum2 / n);
+
+    let (var1, var2, cov) = indices.fold((0., 0., 0.), |(v1, v2, c), i| {
+        let d1 = lum1[i] as f64 - mean1;
+        let d2 = lum2[i] as f64 - mean2;
+        (v1 + d1 * d1, v2 + d2 * d2, c + d1 * d2)
+    });
+    let (var1, var2, cov) = (var1 / n, var2 / n, cov / n);
+
+    ((2. * mean1 * mean2 + SSIM_C1) * (2. * cov + SSIM_C2))
+        / ((mean1 * mean1 + mean2 * mean2 + SSIM_C1) * (var1 + var2 + SSIM_C2))
+}
+
+/// Origins of the windows of size `window` tiling a side of length `size`. When the side isn't a
+/// multiple of the window, a last window overlapping the previous one covers the remaining pixels.
+fn window_origins(size: u32, window: u32) -> Vec<u32> {
+    let mut origins: Vec<u32> = (0..size / window).map(|i| i * window).collect();
+    if !size.is_multiple_of(window) {
+        origins.push(size - window);
+    }
+    origins
+}
+
+/// Structural dissimilarity of the images, based on the structural similarity index (SSIM).
+///
+/// The SSIM compares the luminance, contrast and structure of the images over 8x8 windows, so
+/// tiles whose edges and textures follow the master cell are preferred. The windows at the right
+/// and bottom edges overlap their neighbours when the image isn't a multiple of 8 pixels, and
+/// images smaller than 8 pixels are compared over a single window of their size. The metric is
+/// `1 - mean SSIM`, scaled by 10000 before being rounded, it ranges from 0 for identical images to
+/// 20000.
+///
+/// See:
+///     <https://doi.org/10.1109/TIP.2003.819861>
+///
+/// # Examples
+///
+/// ```rust
+/// use phomo::metrics::ssim;
+/// use image;
+///
+/// let img1 = image::ImageBuffer::from_pixel(2, 2, image::Rgb([0, 0, 0]));
+/// let img2 = image::ImageBuffer::from_pixel(2, 2, image::Rgb([255, 255, 255]));
+/// assert_eq!(ssim(&img1, &img1), 0);
+/// assert_eq!(ssim(&img1, &img2), 9999);
+/// ```
+pub fn ssim(img1: &RgbImage, img2: &RgbImage) -> i64 {
+    let (width, height) = img1.dimensions();
+    let lum1 = luminance_buffer(img1);
+    let lum2 = luminance_buffer(img2);
+
+    // Images smaller than the window are compared as a whole
+    let window_width = SSIM_WINDOW.min(width);
+    let window_height = SSIM_WINDOW.min(height);
+    let origins_x = window_origins(width, window_width);
+    let origins_y = window_origins(height, window_height);
+
+    let ssim_sum: f64 = origins_y
+        .iter()
+        .flat_map(|&y0| origins_x.iter().map(move |&x0| (x0, y0)))
+        .map(|origin| ssim_window(&lum1, &lum2, width, origin, (window_width, window_height)))
+        .sum();
+    let mean_ssim = ssim_sum / (origins_x.len() * origins_y.len()) as f64;
+
+    ((1. - mean_ssim) * SSIM_SCALE).round() as i64
+}
+
+/// Sobel gradients of the luminance buffer, the image borders are extended.
+fn sobel(lum: &[i64], width: u32, height: u32) -> Vec<(i64, i64)> {
+    let at = |x: i64, y: i64| {
+        let x = x.clamp(0, width as i64 - 1);
+        let y = y.clamp(0, height as i64 - 1);
+        lum[(y * width as i64 + x) as usize]
+    };
+
+    (0..height as i64)
+        .flat_map(|y| (0..width as i64).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let gx = at(x + 1, y - 1) + 2 * at(x + 1, y) + at(x + 1, y + 1)
+                - at(x - 1, y - 1)
+                - 2 * at(x - 1, y)
+                - at(x - 1, y + 1);
+            let gy = at(x - 1, y + 1) + 2 * at(x, y + 1) + at(x + 1, y + 1)
+                - at(x - 1, y - 1)
+                - 2 * at(x, y - 1)
+                - at(x + 1, y - 1);
+            (gx, gy)
+        })
+        .collect()
+}
+
+/// L1 norm of the difference of the luminance gradients of the pixels.
+///
+/// The horizontal and vertical gradients are computed with the Sobel operator, so edges which
+/// differ in position, strength or orientation are penalised. Flat regions are ignored, so it
+/// is best combined with a color metric.
+///
+/// # Examples
+///
+/// ```rust
+/// use phomo::metrics::gradient;
+/// use image;
+///
+/// // vertical edge
+/// let img1 = image::ImageBuffer::from_fn(2, 2, |x, _| image::Rgb([255 * x as u8; 3]));
+/// // horizontal edge
+/// let img2 = image::ImageBuffer::from_fn(2, 2, |_, y| image::Rgb([255 * y as u8; 3]));
+/// assert_eq!(gradient(&img1, &img1), 0);
+/// assert_eq!(gradient(&img1, &img2), 2 * 4 * 4 * 255);
+/// ```
+pub fn gradient(img1: &RgbImage, img2: &RgbImage) -> i64 {
+    let (width, height) = img1.dimensions();
+    let grad1 = sobel(&luminance_buffer(img1), width, height);
+    let grad2 = sobel(&luminance_buffer(img2), width, height);
+
+    grad1
+        .iter()
+        .zip(grad2.iter())
+        .fold(0, |sum, ((gx1, gy1), (gx2, gy2))| {
+            sum + (gx1 - gx2).abs() + (gy1 - gy2).abs()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_origins() {
+        assert_eq!(window_origins(16, 8), vec![0, 8]);
+        assert_eq!(window_origins(12, 8), vec![0, 4]);
+        assert_eq!(window_origins(5, 5), vec![0]);
+    }
+
+    #[test]
+    fn test_ssim_small_cell() {
+        let img1 = RgbImage::from_fn(5, 5, |x, y| image::Rgb([(x * 50) as u8, (y * 50) as u8, 0]));
+        let img2 = RgbImage::from_fn(5, 5, |x, y| image::Rgb([(y * 50) as u8, (x * 50) as u8, 0]));
+        assert_eq!(ssim(&img1, &img1), 0);
+        assert!(ssim(&img1, &img2) > 0);
+    }
+
+    #[test]
+    fn test_ssim_edge_pixels() {
+        // The images only differ in the pixels past the first 8x8 window
+        let img1 = RgbImage::from_pixel(12, 12, image::Rgb([128, 128, 128]));
+        let img2 = RgbImage::from_fn(12, 12, |x, y| {
+            if x >= 8 || y >= 8 {
+                image::Rgb([((x + y) * 20) as u8, 0, 255])
+            } else {
+                image::Rgb([128, 128, 128])
+            }
+        });
+        assert_eq!(ssim(&img1, &img1), 0);
+        assert!(ssim(&img1, &img2) > 0);
+    }
+}
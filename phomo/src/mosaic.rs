@@ -3,18 +3,59 @@ use std::path::Path;
 use std::time;
 
 extern crate image;
-use image::{GenericImage, RgbImage};
+use image::{Rgb, RgbImage};
 use log::info;
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
-use crate::distance_matrix::DistanceMatrix;
+use crate::cost_matrix::CostMatrix;
+use crate::distance_matrix::{solve_timed, DistanceMatrix};
 use crate::error::{MosaicError, PhomoError};
+use crate::features::FeatureExtractor;
+use crate::layout::{self, Layout};
 use crate::macros;
 use crate::master::Master;
-use crate::metrics::{norm_l1, MetricFn};
+use crate::metrics::{norm_l1, MaskedCell, Metric};
+use crate::progress::{CancellationToken, Monitor};
+use crate::solvers::annealing::{Annealing, Objective, Schedule};
+use crate::solvers::spacing::Spacing;
 use crate::solvers::{hungarian::Hungarian, Solve, SolverConfig};
+use crate::sparse_distance_matrix::SparseDistanceMatrix;
 use crate::utils;
+use crate::vp_tree::VpTree;
+
+/// The features of the tiles and of the master cells.
+type Features<F> = (
+    Vec<<F as FeatureExtractor>::Feature>,
+    Vec<<F as FeatureExtractor>::Feature>,
+);
+
+/// The fill of the parts of the mosaic which aren't covered by a cell, such as the parts of the
+/// master outside of its [`mask`](Master::mask).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Background {
+    /// The master image.
+    #[default]
+    Master,
+    /// A flat RGB colour.
+    Color([u8; 3]),
+}
+
+impl Background {
+    /// The image the tiles are drawn onto, of the size of the `master_img`.
+    pub(crate) fn fill(&self, master_img: &RgbImage) -> RgbImage {
+        match self {
+            Background::Master => master_img.clone(),
+            Background::Color(color) => {
+                RgbImage::from_pixel(master_img.width(), master_img.height(), Rgb(*color))
+            }
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct Mosaic {
@@ -26,6 +67,17 @@ pub struct Mosaic {
     pub tiles: Vec<RgbImage>,
     /// The number of cells horizontally and vertically in the mosaic.
     pub grid_size: (u32, u32),
+    /// The cost offset of each tile, added to the distances between the tile and every master
+    /// cell. Lower weights make a tile more likely to be used. See
+    /// [`set_tile_weights`](Mosaic::set_tile_weights).
+    pub tile_weights: Option<Vec<i64>>,
+    /// The fill of the parts of the mosaic which aren't covered by a cell.
+    pub background: Background,
+    /// The progress reporting and cancellation of the distance matrix computations and of the
+    /// rendering. The solvers use the [`SolverConfig::monitor`]. The cancellation is only checked
+    /// by the rendering and the `try_` variants of the distance matrix computations, such as
+    /// [`try_distance_matrix`](Mosaic::try_distance_matrix).
+    pub monitor: Monitor,
 }
 
 /// Represents a photo mosaic.
@@ -104,9 +156,44 @@ impl Mosaic {
             master,
             tiles,
             grid_size,
+            tile_weights: None,
+            background: Background::default(),
+            monitor: Monitor::default(),
         })
     }
 
+    /// Set the cost offset of each tile, which is added to the distances of the tile in the
+    /// distance matrices computed by the [`Mosaic`]. Use it to prefer some tiles, with a negative
+    /// weight, or to penalise others, with a positive weight.
+    ///
+    /// # Arguments
+    /// - `tile_weights`: The weight of each tile, in the order of the [`Mosaic::tiles`].
+    ///
+    /// # Errors
+    /// - [`PhomoError::MosaicError`]: The number of weights doesn't match the number of tiles.
+    pub fn set_tile_weights(&mut self, tile_weights: Vec<i64>) -> Result<(), PhomoError> {
+        if tile_weights.len() != self.tiles.len() {
+            return Err(MosaicError::TileWeightsLengthMismatch {
+                expected: self.tiles.len(),
+                found: tile_weights.len(),
+            }
+            .into());
+        }
+        self.tile_weights = Some(tile_weights);
+        Ok(())
+    }
+
+    /// Add the tile weights to the costs of a dense distance matrix, stored in row major order.
+    fn apply_tile_weights(&self, data: &mut [i64]) {
+        if let Some(tile_weights) = &self.tile_weights {
+            for row in data.chunks_mut(tile_weights.len()) {
+                row.iter_mut()
+                    .zip(tile_weights)
+                    .for_each(|(cost, weight)| *cost += weight);
+            }
+        }
+    }
+
     /// Compute the [`DistanceMatrix`] between the tiles and the master cells, using the
     /// [`norm_l1`] metric.
     ///
@@ -117,45 +204,326 @@ impl Mosaic {
         self.distance_matrix_with_metric(norm_l1)
     }
 
+    /// Compute the [`DistanceMatrix`] like [`distance_matrix`](Mosaic::distance_matrix), until
+    /// the [`Mosaic::monitor`] is cancelled.
+    ///
+    /// # Errors
+    /// - [`PhomoError::Cancelled`]: The [`Mosaic::monitor`] was cancelled.
+    pub fn try_distance_matrix(&self) -> Result<DistanceMatrix, PhomoError> {
+        self.try_distance_matrix_with_metric(norm_l1)
+    }
+
     /// Compute the [`DistanceMatrix`] between the tiles and the master cells using the provided
-    /// `metric` function. See [`phomo::metrics`](crate::metrics) for implemented distance metrics.
+    /// `metric`. See [`phomo::metrics`](crate::metrics) for implemented distance metrics, any
+    /// type implementing the [`Metric`] trait, including closures, can be used.
+    ///
+    /// The cells with a [`mask`](Master::masks) are only compared to the tiles within their
+    /// mask.
     ///
     /// The row index is the cell index and the column index is the tile index.
-    pub fn distance_matrix_with_metric(&self, metric: MetricFn) -> DistanceMatrix {
+    pub fn distance_matrix_with_metric<M: Metric>(&self, metric: M) -> DistanceMatrix {
+        self.metric_distance_matrix(metric, &self.uncancellable_monitor())
+            .expect("The distance matrix computation can't be cancelled")
+    }
+
+    /// Compute the [`DistanceMatrix`] like
+    /// [`distance_matrix_with_metric`](Mosaic::distance_matrix_with_metric), until the
+    /// [`Mosaic::monitor`] is cancelled.
+    ///
+    /// # Errors
+    /// - [`PhomoError::Cancelled`]: The [`Mosaic::monitor`] was cancelled.
+    pub fn try_distance_matrix_with_metric<M: Metric>(
+        &self,
+        metric: M,
+    ) -> Result<DistanceMatrix, PhomoError> {
+        self.metric_distance_matrix(metric, &self.monitor)
+    }
+
+    fn metric_distance_matrix<M: Metric>(
+        &self,
+        metric: M,
+        monitor: &Monitor,
+    ) -> Result<DistanceMatrix, PhomoError> {
         #[cfg(not(target_family = "wasm"))]
         info!("Computing distance matrix...");
         #[cfg(not(target_family = "wasm"))]
         let start_time = time::Instant::now();
 
-        let d_matrix = macros::maybe_progress_bar!(
-            macros::iter_or_par_iter!(self.master.cells),
-            "Computing distance matrix",
-            par
-        )
-        .flat_map(|cell| macros::iter_or_par_iter!(self.tiles).map(|tile| metric(tile, cell)))
-        .collect();
+        let step = monitor.step("Computing distance matrix", self.master.cells.len());
+        // The cancelled rows are skipped, the step then returns an error
+        let mut d_matrix = macros::iter_or_par_iter!(self.master.cells)
+            .zip(macros::iter_or_par_iter!(self.master.masks))
+            .filter(|_| step.tick().is_ok())
+            .flat_map(|(cell, mask)| match mask {
+                Some(mask) => {
+                    let mut masked = MaskedCell::new(cell, mask);
+                    self.tiles
+                        .iter()
+                        .map(|tile| masked.distance(&metric, tile))
+                        .collect::<Vec<_>>()
+                }
+                None => macros::iter_or_par_iter!(self.tiles)
+                    .map(|tile| metric.distance(tile, cell))
+                    .collect(),
+            })
+            .collect::<Vec<_>>();
+        step.finish()?;
+        self.apply_tile_weights(&mut d_matrix);
 
         #[cfg(not(target_family = "wasm"))]
         info!("Completed in {:?}", start_time.elapsed());
 
         // We can construct the struct directly because we know the sizes should line up
-        DistanceMatrix {
+        Ok(DistanceMatrix {
+            rows: self.master.cells.len(),
+            columns: self.tiles.len(),
+            data: d_matrix,
+        })
+    }
+
+    /// Compute the [`DistanceMatrix`] between the tiles and the master cells using the provided
+    /// feature `extractor`. See [`phomo::features`](crate::features) for implemented feature
+    /// extractors.
+    ///
+    /// The features of each tile and master cell are extracted once, the distances are then
+    /// computed between the features, which is much faster than comparing the full images for
+    /// every pair. The features of the cells with a [`mask`](Master::masks) are only extracted
+    /// from the pixels within their mask.
+    ///
+    /// The row index is the cell index and the column index is the tile index.
+    pub fn distance_matrix_with_features<F: FeatureExtractor>(
+        &self,
+        extractor: F,
+    ) -> DistanceMatrix {
+        self.feature_distance_matrix(extractor, &self.uncancellable_monitor())
+            .expect("The distance matrix computation can't be cancelled")
+    }
+
+    /// Compute the [`DistanceMatrix`] like
+    /// [`distance_matrix_with_features`](Mosaic::distance_matrix_with_features), until the
+    /// [`Mosaic::monitor`] is cancelled.
+    ///
+    /// # Errors
+    /// - [`PhomoError::Cancelled`]: The [`Mosaic::monitor`] was cancelled.
+    pub fn try_distance_matrix_with_features<F: FeatureExtractor>(
+        &self,
+        extractor: F,
+    ) -> Result<DistanceMatrix, PhomoError> {
+        self.feature_distance_matrix(extractor, &self.monitor)
+    }
+
+    fn feature_distance_matrix<F: FeatureExtractor>(
+        &self,
+        extractor: F,
+        monitor: &Monitor,
+    ) -> Result<DistanceMatrix, PhomoError> {
+        let (tile_features, cell_features) = self.features(&extractor, monitor)?;
+
+        #[cfg(not(target_family = "wasm"))]
+        info!("Computing distance matrix...");
+        #[cfg(not(target_family = "wasm"))]
+        let start_time = time::Instant::now();
+
+        let step = monitor.step("Computing distance matrix", cell_features.len());
+        let mut d_matrix = macros::iter_or_par_iter!(cell_features)
+            .filter(|_| step.tick().is_ok())
+            .flat_map(|cell| {
+                macros::iter_or_par_iter!(tile_features).map(|tile| extractor.distance(tile, cell))
+            })
+            .collect::<Vec<_>>();
+        step.finish()?;
+        self.apply_tile_weights(&mut d_matrix);
+
+        #[cfg(not(target_family = "wasm"))]
+        info!("Completed in {:?}", start_time.elapsed());
+
+        Ok(DistanceMatrix {
             rows: self.master.cells.len(),
             columns: self.tiles.len(),
             data: d_matrix,
+        })
+    }
+
+    /// Find the `k` nearest tiles of each master cell, using the provided feature `extractor`.
+    /// See [`phomo::features`](crate::features) for implemented feature extractors.
+    ///
+    /// The tile features are indexed in a vantage point tree, so the nearest tiles of each cell
+    /// are found without computing the distance to every tile, and the memory usage grows with
+    /// `k` instead of with the number of tiles. The search is exact when the feature distance
+    /// satisfies the triangle inequality, which is the case of the
+    /// [`MeanColor`](crate::features::MeanColor) and [`Histogram`](crate::features::Histogram)
+    /// features, and of the [`Thumbnail`](crate::features::Thumbnail) feature with the
+    /// [`norm_l1`] metric.
+    ///
+    /// Returns, for each master cell, the indices of its nearest tiles and their distances, in
+    /// increasing distance order. The [tile weights](Mosaic::set_tile_weights) are added to the
+    /// distances of the nearest tiles, they don't change which tiles are the nearest.
+    pub fn nearest_tiles_with_features<F: FeatureExtractor>(
+        &self,
+        extractor: F,
+        k: usize,
+    ) -> Vec<Vec<(usize, i64)>> {
+        self.nearest_tiles(extractor, k, &self.uncancellable_monitor())
+            .expect("The nearest tiles search can't be cancelled")
+    }
+
+    /// Find the `k` nearest tiles of each master cell like
+    /// [`nearest_tiles_with_features`](Mosaic::nearest_tiles_with_features), until the
+    /// [`Mosaic::monitor`] is cancelled.
+    ///
+    /// # Errors
+    /// - [`PhomoError::Cancelled`]: The [`Mosaic::monitor`] was cancelled.
+    pub fn try_nearest_tiles_with_features<F: FeatureExtractor>(
+        &self,
+        extractor: F,
+        k: usize,
+    ) -> Result<Vec<Vec<(usize, i64)>>, PhomoError> {
+        self.nearest_tiles(extractor, k, &self.monitor)
+    }
+
+    fn nearest_tiles<F: FeatureExtractor>(
+        &self,
+        extractor: F,
+        k: usize,
+        monitor: &Monitor,
+    ) -> Result<Vec<Vec<(usize, i64)>>, PhomoError> {
+        let (tile_features, cell_features) = self.features(&extractor, monitor)?;
+
+        #[cfg(not(target_family = "wasm"))]
+        info!("Indexing tile features...");
+        #[cfg(not(target_family = "wasm"))]
+        let start_time = time::Instant::now();
+
+        let tree = VpTree::new(tile_features.len(), |a, b| {
+            extractor.distance(&tile_features[a], &tile_features[b])
+        });
+
+        #[cfg(not(target_family = "wasm"))]
+        info!("Completed in {:?}", start_time.elapsed());
+        #[cfg(not(target_family = "wasm"))]
+        info!("Searching {} nearest tiles...", k);
+        #[cfg(not(target_family = "wasm"))]
+        let start_time = time::Instant::now();
+
+        let step = monitor.step("Searching nearest tiles", cell_features.len());
+        let candidates = macros::iter_or_par_iter!(cell_features)
+            .map(|cell| {
+                step.tick()?;
+                let mut nearest =
+                    tree.nearest(k, |tile| extractor.distance(&tile_features[tile], cell));
+                // The candidates are the nearest tiles, the weights only offset their costs
+                if let Some(tile_weights) = &self.tile_weights {
+                    nearest
+                        .iter_mut()
+                        .for_each(|(tile, cost)| *cost += tile_weights[*tile]);
+                }
+                Ok(nearest)
+            })
+            .collect::<Result<_, PhomoError>>()?;
+        step.finish()?;
+
+        #[cfg(not(target_family = "wasm"))]
+        info!("Completed in {:?}", start_time.elapsed());
+
+        Ok(candidates)
+    }
+
+    /// Compute a [`SparseDistanceMatrix`] which only holds the `k` nearest tiles of each master
+    /// cell, using the provided feature `extractor`. See [`phomo::features`](crate::features) for
+    /// implemented feature extractors.
+    ///
+    /// The nearest tiles are found with
+    /// [`nearest_tiles_with_features`](Mosaic::nearest_tiles_with_features), so the memory usage
+    /// grows with `k` instead of with the number of tiles.
+    ///
+    /// The solvers can only assign a candidate tile to a cell, so `k` should leave enough room
+    /// for the solver to find a complete assignment, especially without repeated tiles.
+    ///
+    /// The row index is the cell index and the column index is the tile index.
+    pub fn sparse_distance_matrix_with_features<F: FeatureExtractor>(
+        &self,
+        extractor: F,
+        k: usize,
+    ) -> SparseDistanceMatrix {
+        self.sparse_distance_matrix(self.nearest_tiles_with_features(extractor, k))
+    }
+
+    /// Compute a [`SparseDistanceMatrix`] like
+    /// [`sparse_distance_matrix_with_features`](Mosaic::sparse_distance_matrix_with_features),
+    /// until the [`Mosaic::monitor`] is cancelled.
+    ///
+    /// # Errors
+    /// - [`PhomoError::Cancelled`]: The [`Mosaic::monitor`] was cancelled.
+    pub fn try_sparse_distance_matrix_with_features<F: FeatureExtractor>(
+        &self,
+        extractor: F,
+        k: usize,
+    ) -> Result<SparseDistanceMatrix, PhomoError> {
+        Ok(self.sparse_distance_matrix(self.try_nearest_tiles_with_features(extractor, k)?))
+    }
+
+    fn sparse_distance_matrix(&self, candidates: Vec<Vec<(usize, i64)>>) -> SparseDistanceMatrix {
+        // The candidates are tile indices so they are within bounds
+        SparseDistanceMatrix::new(self.master.cells.len(), self.tiles.len(), candidates)
+            .expect("The candidates should match the mosaic dimensions")
+    }
+
+    /// The [`Mosaic::monitor`] without its cancellation, for the computations which can't fail.
+    fn uncancellable_monitor(&self) -> Monitor {
+        Monitor {
+            progress: self.monitor.progress.clone(),
+            cancellation: CancellationToken::default(),
         }
     }
 
-    pub(crate) fn check_distance_matrix(
+    /// Extract the features of the tiles and of the master cells.
+    fn features<F: FeatureExtractor>(
         &self,
-        distance_matrix: &DistanceMatrix,
+        extractor: &F,
+        monitor: &Monitor,
+    ) -> Result<Features<F>, PhomoError> {
+        #[cfg(not(target_family = "wasm"))]
+        info!("Computing features...");
+        #[cfg(not(target_family = "wasm"))]
+        let start_time = time::Instant::now();
+
+        let step = monitor.step("Computing tile features", self.tiles.len());
+        let tile_features = macros::iter_or_par_iter!(self.tiles)
+            .map(|tile| {
+                step.tick()?;
+                Ok(extractor.extract(tile))
+            })
+            .collect::<Result<_, PhomoError>>()?;
+        step.finish()?;
+        let step = monitor.step("Computing cell features", self.master.cells.len());
+        let cell_features = macros::iter_or_par_iter!(self.master.cells)
+            .zip(macros::iter_or_par_iter!(self.master.masks))
+            .map(|(cell, mask)| {
+                step.tick()?;
+                Ok(match mask {
+                    Some(mask) => extractor.extract_masked(cell, mask),
+                    None => extractor.extract(cell),
+                })
+            })
+            .collect::<Result<_, PhomoError>>()?;
+        step.finish()?;
+
+        #[cfg(not(target_family = "wasm"))]
+        info!("Completed in {:?}", start_time.elapsed());
+
+        Ok((tile_features, cell_features))
+    }
+
+    pub(crate) fn check_distance_matrix<C: CostMatrix>(
+        &self,
+        distance_matrix: &C,
     ) -> Result<(), PhomoError> {
-        if distance_matrix.rows != self.master.cells.len()
-            || distance_matrix.columns < self.tiles.len()
+        if distance_matrix.n_rows() != self.master.cells.len()
+            || distance_matrix.n_columns() < self.tiles.len()
         {
             return Err(MosaicError::DistanceMatrixSizeMismatch {
                 expected: (self.master.cells.len(), self.tiles.len()),
-                found: (distance_matrix.rows, distance_matrix.columns),
+                found: (distance_matrix.n_rows(), distance_matrix.n_columns()),
             }
             .into());
         }
@@ -172,6 +540,7 @@ impl Mosaic {
     /// # Errors
     /// - [`PhomoError::MosaicError`]: An error occurred while rendering the mosaic.
     /// - [`PhomoError::ImageError`]: An error occurred while copying the tiles to the mosaic image.
+    /// - [`PhomoError::Cancelled`]: The [`Mosaic::monitor`] was cancelled.
     pub fn render(&self, assignments: Vec<usize>) -> Result<RgbImage, PhomoError> {
         if assignments.len() != self.master.cells.len() {
             return Err(MosaicError::InvalidAssignmentsLength {
@@ -181,30 +550,69 @@ impl Mosaic {
             .into());
         }
 
-        let (grid_width, _) = self.grid_size;
-        let (cell_width, cell_height) = self.master.cell_size;
-        let mut mosaic_img = RgbImage::new(self.master.img.width(), self.master.img.height());
-        for (cell_idx, tile_idx) in assignments.into_iter().enumerate() {
-            let x = (cell_idx as u32 % grid_width) * cell_width;
-            let y = (cell_idx as u32 / grid_width) * cell_height;
+        let mut mosaic_img = self.background.fill(&self.master.img);
+        let step = self.monitor.step("Rendering mosaic", assignments.len());
+        for (region, tile_idx) in self.master.regions.iter().zip(assignments) {
             let tile = self
                 .tiles
                 .get(tile_idx % self.tiles.len())
                 .ok_or(MosaicError::InvalidTileIndex(tile_idx))?;
-            mosaic_img.copy_from(tile, x, y)?;
+            layout::draw_tile(&mut mosaic_img, tile, region)?;
+            step.tick()?;
         }
         Ok(mosaic_img)
     }
 
+    /// The [`Spacing`] of the master cells, on the grid or between the cell regions when the
+    /// [`Master`] doesn't use the [`Layout::Grid`] or has a [`mask`](Master::mask).
+    ///
+    /// # Arguments
+    /// - `config`: The configuration the repaired assignments must respect.
+    /// - `radius`: The radius within which a tile can't be repeated, 0 disables the repairs.
+    pub fn spacing(&self, config: SolverConfig, radius: u32) -> Spacing {
+        if *self.master.layout() == Layout::Grid && self.master.mask.is_none() {
+            Spacing::new(config, self.grid_size.0, radius)
+        } else {
+            let cell_neighbours = self.master.neighbours(radius);
+            Spacing::with_neighbours(config, radius, cell_neighbours)
+        }
+    }
+
+    /// The [`Annealing`] solver of the master cells, with the neighbours of the cells on the grid or
+    /// between the cell regions when the [`Master`] doesn't use the [`Layout::Grid`] or has a
+    /// [`mask`](Master::mask), in which case the seams are ignored.
+    ///
+    /// # Arguments
+    /// - `config`: The configuration for the solver.
+    /// - `objective`: The neighbour aware terms of the objective.
+    /// - `schedule`: The cooling schedule.
+    pub fn annealing(
+        &self,
+        config: SolverConfig,
+        objective: Objective,
+        schedule: Schedule,
+    ) -> Annealing {
+        if *self.master.layout() == Layout::Grid && self.master.mask.is_none() {
+            Annealing::new(config, self.grid_size.0, objective, schedule)
+        } else {
+            let cell_neighbours = self.master.neighbours(objective.repeat_radius);
+            Annealing::with_neighbours(config, cell_neighbours, objective, schedule)
+        }
+    }
+
     /// Compute the tile to master cell assignments using the [`Hungarian`] solver
     /// algorithm, and build the photo mosaic image.
     ///
+    /// The `distance_matrix` can be a dense [`DistanceMatrix`] or a [`SparseDistanceMatrix`].
+    /// Use [`Mosaic::build_with_solver`] with a [`Spaced`](crate::Spaced) solver to avoid the
+    /// repeated tiles within a radius.
+    ///
     /// # Errors
     /// - [`PhomoError::MosaicError`]: An error occurred while building the mosaic.
     /// - [`PhomoError::SolverError`]: An error occurred while solving the tile to cell assignments.
-    pub fn build(
+    pub fn build<C: CostMatrix>(
         &self,
-        distance_matrix: DistanceMatrix,
+        distance_matrix: C,
         config: SolverConfig,
     ) -> Result<RgbImage, PhomoError> {
         let solver = Hungarian::new(config);
@@ -215,19 +623,20 @@ impl Mosaic {
     /// the photo mosaic image. See [`phomo::solvers`](crate::solvers) for implemented solvers.
     ///
     /// # Arguments
-    /// - `distance_matrix`: The distance matrix between the master image and the tiles.
+    /// - `distance_matrix`: The distance matrix between the master image and the tiles, either a
+    ///   dense [`DistanceMatrix`] or a [`SparseDistanceMatrix`].
     /// - `solver`: The solver algorithm to use for the assignment problem.
     ///
     /// # Errors
     /// - [`PhomoError::MosaicError`]: An error occurred while building the mosaic.
     /// - [`PhomoError::SolverError`]: An error occurred while solving the tile to cell assignments.
-    pub fn build_with_solver<S: Solve>(
+    pub fn build_with_solver<C: CostMatrix, S: Solve>(
         &self,
-        distance_matrix: DistanceMatrix,
+        distance_matrix: C,
         mut solver: S,
     ) -> Result<RgbImage, PhomoError> {
         self.check_distance_matrix(&distance_matrix)?;
-        let assignments = distance_matrix.assignments(&mut solver)?;
+        let assignments = solve_timed(&distance_matrix, &mut solver)?;
         self.render(assignments)
     }
 }
@@ -235,6 +644,11 @@ impl Mosaic {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::layout::Voronoi;
+    use crate::progress::CancellationToken;
+    use crate::solvers::error::SolverError;
+    use crate::solvers::spacing::Spaced;
+    use image::{imageops, GenericImageView};
     use std::path::PathBuf;
 
     fn test_dir() -> PathBuf {
@@ -304,6 +718,471 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_distance_matrix_with_custom_metric() {
+        struct Scaled(i64);
+        impl Metric for Scaled {
+            fn distance(&self, tile: &RgbImage, cell: &RgbImage) -> i64 {
+                self.0 * norm_l1(tile, cell)
+            }
+        }
+
+        let master_img = image::open(test_master_img()).unwrap().to_rgb8();
+        let tiles = utils::read_images_from_dir(test_tile_dir()).unwrap();
+        let mosaic = Mosaic::from_images(master_img, tiles, (4, 4)).unwrap();
+        let distance_matrix = mosaic.distance_matrix();
+        let scaled = mosaic.distance_matrix_with_metric(Scaled(2));
+        assert!(distance_matrix
+            .data
+            .iter()
+            .zip(scaled.data.iter())
+            .all(|(d, s)| 2 * d == *s));
+
+        let boxed: Box<dyn Metric> = Box::new(Scaled(3));
+        let scaled = mosaic.distance_matrix_with_metric(boxed);
+        assert!(distance_matrix
+            .data
+            .iter()
+            .zip(scaled.data.iter())
+            .all(|(d, s)| 3 * d == *s));
+    }
+
+    #[test]
+    fn test_distance_matrix_with_features() {
+        let master_img = image::open(test_master_img()).unwrap().to_rgb8();
+        let tiles = utils::read_images_from_dir(test_tile_dir()).unwrap();
+        let mosaic = Mosaic::from_images(master_img, tiles, (4, 4)).unwrap();
+        let distance_matrix = mosaic.distance_matrix_with_metric(crate::metrics::avg_color);
+        let features = mosaic.distance_matrix_with_features(crate::features::MeanColor);
+        assert_eq!(distance_matrix.rows, features.rows);
+        assert_eq!(distance_matrix.columns, features.columns);
+        assert!(distance_matrix
+            .data
+            .iter()
+            .zip(features.data.iter())
+            .all(|(d, f)| d.abs_diff(*f) <= 1));
+    }
+
+    #[test]
+    fn test_nearest_tiles_with_features() {
+        use crate::features::Histogram;
+
+        let master_img = image::open(test_master_img()).unwrap().to_rgb8();
+        let tiles = utils::read_images_from_dir(test_tile_dir()).unwrap();
+        let mosaic = Mosaic::from_images(master_img, tiles, (4, 4)).unwrap();
+        let distance_matrix = mosaic.distance_matrix_with_features(Histogram::default());
+
+        let nearest = mosaic.nearest_tiles_with_features(Histogram::default(), 3);
+        assert_eq!(nearest.len(), mosaic.master.cells.len());
+        for (row, nearest) in nearest.into_iter().enumerate() {
+            let mut expected = distance_matrix.data
+                [row * distance_matrix.columns..(row + 1) * distance_matrix.columns]
+                .iter()
+                .copied()
+                .enumerate()
+                .collect::<Vec<_>>();
+            expected.sort_by_key(|&(column, cost)| (cost, column));
+            assert_eq!(nearest.len(), 3);
+            // The distances are sorted, the ties may be in any order
+            assert_eq!(
+                nearest.iter().map(|&(_, cost)| cost).collect::<Vec<_>>(),
+                expected[..3]
+                    .iter()
+                    .map(|&(_, cost)| cost)
+                    .collect::<Vec<_>>()
+            );
+            assert!(nearest.iter().all(|&(column, cost)| distance_matrix.data
+                [row * distance_matrix.columns + column]
+                == cost));
+        }
+    }
+
+    #[test]
+    fn test_sparse_distance_matrix_with_features() {
+        use crate::features::Histogram;
+        use crate::solvers::{auction::Auction, greedy::Greedy};
+
+        let master_img = image::open(test_master_img()).unwrap().to_rgb8();
+        let tiles = utils::read_images_from_dir(test_tile_dir()).unwrap();
+        let mosaic = Mosaic::from_images(master_img, tiles, (4, 4)).unwrap();
+        let distance_matrix = mosaic.distance_matrix_with_features(Histogram::default());
+
+        let sparse = mosaic.sparse_distance_matrix_with_features(Histogram::default(), 3);
+        assert_eq!(sparse.n_candidates(), 3 * mosaic.master.cells.len());
+        for row in 0..sparse.rows {
+            let mut expected = distance_matrix.row_costs(row).collect::<Vec<_>>();
+            expected.sort_by_key(|&(column, cost)| (cost, column));
+            let mut nearest = sparse.row_costs(row).collect::<Vec<_>>();
+            nearest.sort_by_key(|&(column, cost)| (cost, column));
+            assert_eq!(nearest, expected[..3]);
+        }
+
+        // with all the tiles as candidates, the solvers should match the dense solution
+        let sparse = mosaic.sparse_distance_matrix_with_features(Histogram::default(), 16);
+        assert_eq!(
+            sparse.assignments(&mut Hungarian::default()).unwrap(),
+            distance_matrix
+                .assignments(&mut Hungarian::default())
+                .unwrap()
+        );
+        assert_eq!(
+            sparse.assignments(&mut Greedy::default()).unwrap(),
+            distance_matrix.assignments(&mut Greedy::default()).unwrap()
+        );
+        assert_eq!(
+            sparse.assignments(&mut Auction::default()).unwrap(),
+            distance_matrix
+                .assignments(&mut Auction::default())
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_build_sparse_repeats() {
+        let master_img = image::open(test_master_img()).unwrap().to_rgb8();
+        let tiles = utils::read_images_from_dir(test_tile_dir())
+            .unwrap()
+            .into_iter()
+            .take(4)
+            .collect::<Vec<_>>();
+        let mosaic = Mosaic::from_images(master_img, tiles, (4, 4)).unwrap();
+        let config = SolverConfig {
+            max_tile_occurrences: 4,
+            ..Default::default()
+        };
+
+        let distance_matrix = mosaic.distance_matrix();
+        let dense = distance_matrix
+            .assignments(&mut Hungarian::new(config.clone()))
+            .unwrap();
+        assert!(dense.iter().all(|&tile| tile < 4));
+
+        // keeping every candidate gives the same assignments
+        let sparse = distance_matrix.to_sparse(4);
+        assert_eq!(
+            sparse
+                .assignments(&mut Hungarian::new(config.clone()))
+                .unwrap(),
+            dense
+        );
+        assert!(mosaic.build(sparse, config.clone()).is_ok());
+
+        // a single candidate tile for all the cells can't satisfy the capacities
+        let sparse = SparseDistanceMatrix::new(16, 4, vec![vec![(0, 0)]; 16]).unwrap();
+        assert!(mosaic.build(sparse, config).is_err());
+    }
+
+    #[test]
+    fn test_build_quadtree() {
+        let master_img = image::open(test_master_img()).unwrap().to_rgb8();
+        let layout = Layout::Quadtree(crate::layout::Quadtree {
+            max_depth: 1,
+            threshold: 0.0,
+            ..Default::default()
+        });
+        let master = Master::from_image_with_layout(master_img, (4, 4), layout).unwrap();
+        let tiles = utils::read_images_from_dir(test_tile_dir()).unwrap();
+        let mosaic = Mosaic::new(master, tiles, (4, 4)).unwrap();
+        // Every grid cell is subdivided
+        assert_eq!(mosaic.master.cells.len(), 64);
+
+        let config = SolverConfig {
+            max_tile_occurrences: 64,
+            ..Default::default()
+        };
+        let d_matrix = mosaic.distance_matrix();
+        let assignments = d_matrix
+            .assignments(&mut Spaced::new(
+                Hungarian::new(config.clone()),
+                mosaic.spacing(config, 1),
+            ))
+            .unwrap();
+        let mosaic_img = mosaic.render(assignments.clone()).unwrap();
+        assert_eq!(mosaic_img.dimensions(), mosaic.master.img.dimensions());
+
+        // The tiles are scaled down to the quadrants
+        let region = &mosaic.master.regions[0];
+        let tile = imageops::resize(
+            &mosaic.tiles[assignments[0]],
+            region.width,
+            region.height,
+            imageops::FilterType::Triangle,
+        );
+        assert_eq!(
+            mosaic_img
+                .view(
+                    region.x as u32,
+                    region.y as u32,
+                    region.width,
+                    region.height
+                )
+                .to_image(),
+            tile
+        );
+    }
+
+    #[test]
+    fn test_build_hexagonal() {
+        let master_img = image::open(test_master_img()).unwrap().to_rgb8();
+        let master = Master::from_image_with_layout(master_img, (4, 4), Layout::Hexagonal).unwrap();
+        let tiles = utils::read_images_from_dir(test_tile_dir()).unwrap();
+        let mosaic = Mosaic::new(master, tiles, (4, 4)).unwrap();
+        // 6 rows of hexagons, the odd rows have a partial hexagon on each side
+        assert_eq!(mosaic.master.cells.len(), 3 * 4 + 3 * 5);
+        assert!(mosaic.master.masks.iter().all(|mask| mask.is_some()));
+
+        // Only the pixels within the hexagons weigh on the distances
+        let d_matrix = mosaic.distance_matrix();
+        let (tile, cell) = (&mosaic.tiles[0], &mosaic.master.cells[0]);
+        let mask = mosaic.master.masks[0].as_ref().unwrap();
+        let masked = RgbImage::from_fn(cell.width(), cell.height(), |x, y| {
+            if mask.get_pixel(x, y).0 == [0] {
+                *cell.get_pixel(x, y)
+            } else {
+                *tile.get_pixel(x, y)
+            }
+        });
+        assert_eq!(d_matrix.data[0], norm_l1(&masked, cell));
+        assert!(d_matrix.data[0] < norm_l1(tile, cell));
+
+        let config = SolverConfig {
+            max_tile_occurrences: 2,
+            ..Default::default()
+        };
+        let mosaic_img = mosaic.build(d_matrix, config).unwrap();
+        assert_eq!(mosaic_img.dimensions(), (256, 256));
+        // The hexagons cover the whole mosaic, the corners of the cells show the neighbouring
+        // tiles
+        let region = &mosaic.master.regions[5];
+        assert_eq!((region.x, region.y), (32, 32));
+        let corner = mosaic_img.get_pixel(region.x as u32, region.y as u32);
+        let centre = mosaic_img.get_pixel(region.x as u32 + 32, region.y as u32 + 32);
+        assert_ne!(corner, centre);
+    }
+
+    /// Paint the pixels of the masked cells outside of their mask.
+    fn paint_outside_masks(mosaic: &mut Mosaic) {
+        for (cell, mask) in mosaic.master.cells.iter_mut().zip(&mosaic.master.masks) {
+            if let Some(mask) = mask {
+                for (pixel, m) in cell.pixels_mut().zip(mask.pixels()) {
+                    if m.0 == [0] {
+                        *pixel = image::Rgb([255, 0, 255]);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_features_hexagonal() {
+        let master_img = image::open(test_master_img()).unwrap().to_rgb8();
+        let master = Master::from_image_with_layout(master_img, (4, 4), Layout::Hexagonal).unwrap();
+        let tiles = utils::read_images_from_dir(test_tile_dir()).unwrap();
+        let mut mosaic = Mosaic::new(master, tiles, (4, 4)).unwrap();
+        let d_matrix = mosaic.distance_matrix_with_features(crate::features::MeanColor);
+
+        // The corners of the cells outside of the hexagons don't weigh on the features
+        paint_outside_masks(&mut mosaic);
+        assert_eq!(
+            mosaic.distance_matrix_with_features(crate::features::MeanColor),
+            d_matrix
+        );
+        let (tile, cell) = (&mosaic.tiles[0], &mosaic.master.cells[0]);
+        let mask = mosaic.master.masks[0].as_ref().unwrap();
+        assert_eq!(
+            d_matrix.data[0],
+            crate::features::MeanColor.distance(
+                &crate::features::MeanColor.extract(tile),
+                &crate::features::MeanColor.extract_masked(cell, mask)
+            )
+        );
+    }
+
+    #[test]
+    fn test_build_voronoi() {
+        let master_img = image::open(test_master_img()).unwrap().to_rgb8();
+        let layout = Layout::Voronoi(Voronoi::default());
+        let master = Master::from_image_with_layout(master_img, (4, 4), layout).unwrap();
+        let tiles = utils::read_images_from_dir(test_tile_dir()).unwrap();
+        let mosaic = Mosaic::new(master, tiles, (4, 4)).unwrap();
+        assert_eq!(mosaic.master.cells.len(), 16);
+        assert!(mosaic.master.masks.iter().all(|mask| mask.is_some()));
+
+        // Only the pixels within the polygons weigh on the distances
+        let d_matrix = mosaic.distance_matrix();
+        let (tile, cell) = (&mosaic.tiles[0], &mosaic.master.cells[0]);
+        let mask = mosaic.master.masks[0].as_ref().unwrap();
+        let masked = RgbImage::from_fn(cell.width(), cell.height(), |x, y| {
+            if mask.get_pixel(x, y).0 == [0] {
+                *cell.get_pixel(x, y)
+            } else {
+                *tile.get_pixel(x, y)
+            }
+        });
+        assert_eq!(d_matrix.data[0], norm_l1(&masked, cell));
+        assert!(d_matrix.data[0] < norm_l1(tile, cell));
+
+        let assignments = (0..16).collect::<Vec<_>>();
+        let mosaic_img = mosaic.render(assignments).unwrap();
+        // The tiles are scaled to cover the regions of the polygons, and clipped to them
+        for (cell, region) in mosaic.master.regions.iter().enumerate() {
+            let (x, y) = region.pixels(mosaic_img.dimensions()).next().unwrap();
+            let (img_x, img_y) = region.absolute(x, y);
+            let tile = imageops::resize(
+                &mosaic.tiles[cell],
+                region.width,
+                region.height,
+                imageops::FilterType::Triangle,
+            );
+            assert_eq!(mosaic_img.get_pixel(img_x, img_y), tile.get_pixel(x, y));
+        }
+    }
+
+    #[test]
+    fn test_features_voronoi() {
+        let master_img = image::open(test_master_img()).unwrap().to_rgb8();
+        let layout = Layout::Voronoi(Voronoi::default());
+        let master = Master::from_image_with_layout(master_img, (4, 4), layout).unwrap();
+        let tiles = utils::read_images_from_dir(test_tile_dir()).unwrap();
+        let mut mosaic = Mosaic::new(master, tiles, (4, 4)).unwrap();
+        let histograms =
+            mosaic.distance_matrix_with_features(crate::features::Histogram::default());
+        let mean_colors = mosaic.distance_matrix_with_features(crate::features::MeanColor);
+
+        // The pixels of the bounding boxes outside of the polygons are ignored
+        paint_outside_masks(&mut mosaic);
+        assert_eq!(
+            mosaic.distance_matrix_with_features(crate::features::Histogram::default()),
+            histograms
+        );
+        assert_eq!(
+            mosaic.distance_matrix_with_features(crate::features::MeanColor),
+            mean_colors
+        );
+    }
+
+    #[test]
+    fn test_build_masked() {
+        let master_img = image::open(test_master_img()).unwrap().to_rgb8();
+        // Only the top left quarter of the master is tiled
+        let mask = image::GrayImage::from_fn(256, 256, |x, y| {
+            image::Luma([if x < 128 && y < 128 { 255 } else { 0 }])
+        });
+        let master = Master::from_image(master_img, (4, 4))
+            .unwrap()
+            .with_mask(&image::DynamicImage::ImageLuma8(mask))
+            .unwrap();
+        let tiles = utils::read_images_from_dir(test_tile_dir()).unwrap();
+        let mut mosaic = Mosaic::new(master, tiles, (4, 4)).unwrap();
+        assert_eq!(mosaic.master.cells.len(), 4);
+
+        let d_matrix = mosaic.distance_matrix();
+        assert_eq!(d_matrix.rows, 4);
+        let config = SolverConfig {
+            max_tile_occurrences: 4,
+            ..Default::default()
+        };
+        let spacing = mosaic.spacing(config.clone(), 1);
+        let spaced = || Spaced::new(Hungarian::new(config.clone()), spacing.clone());
+        let mosaic_img = mosaic
+            .build_with_solver(d_matrix.clone(), spaced())
+            .unwrap();
+        // The rest of the mosaic is the master
+        assert_eq!(
+            mosaic_img.view(128, 0, 128, 256).to_image(),
+            mosaic.master.img.view(128, 0, 128, 256).to_image()
+        );
+        assert_ne!(
+            mosaic_img.view(0, 0, 128, 128).to_image(),
+            mosaic.master.img.view(0, 0, 128, 128).to_image()
+        );
+
+        mosaic.background = Background::Color([255, 0, 255]);
+        let mosaic_img = mosaic.build_with_solver(d_matrix, spaced()).unwrap();
+        assert_eq!(mosaic_img.get_pixel(128, 0).0, [255, 0, 255]);
+        assert_eq!(mosaic_img.get_pixel(255, 255).0, [255, 0, 255]);
+    }
+
+    #[test]
+    fn test_features_partly_masked() {
+        let master_img = image::open(test_master_img()).unwrap().to_rgb8();
+        // The cells of the second column are cut in half by the mask
+        let mask =
+            image::GrayImage::from_fn(256, 256, |x, _| image::Luma([if x < 96 { 255 } else { 0 }]));
+        let master = Master::from_image(master_img, (4, 4))
+            .unwrap()
+            .with_mask(&image::DynamicImage::ImageLuma8(mask))
+            .unwrap();
+        let tiles = utils::read_images_from_dir(test_tile_dir()).unwrap();
+        let mut mosaic = Mosaic::new(master, tiles, (4, 4)).unwrap();
+        assert_eq!(mosaic.master.cells.len(), 8);
+        assert_eq!(
+            mosaic
+                .master
+                .masks
+                .iter()
+                .filter(|mask| mask.is_some())
+                .count(),
+            4
+        );
+        let histograms =
+            mosaic.distance_matrix_with_features(crate::features::Histogram::default());
+        let mean_colors = mosaic.distance_matrix_with_features(crate::features::MeanColor);
+
+        // Only the halves of the cells within the mask weigh on their features
+        paint_outside_masks(&mut mosaic);
+        assert_eq!(
+            mosaic.distance_matrix_with_features(crate::features::Histogram::default()),
+            histograms
+        );
+        assert_eq!(
+            mosaic.distance_matrix_with_features(crate::features::MeanColor),
+            mean_colors
+        );
+    }
+
+    #[test]
+    fn test_build_brick() {
+        let master_img = image::open(test_master_img()).unwrap().to_rgb8();
+        let master = Master::from_image_with_layout(master_img, (4, 4), Layout::Brick).unwrap();
+        let tiles = utils::read_images_from_dir(test_tile_dir()).unwrap();
+        let mosaic = Mosaic::new(master, tiles, (4, 4)).unwrap();
+        // The offset rows have an extra cell
+        assert_eq!(mosaic.master.cells.len(), 2 * 4 + 2 * 5);
+        assert!(mosaic
+            .master
+            .cells
+            .iter()
+            .all(|cell| cell.dimensions() == (64, 64)));
+
+        // The partial cells are sampled from the part of the master within the image
+        assert_eq!(
+            mosaic.master.cells[4].view(32, 0, 32, 64).to_image(),
+            mosaic.master.img.view(0, 64, 32, 64).to_image()
+        );
+        // Only the part within the image is compared to the tiles
+        let mask = mosaic.master.masks[4].as_ref().unwrap();
+        assert_eq!(mask.get_pixel(31, 0).0, [0]);
+        assert_eq!(mask.get_pixel(32, 0).0, [255]);
+        assert!(mosaic.master.masks[0].is_none());
+
+        let assignments = (0..18).map(|cell| cell % 16).collect::<Vec<_>>();
+        let mosaic_img = mosaic.render(assignments.clone()).unwrap();
+        // The partial cells show the part of their tile within the image, without scaling it
+        let region = &mosaic.master.regions[4];
+        assert_eq!((region.x, region.y), (-32, 64));
+        let tile = &mosaic.tiles[assignments[4]];
+        assert_eq!(
+            mosaic_img.view(0, 64, 32, 64).to_image(),
+            tile.view(32, 0, 32, 64).to_image()
+        );
+        let tile = &mosaic.tiles[assignments[8]];
+        assert_eq!(
+            mosaic_img.view(224, 64, 32, 64).to_image(),
+            tile.view(0, 0, 32, 64).to_image()
+        );
+    }
+
     #[test]
     fn test_too_few_tiles() {
         let master_img = image::open(test_master_img()).unwrap().to_rgb8();
@@ -334,8 +1213,146 @@ mod tests {
             d_matrix,
             SolverConfig {
                 max_tile_occurrences: 8,
+                ..Default::default()
             },
         );
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_min_tile_occurrences() {
+        let master_img = image::open(test_master_img()).unwrap().to_rgb8();
+        let tiles = utils::read_images_from_dir(test_tile_dir())
+            .unwrap()
+            .into_iter()
+            .take(8)
+            .collect::<Vec<_>>();
+        let mosaic = Mosaic::from_images(master_img, tiles, (4, 4)).unwrap();
+        let config = SolverConfig {
+            max_tile_occurrences: 4,
+            min_tile_occurrences: 2,
+            ..Default::default()
+        };
+
+        let assignments = mosaic
+            .distance_matrix()
+            .assignments(&mut Hungarian::new(config.clone()))
+            .unwrap();
+        assert!((0..8).all(|tile| assignments.iter().filter(|&&t| t == tile).count() >= 2));
+
+        // 16 cells can't use each of the 8 tiles 3 times
+        let result = mosaic.build(
+            mosaic.distance_matrix(),
+            SolverConfig {
+                min_tile_occurrences: 3,
+                ..config
+            },
+        );
+        assert!(matches!(
+            result,
+            Err(PhomoError::SolverError(SolverError::TooFewRows { .. }))
+        ));
+    }
+
+    #[test]
+    fn test_tile_weights() {
+        // 4 cells for 16 tiles
+        let master_img = image::open(test_master_img()).unwrap().to_rgb8();
+        let master_img = image::imageops::crop_imm(&master_img, 0, 0, 128, 128).to_image();
+        let tiles = utils::read_images_from_dir(test_tile_dir()).unwrap();
+        let mut mosaic = Mosaic::from_images(master_img, tiles, (2, 2)).unwrap();
+        let distance_matrix = mosaic.distance_matrix();
+        let assignments = distance_matrix
+            .assignments(&mut Hungarian::default())
+            .unwrap();
+
+        assert!(mosaic.set_tile_weights(vec![0; 4]).is_err());
+        // Penalising the used tiles makes the solver pick other tiles
+        let mut tile_weights = vec![0; 16];
+        assignments
+            .iter()
+            .for_each(|&tile| tile_weights[tile] = i64::from(u32::MAX));
+        mosaic.set_tile_weights(tile_weights).unwrap();
+        let weighted = mosaic.distance_matrix();
+        assert_eq!(
+            weighted.get(0, assignments[0]),
+            distance_matrix.get(0, assignments[0]) + i64::from(u32::MAX)
+        );
+        let weighted_assignments = weighted.assignments(&mut Hungarian::default()).unwrap();
+        assert!(weighted_assignments
+            .iter()
+            .all(|tile| !assignments.contains(tile)));
+    }
+
+    #[test]
+    fn test_monitor() {
+        let mut mosaic = Mosaic::from_file_and_dir(test_master_img(), test_tile_dir(), (4, 4))
+            .expect("Failed to create mosaic");
+        let updates = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = updates.clone();
+        mosaic.monitor = Monitor::new(
+            move |step: &str, done, total| {
+                recorded
+                    .lock()
+                    .unwrap()
+                    .push((step.to_string(), done, total))
+            },
+            CancellationToken::new(),
+        );
+
+        let distance_matrix = mosaic.distance_matrix();
+        mosaic.render(vec![0; 16]).unwrap();
+        {
+            let updates = updates.lock().unwrap();
+            assert_eq!(updates.len(), 32);
+            assert!(updates.contains(&("Computing distance matrix".to_string(), 16, 16)));
+            assert!(updates.contains(&("Rendering mosaic".to_string(), 16, 16)));
+        }
+
+        mosaic.monitor.cancellation.cancel();
+        assert!(matches!(
+            mosaic.try_distance_matrix(),
+            Err(PhomoError::Cancelled)
+        ));
+        assert!(matches!(
+            mosaic.try_sparse_distance_matrix_with_features(crate::features::MeanColor, 4),
+            Err(PhomoError::Cancelled)
+        ));
+        // The infallible variants ignore the cancellation
+        assert_eq!(mosaic.distance_matrix().data, distance_matrix.data);
+        assert!(matches!(
+            mosaic.render(
+                distance_matrix
+                    .assignments(&mut Hungarian::default())
+                    .unwrap()
+            ),
+            Err(PhomoError::Cancelled)
+        ));
+    }
+
+    #[test]
+    fn test_monitor_cell_features() {
+        let mut mosaic = Mosaic::from_file_and_dir(test_master_img(), test_tile_dir(), (4, 4))
+            .expect("Failed to create mosaic");
+        // Cancel as soon as the cell features are being extracted
+        let cancellation = CancellationToken::new();
+        let token = cancellation.clone();
+        let cell_updates = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = cell_updates.clone();
+        mosaic.monitor = Monitor::new(
+            move |step: &str, _, _| {
+                if step == "Computing cell features" {
+                    counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                    token.cancel();
+                }
+            },
+            cancellation,
+        );
+
+        assert!(matches!(
+            mosaic.try_distance_matrix_with_features(crate::features::MeanColor),
+            Err(PhomoError::Cancelled)
+        ));
+        assert!(cell_updates.load(std::sync::atomic::Ordering::Relaxed) < 16);
+    }
 }
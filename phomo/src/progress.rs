@@ -0,0 +1,262 @@
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+#[cfg(feature = "progress_bar")]
+extern crate indicatif;
+
+use crate::error::PhomoError;
+
+/// Receives the progress of the long running steps of the mosaic generation, such as the
+/// computation of the distance matrix, the solving of the assignments and the rendering.
+///
+/// Closures taking the step name, the number of completed units of work and the total number of
+/// units of work implement this trait.
+///
+/// The methods can be called from several threads when the `parallel` feature is enabled.
+pub trait Progress: Send + Sync {
+    /// Called when a step starts.
+    ///
+    /// # Arguments
+    /// - `step`: The name of the step.
+    /// - `total`: The number of units of work of the step.
+    fn start(&self, _step: &str, _total: usize) {}
+
+    /// Called when a unit of work of a step is completed.
+    ///
+    /// # Arguments
+    /// - `step`: The name of the step.
+    /// - `done`: The number of completed units of work.
+    /// - `total`: The number of units of work of the step.
+    fn update(&self, step: &str, done: usize, total: usize);
+
+    /// Called when a step ends, whether it completed or was cancelled.
+    ///
+    /// # Arguments
+    /// - `step`: The name of the step.
+    fn finish(&self, _step: &str) {}
+}
+
+impl<F: Fn(&str, usize, usize) + Send + Sync> Progress for F {
+    fn update(&self, step: &str, done: usize, total: usize) {
+        self(step, done, total)
+    }
+}
+
+/// A progress bar in the terminal, using the [`indicatif`] crate.
+#[cfg(feature = "progress_bar")]
+#[derive(Debug, Default)]
+pub struct TerminalProgress {
+    bar: std::sync::Mutex<Option<indicatif::ProgressBar>>,
+}
+
+#[cfg(feature = "progress_bar")]
+impl Progress for TerminalProgress {
+    fn start(&self, step: &str, total: usize) {
+        let bar = indicatif::ProgressBar::new(total as u64)
+            .with_message(step.to_string())
+            .with_style(
+                indicatif::ProgressStyle::with_template(
+                    "{msg}: {wide_bar} {elapsed_precise} {pos}/{len}",
+                )
+                .unwrap(),
+            );
+        *self.bar.lock().unwrap() = Some(bar);
+    }
+
+    fn update(&self, _step: &str, done: usize, _total: usize) {
+        if let Some(bar) = self.bar.lock().unwrap().as_ref() {
+            bar.set_position(done as u64);
+        }
+    }
+
+    fn finish(&self, _step: &str) {
+        if let Some(bar) = self.bar.lock().unwrap().take() {
+            bar.finish();
+        }
+    }
+}
+
+/// A token to cancel a running mosaic generation from another thread, or from a [`Progress`]
+/// callback. The clones of a token share their state.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Creates a new, not cancelled, token.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel the operations using this token, they return a [`PhomoError::Cancelled`] error.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the token was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// The Monitor struct reports the progress of the long running steps to a [`Progress`] and
+/// checks its [`CancellationToken`] between the units of work.
+///
+/// With the `progress_bar` feature, the default monitor reports its progress with a
+/// `TerminalProgress`.
+#[derive(Clone)]
+pub struct Monitor {
+    /// The receiver of the progress, `None` to not report any progress.
+    pub progress: Option<Arc<dyn Progress>>,
+    /// The token to cancel the running steps.
+    pub cancellation: CancellationToken,
+}
+
+impl Default for Monitor {
+    fn default() -> Self {
+        #[cfg(feature = "progress_bar")]
+        let progress: Option<Arc<dyn Progress>> = Some(Arc::new(TerminalProgress::default()));
+        #[cfg(not(feature = "progress_bar"))]
+        let progress = None;
+
+        Self {
+            progress,
+            cancellation: CancellationToken::default(),
+        }
+    }
+}
+
+impl fmt::Debug for Monitor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Monitor")
+            .field("progress", &self.progress.is_some())
+            .field("cancellation", &self.cancellation)
+            .finish()
+    }
+}
+
+impl Monitor {
+    /// Creates a new instance of the Monitor struct.
+    ///
+    /// # Arguments
+    /// - `progress`: The receiver of the progress.
+    /// - `cancellation`: The token to cancel the running steps.
+    pub fn new<P: Progress + 'static>(progress: P, cancellation: CancellationToken) -> Self {
+        Self {
+            progress: Some(Arc::new(progress)),
+            cancellation,
+        }
+    }
+
+    /// A monitor which doesn't report any progress and is never cancelled.
+    pub fn silent() -> Self {
+        Self {
+            progress: None,
+            cancellation: CancellationToken::default(),
+        }
+    }
+
+    /// Check whether the monitor was cancelled.
+    ///
+    /// # Errors
+    /// - [`PhomoError::Cancelled`]: The cancellation token was cancelled.
+    pub(crate) fn check(&self) -> Result<(), PhomoError> {
+        if self.cancellation.is_cancelled() {
+            return Err(PhomoError::Cancelled);
+        }
+        Ok(())
+    }
+
+    /// Start a step of `total` units of work.
+    pub(crate) fn step(&self, name: &'static str, total: usize) -> Step<'_> {
+        if let Some(progress) = &self.progress {
+            progress.start(name, total);
+        }
+        Step {
+            monitor: self,
+            name,
+            total,
+            done: AtomicUsize::new(0),
+        }
+    }
+}
+
+/// A running step of a [`Monitor`], the end of the step is reported when it is dropped.
+pub(crate) struct Step<'a> {
+    monitor: &'a Monitor,
+    name: &'static str,
+    total: usize,
+    done: AtomicUsize,
+}
+
+impl Step<'_> {
+    /// Complete a unit of work of the step.
+    ///
+    /// # Errors
+    /// - [`PhomoError::Cancelled`]: The monitor was cancelled.
+    pub(crate) fn tick(&self) -> Result<(), PhomoError> {
+        self.monitor.check()?;
+        let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Some(progress) = &self.monitor.progress {
+            progress.update(self.name, done, self.total);
+        }
+        Ok(())
+    }
+
+    /// End the step, checking whether it was cancelled. Useful when the units of work are
+    /// skipped rather than interrupted on cancellation, as in iterator adaptors.
+    ///
+    /// # Errors
+    /// - [`PhomoError::Cancelled`]: The monitor was cancelled.
+    pub(crate) fn finish(self) -> Result<(), PhomoError> {
+        self.monitor.check()
+    }
+}
+
+impl Drop for Step<'_> {
+    fn drop(&mut self) {
+        if let Some(progress) = &self.monitor.progress {
+            progress.finish(self.name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_step() {
+        let updates = Arc::new(Mutex::new(Vec::new()));
+        let recorded = updates.clone();
+        let monitor = Monitor::new(
+            move |step: &str, done, total| {
+                recorded
+                    .lock()
+                    .unwrap()
+                    .push((step.to_string(), done, total))
+            },
+            CancellationToken::new(),
+        );
+
+        let step = monitor.step("Testing", 2);
+        step.tick().unwrap();
+        step.tick().unwrap();
+        step.finish().unwrap();
+        assert_eq!(
+            *updates.lock().unwrap(),
+            vec![("Testing".to_string(), 1, 2), ("Testing".to_string(), 2, 2)]
+        );
+    }
+
+    #[test]
+    fn test_cancel() {
+        let monitor = Monitor::silent();
+        let step = monitor.step("Testing", 2);
+        step.tick().unwrap();
+        monitor.cancellation.clone().cancel();
+        assert!(matches!(step.tick(), Err(PhomoError::Cancelled)));
+        assert!(matches!(step.finish(), Err(PhomoError::Cancelled)));
+    }
+}
@@ -0,0 +1,171 @@
+use std::fmt;
+use std::time::Duration;
+#[cfg(not(target_family = "wasm"))]
+use std::time::Instant;
+#[cfg(target_family = "wasm")]
+use web_time::Instant;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::cost_matrix::CostMatrix;
+use crate::distance_matrix::solve_timed;
+use crate::error::PhomoError;
+use crate::solvers::error::SolverError;
+use crate::solvers::Solve;
+
+/// The assignments computed by a solver, along with statistics to compare the solvers and log the
+/// quality of the assignments.
+///
+/// See [`DistanceMatrix::report`](crate::DistanceMatrix::report) and
+/// [`SparseDistanceMatrix::report`](crate::SparseDistanceMatrix::report).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SolveReport {
+    /// The tile index assigned to each master cell.
+    pub assignments: Vec<usize>,
+    /// The sum of the costs of the assignments.
+    pub total_cost: i64,
+    /// The cost of the assignment of each master cell.
+    pub cell_costs: Vec<i64>,
+    /// The number of master cells assigned to each tile.
+    pub tile_usage: Vec<usize>,
+    /// The number of iterations of the solver, see [`Solve::iterations`].
+    pub iterations: Option<usize>,
+    /// The time taken by the solver, `None` if it wasn't measured.
+    pub elapsed: Option<Duration>,
+    /// Whether the assignments are proven optimal, see [`Solve::is_optimal`].
+    pub optimal: bool,
+}
+
+impl SolveReport {
+    /// Create a [`SolveReport`] from the `assignments` of the `cost_matrix`.
+    ///
+    /// # Arguments
+    /// - `cost_matrix`: The cost matrix of the assignments.
+    /// - `assignments`: The tile index assigned to each master cell.
+    /// - `iterations`: The number of iterations of the solver.
+    /// - `elapsed`: The time taken by the solver.
+    /// - `optimal`: Whether the assignments are proven optimal.
+    ///
+    /// # Errors
+    /// - [`PhomoError::SolverError`]: The `assignments` don't match the `cost_matrix`.
+    pub fn new<C: CostMatrix>(
+        cost_matrix: &C,
+        assignments: Vec<usize>,
+        iterations: Option<usize>,
+        elapsed: Option<Duration>,
+        optimal: bool,
+    ) -> Result<Self, PhomoError> {
+        if assignments.len() != cost_matrix.n_rows() {
+            return Err(SolverError::InvalidAssignment {
+                row: assignments.len().min(cost_matrix.n_rows()),
+            }
+            .into());
+        }
+        let mut tile_usage = vec![0; cost_matrix.n_columns()];
+        let mut cell_costs = Vec::with_capacity(assignments.len());
+        for (row, &column) in assignments.iter().enumerate() {
+            if column >= cost_matrix.n_columns() {
+                return Err(SolverError::InvalidAssignment { row }.into());
+            }
+            tile_usage[column] += 1;
+            // A pinned tile can be assigned without being a candidate of a sparse matrix
+            cell_costs.push(cost_matrix.cost(row, column).unwrap_or(0));
+        }
+
+        Ok(Self {
+            assignments,
+            total_cost: cell_costs.iter().sum(),
+            cell_costs,
+            tile_usage,
+            iterations,
+            elapsed,
+            optimal,
+        })
+    }
+
+    /// The mean cost of the assignments of the master cells.
+    pub fn mean_cost(&self) -> f64 {
+        self.total_cost as f64 / self.cell_costs.len().max(1) as f64
+    }
+
+    /// The number of distinct tiles used in the assignments.
+    pub fn n_used_tiles(&self) -> usize {
+        self.tile_usage.iter().filter(|&&usage| usage > 0).count()
+    }
+}
+
+impl fmt::Display for SolveReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "total cost: {}, mean cell cost: {:.1}, max cell cost: {}, tiles used: {}/{}, max tile usage: {}",
+            self.total_cost,
+            self.mean_cost(),
+            self.cell_costs.iter().max().copied().unwrap_or(0),
+            self.n_used_tiles(),
+            self.tile_usage.len(),
+            self.tile_usage.iter().max().copied().unwrap_or(0),
+        )?;
+        if let Some(iterations) = self.iterations {
+            write!(f, ", iterations: {}", iterations)?;
+        }
+        if let Some(elapsed) = self.elapsed {
+            write!(f, ", elapsed: {:?}", elapsed)?;
+        }
+        write!(f, ", optimal: {}", self.optimal)
+    }
+}
+
+/// Solve the assignment problem of the `cost_matrix` using the `solver`, and report on the
+/// assignments.
+pub(crate) fn solve_report<C: CostMatrix, S: Solve>(
+    cost_matrix: &C,
+    solver: &mut S,
+) -> Result<SolveReport, PhomoError> {
+    let start_time = Instant::now();
+    let assignments = solve_timed(cost_matrix, solver)?;
+    let elapsed = start_time.elapsed();
+
+    SolveReport::new(
+        cost_matrix,
+        assignments,
+        solver.iterations(),
+        Some(elapsed),
+        solver.is_optimal(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solvers::local_search::{LocalSearch, Refined};
+    use crate::{DistanceMatrix, Greedy, Hungarian};
+
+    #[test]
+    fn test_report() {
+        let d_matrix = DistanceMatrix::new(3, 4, vec![1, 2, 3, 4, 4, 3, 2, 1, 1, 1, 9, 9]).unwrap();
+        let report = d_matrix.report(&mut Hungarian::default()).unwrap();
+        assert_eq!(report.assignments, vec![0, 3, 1]);
+        assert_eq!(report.cell_costs, vec![1, 1, 1]);
+        assert_eq!(report.total_cost, 3);
+        assert_eq!(report.tile_usage, vec![1, 1, 0, 1]);
+        assert_eq!(report.n_used_tiles(), 3);
+        assert_eq!(report.iterations, None);
+        assert!(report.optimal);
+
+        let mut refined = Refined::new(Greedy::default(), LocalSearch::default());
+        let report = d_matrix.report(&mut refined).unwrap();
+        assert_eq!(report.total_cost, 3);
+        assert!(report.iterations.is_some());
+        assert!(!report.optimal);
+    }
+
+    #[test]
+    fn test_report_invalid() {
+        let d_matrix = DistanceMatrix::new(2, 2, vec![1, 2, 3, 4]).unwrap();
+        assert!(SolveReport::new(&d_matrix, vec![0], None, None, false).is_err());
+        assert!(SolveReport::new(&d_matrix, vec![0, 2], None, None, false).is_err());
+    }
+}
@@ -0,0 +1,655 @@
+use image::RgbImage;
+use log::{info, warn};
+
+use crate::cost_matrix::Pinned;
+use crate::error::PhomoError;
+use crate::solvers::error::SolverError;
+use crate::solvers::greedy::Greedy;
+use crate::solvers::{check_capacity, Deadline, Solve, SolverConfig, SplitMix64};
+use crate::{CostMatrix, DistanceMatrix};
+
+/// The number of moves between two checks of the cancellation and of the deadline.
+const CHECK_INTERVAL: usize = 1024;
+
+/// The border pixels of the tile images, to compare the colours across the seams of neighbouring
+/// tiles.
+#[derive(Debug, Clone)]
+pub struct TileEdges {
+    /// The top, bottom, left and right border pixels of each tile.
+    edges: Vec<[Vec<[i32; 3]>; 4]>,
+}
+
+impl TileEdges {
+    const TOP: usize = 0;
+    const BOTTOM: usize = 1;
+    const LEFT: usize = 2;
+    const RIGHT: usize = 3;
+
+    /// Creates a new instance of the TileEdges struct.
+    ///
+    /// # Arguments
+    /// - `tiles`: The tile images, in the order of the columns of the cost matrix.
+    pub fn new(tiles: &[RgbImage]) -> Self {
+        let edges = tiles
+            .iter()
+            .map(|tile| {
+                let (width, height) = tile.dimensions();
+                if width == 0 || height == 0 {
+                    return Default::default();
+                }
+                let pixel = |x, y| tile.get_pixel(x, y).0.map(i32::from);
+                [
+                    (0..width).map(|x| pixel(x, 0)).collect(),
+                    (0..width).map(|x| pixel(x, height - 1)).collect(),
+                    (0..height).map(|y| pixel(0, y)).collect(),
+                    (0..height).map(|y| pixel(width - 1, y)).collect(),
+                ]
+            })
+            .collect();
+        Self { edges }
+    }
+
+    /// The number of tiles.
+    pub fn len(&self) -> usize {
+        self.edges.len()
+    }
+
+    /// Whether there are no tiles.
+    pub fn is_empty(&self) -> bool {
+        self.edges.is_empty()
+    }
+
+    /// The colour difference between the `side` of the `tile` and the `other_side` of the
+    /// `other_tile`, summed over the pixels and channels.
+    fn difference(&self, tile: usize, side: usize, other_tile: usize, other_side: usize) -> i64 {
+        self.edges[tile][side]
+            .iter()
+            .zip(self.edges[other_tile][other_side].iter())
+            .map(|(a, b)| {
+                a.iter()
+                    .zip(b.iter())
+                    .map(|(a, b)| (a - b).unsigned_abs() as i64)
+                    .sum::<i64>()
+            })
+            .sum()
+    }
+}
+
+/// The terms the [`Annealing`] solver adds to the costs of the cost matrix, which depend on the
+/// tiles of neighbouring cells.
+#[derive(Debug, Clone)]
+pub struct Objective {
+    /// The border pixels of the tiles, `None` to ignore the seams between the tiles.
+    pub edges: Option<TileEdges>,
+    /// The weight of the colour difference across the seam of two horizontally or vertically
+    /// adjacent tiles of the grid, summed over the pixels and channels of the seam.
+    pub seam_weight: i64,
+    /// The penalty of each pair of identical tiles within the `repeat_radius` of each other.
+    pub repeat_penalty: i64,
+    /// The radius, in grid cells, within which repeated tiles are penalised. See
+    /// [`Master::neighbours`](crate::Master::neighbours) for the cells which don't form a grid.
+    pub repeat_radius: u32,
+}
+
+impl Default for Objective {
+    fn default() -> Self {
+        Self {
+            edges: None,
+            seam_weight: 1,
+            repeat_penalty: 0,
+            repeat_radius: 1,
+        }
+    }
+}
+
+/// The cooling schedule of the [`Annealing`] solver. The temperature decreases geometrically from
+/// the initial to the final temperature over the moves.
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    /// The initial temperature, relative to the mean cell cost of the initial assignments.
+    pub initial_temperature: f64,
+    /// The final temperature, relative to the mean cell cost of the initial assignments.
+    pub final_temperature: f64,
+    /// The number of moves tried for each cell which isn't pinned.
+    pub moves_per_cell: usize,
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Self {
+            initial_temperature: 0.05,
+            final_temperature: 0.0005,
+            moves_per_cell: 200,
+        }
+    }
+}
+
+/// The Annealing struct minimises the costs of the assignments plus the neighbour aware terms of
+/// an [`Objective`], with simulated annealing.
+///
+/// Starting from the [`Greedy`] assignments, random moves substitute the tile of a cell with
+/// another tile which can still be used, or swap the tiles of two cells. Moves which raise the
+/// objective are accepted with a probability which decreases with the temperature of the
+/// [`Schedule`]. The moves are drawn from the [`SolverConfig::seed`], 0 when `None`, so the same
+/// seed gives the same assignments.
+///
+/// The pinned cells of the configuration are never moved, and no tile is brought under its minimum
+/// usage. The repeats are only penalised, wrap the solver in a [`Spaced`](crate::Spaced) to
+/// remove them. The cells which don't form a uniform grid, those of the other
+/// [`Layout`](crate::Layout)s and of masked [`Master`](crate::Master)s, take the lists of their
+/// neighbours with [`Annealing::with_neighbours`], see [`Mosaic::annealing`](crate::Mosaic::annealing).
+#[derive(Debug, Clone)]
+pub struct Annealing {
+    config: SolverConfig,
+    grid_width: usize,
+    cell_neighbours: Option<Vec<Vec<usize>>>,
+    objective: Objective,
+    schedule: Schedule,
+    n_moves: Option<usize>,
+}
+
+impl Annealing {
+    /// Creates a new instance of the Annealing struct.
+    ///
+    /// # Arguments
+    /// - `config`: The configuration for the solver.
+    /// - `grid_width`: The number of cells in a row of the mosaic grid, the rows of the cost
+    ///   matrix being the cells in row major order.
+    /// - `objective`: The neighbour aware terms of the objective.
+    /// - `schedule`: The cooling schedule.
+    pub fn new(
+        config: SolverConfig,
+        grid_width: u32,
+        objective: Objective,
+        schedule: Schedule,
+    ) -> Self {
+        Self {
+            config,
+            grid_width: grid_width.max(1) as usize,
+            cell_neighbours: None,
+            objective,
+            schedule,
+            n_moves: None,
+        }
+    }
+
+    /// Creates a new instance of the Annealing struct, for cells which don't form a uniform grid.
+    ///
+    /// The cells have no horizontal or vertical seams, so only the repeated tiles are penalised
+    /// and the `edges` of the `objective` are ignored.
+    ///
+    /// # Arguments
+    /// - `config`: The configuration for the solver.
+    /// - `cell_neighbours`: The cells within the repeat radius of the `objective` of each cell,
+    ///   see [`Master::neighbours`](crate::Master::neighbours).
+    /// - `objective`: The neighbour aware terms of the objective.
+    /// - `schedule`: The cooling schedule.
+    pub fn with_neighbours(
+        config: SolverConfig,
+        cell_neighbours: Vec<Vec<usize>>,
+        objective: Objective,
+        schedule: Schedule,
+    ) -> Self {
+        if objective.edges.is_some() && objective.seam_weight != 0 {
+            warn!("Annealing: the seams are ignored when the cells don't form a grid");
+        }
+        Self {
+            config,
+            grid_width: 1,
+            cell_neighbours: Some(cell_neighbours),
+            objective: Objective {
+                edges: None,
+                ..objective
+            },
+            schedule,
+            n_moves: None,
+        }
+    }
+
+    /// The radius of the neighbourhood of a cell in which the pairwise terms are non zero.
+    fn radius(&self) -> usize {
+        if self.objective.repeat_penalty != 0 {
+            self.objective.repeat_radius.max(1) as usize
+        } else {
+            1
+        }
+    }
+
+    /// The cells within the radius of the `row`, excluding the `row` itself.
+    fn neighbours(&self, row: usize, n_rows: usize) -> impl Iterator<Item = usize> + '_ {
+        let listed = self
+            .cell_neighbours
+            .as_ref()
+            .map(|cell_neighbours| cell_neighbours.get(row).into_iter().flatten().copied());
+        let radius = self.radius();
+        let grid_height = n_rows.div_ceil(self.grid_width);
+        let (x, y) = (row % self.grid_width, row / self.grid_width);
+        let xs = x.saturating_sub(radius)..(x + radius + 1).min(self.grid_width);
+        let grid = (y.saturating_sub(radius)..(y + radius + 1).min(grid_height))
+            .flat_map(move |ny| xs.clone().map(move |nx| ny * self.grid_width + nx))
+            .filter(move |&other_row| other_row != row && other_row < n_rows);
+        // Both iterators are chained to unify their types, only one of them yields cells
+        let grid = listed.is_none().then_some(grid).into_iter().flatten();
+        listed
+            .into_iter()
+            .flatten()
+            .filter(move |&other_row| other_row != row && other_row < n_rows)
+            .chain(grid)
+    }
+
+    /// The pairwise term of the cells `row` and `other_row`.
+    fn pair_cost(&self, row: usize, other_row: usize, assignments: &[usize]) -> i64 {
+        let (column, other_column) = (assignments[row], assignments[other_row]);
+        if self.cell_neighbours.is_some() {
+            // The listed neighbours are all within the repeat radius
+            return if column == other_column {
+                self.objective.repeat_penalty
+            } else {
+                0
+            };
+        }
+        let (x, y) = (row % self.grid_width, row / self.grid_width);
+        let (other_x, other_y) = (other_row % self.grid_width, other_row / self.grid_width);
+        let mut cost = 0;
+        if column == other_column
+            && x.abs_diff(other_x).max(y.abs_diff(other_y)) <= self.objective.repeat_radius as usize
+        {
+            cost += self.objective.repeat_penalty;
+        }
+        if let Some(edges) = &self.objective.edges {
+            let seam = match (other_x as isize - x as isize, other_y as isize - y as isize) {
+                (1, 0) => Some((TileEdges::RIGHT, TileEdges::LEFT)),
+                (-1, 0) => Some((TileEdges::LEFT, TileEdges::RIGHT)),
+                (0, 1) => Some((TileEdges::BOTTOM, TileEdges::TOP)),
+                (0, -1) => Some((TileEdges::TOP, TileEdges::BOTTOM)),
+                _ => None,
+            };
+            if let Some((side, other_side)) = seam {
+                cost += self.objective.seam_weight
+                    * edges.difference(column, side, other_column, other_side);
+            }
+        }
+        cost
+    }
+
+    /// The pairwise terms involving the `rows`, each pair counted once.
+    fn local_cost(&self, rows: &[usize], assignments: &[usize]) -> i64 {
+        rows.iter()
+            .flat_map(|&row| {
+                self.neighbours(row, assignments.len())
+                    .filter(move |other_row| !rows.contains(other_row) || *other_row > row)
+                    .map(move |other_row| self.pair_cost(row, other_row, assignments))
+            })
+            .sum()
+    }
+
+    /// The pairwise terms of all the cells, each pair counted once.
+    fn pairwise_cost(&self, assignments: &[usize]) -> i64 {
+        (0..assignments.len())
+            .flat_map(|row| {
+                self.neighbours(row, assignments.len())
+                    .filter(move |&other_row| other_row > row)
+                    .map(move |other_row| self.pair_cost(row, other_row, assignments))
+            })
+            .sum()
+    }
+
+    /// Anneal the `assignments` of the `distance_matrix` until the `deadline`, and return the
+    /// number of moves tried.
+    fn anneal<C: CostMatrix>(
+        &self,
+        distance_matrix: &C,
+        mut assignments: Vec<usize>,
+        deadline: Deadline,
+    ) -> Result<(Vec<usize>, usize), PhomoError> {
+        // The pinned rows only have their pinned column as candidate, so they are never moved
+        let distance_matrix = &Pinned::new(distance_matrix, &self.config);
+        let n_rows = distance_matrix.n_rows();
+        let n_columns = distance_matrix.n_columns();
+        let capacity = self.config.max_tile_occurrences;
+        let min_occurrences = self.config.min_tile_occurrences;
+        if assignments.len() != n_rows {
+            return Err(SolverError::InvalidAssignment {
+                row: assignments.len().min(n_rows),
+            }
+            .into());
+        }
+        if let Some(edges) = &self.objective.edges {
+            if edges.len() != n_columns {
+                return Err(SolverError::InvalidTileEdges {
+                    expected: n_columns,
+                    found: edges.len(),
+                }
+                .into());
+            }
+        }
+
+        let mut counts = vec![0; n_columns];
+        let mut costs = Vec::with_capacity(n_rows);
+        for (row, &column) in assignments.iter().enumerate() {
+            let cost = (column < n_columns)
+                .then(|| distance_matrix.cost(row, column))
+                .flatten()
+                .ok_or(SolverError::InvalidAssignment { row })?;
+            counts[column] += 1;
+            if counts[column] > capacity {
+                return Err(SolverError::InvalidAssignment { row }.into());
+            }
+            costs.push(cost);
+        }
+
+        let pins = self.config.pinned_columns(n_rows);
+        let free_rows = (0..n_rows)
+            .filter(|&row| pins[row].is_none())
+            .collect::<Vec<_>>();
+        let n_moves = self.schedule.moves_per_cell * free_rows.len();
+        let initial_cost = costs.iter().sum::<i64>() + self.pairwise_cost(&assignments);
+        if n_moves == 0 {
+            return Ok((assignments, 0));
+        }
+
+        // The temperatures are scaled to the costs of the cells
+        let mean_cost = (costs.iter().sum::<i64>() as f64 / n_rows as f64).max(1.0);
+        let initial_temperature = self.schedule.initial_temperature * mean_cost;
+        let final_temperature = self
+            .schedule
+            .final_temperature
+            .min(self.schedule.initial_temperature)
+            * mean_cost;
+        let cooling = if initial_temperature > 0.0 && final_temperature > 0.0 {
+            (final_temperature / initial_temperature).powf(1.0 / n_moves as f64)
+        } else {
+            0.0
+        };
+
+        let mut rng = SplitMix64::new(self.config.seed.unwrap_or(0));
+        let mut temperature = initial_temperature;
+        let mut cost = initial_cost;
+        let mut best_cost = cost;
+        let mut best_assignments = assignments.clone();
+        let mut n_tried = 0;
+        let step = self
+            .config
+            .monitor
+            .step("Annealing", n_moves.div_ceil(CHECK_INTERVAL));
+        while n_tried < n_moves {
+            if n_tried % CHECK_INTERVAL == 0 {
+                step.tick()?;
+                if cost < best_cost {
+                    best_cost = cost;
+                    best_assignments.clone_from(&assignments);
+                }
+                if deadline.is_past() {
+                    break;
+                }
+            }
+            n_tried += 1;
+            temperature *= cooling;
+
+            let row = free_rows[rng.below(free_rows.len())];
+            let column = assignments[row];
+            // The cells changed by the move, with their new tile and cost. Half of the moves swap
+            // the tiles of two cells, the others substitute the tile of a cell
+            let (changes, n_changes) = if rng.next_u64() & 1 == 0 {
+                let other_row = free_rows[rng.below(free_rows.len())];
+                let other_column = assignments[other_row];
+                if other_column == column {
+                    continue;
+                }
+                let (Some(cost), Some(other_cost)) = (
+                    distance_matrix.cost(row, other_column),
+                    distance_matrix.cost(other_row, column),
+                ) else {
+                    continue;
+                };
+                (
+                    [(row, other_column, cost), (other_row, column, other_cost)],
+                    2,
+                )
+            } else {
+                let new_column = rng.below(n_columns);
+                if new_column == column
+                    || counts[new_column] >= capacity
+                    || counts[column] <= min_occurrences
+                {
+                    continue;
+                }
+                let Some(cost) = distance_matrix.cost(row, new_column) else {
+                    continue;
+                };
+                ([(row, new_column, cost); 2], 1)
+            };
+            let changes = &changes[..n_changes];
+            let rows = changes.iter().map(|&(row, _, _)| row).collect::<Vec<_>>();
+
+            let old_cost = self.local_cost(&rows, &assignments)
+                + rows.iter().map(|&row| costs[row]).sum::<i64>();
+            let reverse = apply(changes, &mut assignments, &mut costs, &mut counts);
+            let new_cost = self.local_cost(&rows, &assignments)
+                + rows.iter().map(|&row| costs[row]).sum::<i64>();
+            let delta = new_cost - old_cost;
+            if delta <= 0 || rng.unit() < (-(delta as f64) / temperature).exp() {
+                cost += delta;
+            } else {
+                // The changes are undone in reverse order
+                reverse.iter().rev().for_each(|&change| {
+                    apply(&[change], &mut assignments, &mut costs, &mut counts);
+                });
+            }
+        }
+        if cost < best_cost {
+            best_cost = cost;
+            best_assignments = assignments;
+        }
+
+        info!(
+            "Annealing: cost {} -> {} in {} moves",
+            initial_cost, best_cost, n_tried
+        );
+        Ok((best_assignments, n_tried))
+    }
+}
+
+impl Solve for Annealing {
+    fn solve(&mut self, distance_matrix: &DistanceMatrix) -> Result<Vec<usize>, PhomoError> {
+        self.solve_cost_matrix(distance_matrix)
+    }
+
+    fn solve_cost_matrix<C: CostMatrix>(
+        &mut self,
+        distance_matrix: &C,
+    ) -> Result<Vec<usize>, PhomoError> {
+        check_capacity(distance_matrix, &self.config)?;
+        // The time budget of the configuration covers both the greedy and the annealing
+        let deadline = Deadline::new(self.config.time_budget);
+        let assignments = Greedy::new(self.config.clone()).solve_cost_matrix(distance_matrix)?;
+        let (assignments, n_moves) = self.anneal(distance_matrix, assignments, deadline)?;
+        self.n_moves = Some(n_moves);
+        Ok(assignments)
+    }
+
+    /// The number of moves tried.
+    fn iterations(&self) -> Option<usize> {
+        self.n_moves
+    }
+}
+
+/// Apply the `changes` of a move, each being a row with its new column and cost, and return the
+/// changes which undo them.
+fn apply(
+    changes: &[(usize, usize, i64)],
+    assignments: &mut [usize],
+    costs: &mut [i64],
+    counts: &mut [usize],
+) -> Vec<(usize, usize, i64)> {
+    changes
+        .iter()
+        .map(|&(row, column, cost)| {
+            let reverse = (row, assignments[row], costs[row]);
+            counts[assignments[row]] -= 1;
+            counts[column] += 1;
+            assignments[row] = column;
+            costs[row] = cost;
+            reverse
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solvers::test_utils::distance_matrix;
+    use crate::DistanceMatrix;
+    use image::Rgb;
+
+    /// Tiles whose left and top halves have a colour, and right and bottom halves another.
+    fn tiles(n: usize) -> Vec<RgbImage> {
+        (0..n)
+            .map(|i| {
+                let (a, b) = ((i * 37 % 256) as u8, (i * 91 % 256) as u8);
+                RgbImage::from_fn(4, 4, |x, y| {
+                    if x + y < 4 {
+                        Rgb([a, a, b])
+                    } else {
+                        Rgb([b, a, a])
+                    }
+                })
+            })
+            .collect()
+    }
+
+    fn objective_cost(
+        annealing: &Annealing,
+        d_matrix: &DistanceMatrix,
+        assignments: &[usize],
+    ) -> i64 {
+        let unary: i64 = assignments
+            .iter()
+            .enumerate()
+            .map(|(row, &column)| d_matrix.get(row, column))
+            .sum();
+        unary + annealing.pairwise_cost(assignments)
+    }
+
+    #[test]
+    fn test_tile_edges() {
+        let tile = RgbImage::from_fn(2, 3, |x, y| Rgb([x as u8, y as u8, 0]));
+        let edges = TileEdges::new(&[tile.clone(), tile]);
+        assert_eq!(edges.len(), 2);
+        // The right edge has x = 1 and the left edge x = 0, over 3 pixels
+        assert_eq!(edges.difference(0, TileEdges::RIGHT, 1, TileEdges::LEFT), 3);
+        // The bottom edge has y = 2 and the top edge y = 0, over 2 pixels
+        assert_eq!(edges.difference(0, TileEdges::BOTTOM, 1, TileEdges::TOP), 4);
+    }
+
+    #[test]
+    fn test_annealing() {
+        // 8x8 grid
+        let d_matrix = distance_matrix(64, 40);
+        let config = SolverConfig {
+            max_tile_occurrences: 2,
+            min_tile_occurrences: 1,
+            pinned: vec![(0, 3), (9, 3)],
+            ..Default::default()
+        };
+        let objective = Objective {
+            edges: Some(TileEdges::new(&tiles(40))),
+            repeat_penalty: 500,
+            ..Default::default()
+        };
+        let mut annealing = Annealing::new(config.clone(), 8, objective, Schedule::default());
+        let assignments = annealing.solve(&d_matrix).unwrap();
+        assert_eq!(annealing.iterations(), Some(62 * 200));
+
+        let mut counts = [0; 40];
+        assignments.iter().for_each(|&column| counts[column] += 1);
+        assert!(counts.iter().all(|&count| (1..=2).contains(&count)));
+        assert_eq!((assignments[0], assignments[9]), (3, 3));
+
+        let greedy = Greedy::new(config).solve(&d_matrix).unwrap();
+        assert!(
+            objective_cost(&annealing, &d_matrix, &assignments)
+                < objective_cost(&annealing, &d_matrix, &greedy)
+        );
+    }
+
+    #[test]
+    fn test_repeat_penalty() {
+        // 4 tiles of equal costs, each used 4 times on a 4x4 grid, can be laid out in 2x2 blocks
+        // without any repeat within a radius of 1
+        let d_matrix = DistanceMatrix::new(16, 4, vec![0; 64]).unwrap();
+        let config = SolverConfig {
+            max_tile_occurrences: 4,
+            ..Default::default()
+        };
+        let objective = Objective {
+            repeat_penalty: 100,
+            ..Default::default()
+        };
+        let mut annealing = Annealing::new(config, 4, objective, Schedule::default());
+        let assignments = annealing.solve(&d_matrix).unwrap();
+        assert_eq!(objective_cost(&annealing, &d_matrix, &assignments), 0);
+    }
+
+    #[test]
+    fn test_with_neighbours() {
+        // A row of 6 cells, each neighbouring the previous and the next cell, which can use the 3
+        // tiles of equal costs without any neighbouring repeat
+        let d_matrix = DistanceMatrix::new(6, 3, vec![0; 18]).unwrap();
+        let cell_neighbours = (0..6)
+            .map(|row: usize| {
+                (row.saturating_sub(1)..(row + 2).min(6))
+                    .filter(|&other_row| other_row != row)
+                    .collect()
+            })
+            .collect();
+        let config = SolverConfig {
+            max_tile_occurrences: 2,
+            ..Default::default()
+        };
+        let objective = Objective {
+            edges: Some(TileEdges::new(&tiles(3))),
+            repeat_penalty: 100,
+            ..Default::default()
+        };
+        let mut annealing =
+            Annealing::with_neighbours(config, cell_neighbours, objective, Schedule::default());
+        let assignments = annealing.solve(&d_matrix).unwrap();
+        assert_eq!(objective_cost(&annealing, &d_matrix, &assignments), 0);
+        for row in 0..5 {
+            assert_ne!(assignments[row], assignments[row + 1]);
+        }
+    }
+
+    #[test]
+    fn test_seed() {
+        let d_matrix = distance_matrix(36, 40);
+        let objective = Objective {
+            edges: Some(TileEdges::new(&tiles(40))),
+            ..Default::default()
+        };
+        let solve = |seed| {
+            let config = SolverConfig {
+                seed,
+                ..Default::default()
+            };
+            Annealing::new(config, 6, objective.clone(), Schedule::default())
+                .solve(&d_matrix)
+                .unwrap()
+        };
+        assert_eq!(solve(Some(1)), solve(Some(1)));
+        assert_ne!(solve(Some(1)), solve(Some(2)));
+    }
+
+    #[test]
+    fn test_invalid_tile_edges() {
+        let d_matrix = distance_matrix(4, 4);
+        let objective = Objective {
+            edges: Some(TileEdges::new(&tiles(3))),
+            ..Default::default()
+        };
+        let mut annealing =
+            Annealing::new(SolverConfig::default(), 2, objective, Schedule::default());
+        assert!(annealing.solve(&d_matrix).is_err());
+    }
+}
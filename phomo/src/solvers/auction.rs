@@ -1,126 +1,631 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use log::info;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::cost_matrix::{MinUsage, Pinned, Shuffled};
 use crate::error::PhomoError;
-use crate::solvers::Solve;
+use crate::macros;
+use crate::progress::Monitor;
 use crate::solvers::SolverConfig;
-use crate::DistanceMatrix;
+use crate::solvers::{check_capacity, fallback, Deadline, Solve};
+use crate::{CostMatrix, DistanceMatrix};
 
 use super::error::AuctionError;
 use super::error::SolverError;
 
+const UNASSIGNED: usize = usize::MAX;
+
+/// How the unassigned agents bid in each round of the [`Auction`] solver.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Bidding {
+    /// The unassigned agents bid one at a time, each bid sees the prices raised by the previous
+    /// bids.
+    #[default]
+    GaussSeidel,
+    /// All the unassigned agents bid at once against the same prices, the highest bid for each
+    /// object wins. The bids are computed in parallel with the `parallel` feature.
+    Jacobi,
+}
+
 /// The Auction struct represents the auction algorithm solver.
+///
+/// The cells are the agents, bidding for the tiles. Each tile is made of
+/// [`SolverConfig::max_tile_occurrences`] objects, each with its own price. An agent bids on the
+/// cheapest object of its best tile, and an outbid agent goes back to bidding. When there are
+/// more objects than cells, dummy agents for which every tile costs 0 take the leftover objects.
+///
+/// With ε-scaling, the auction is first run with a large ε, which is divided by the scaling
+/// factor for each following run, down to the final ε. Each run starts from the prices of the
+/// previous one.
+///
+/// After solving, [`Auction::optimality_gap`] gives an upper bound of the difference between
+/// the cost of the assignment and the optimal cost, the duality gap of the final prices. It isn't
+/// bounded by `n_cells * epsilon` when the dummy agents or the sparse candidates make the
+/// problem unbalanced.
+///
+/// See:
+///     <https://web.mit.edu/dimitrib/www/Auction_Survey.pdf>
 #[derive(Debug)]
 pub struct Auction {
     epsilon: i64,
+    scaling_factor: Option<i64>,
+    bidding: Bidding,
     config: SolverConfig,
+    optimality_gap: Option<i64>,
+    iterations: Option<usize>,
 }
 
 impl Default for Auction {
+    /// An epsilon of 1 without ε-scaling, like [`Auction::new`].
     fn default() -> Self {
-        Self {
-            epsilon: 1,
-            config: SolverConfig::default(),
-        }
+        Self::new(1, SolverConfig::default())
     }
 }
 
 impl Auction {
-    /// Creates a new instance of the Auction struct with the given epsilon and configuration.
+    /// Creates a new instance of the Auction struct with the given epsilon and configuration,
+    /// without ε-scaling.
     ///
     /// # Arguments
     /// - `epsilon`: The epsilon value for the auction algorithm.
     /// - `config`: The configuration for the solver.
     pub fn new(epsilon: i64, config: SolverConfig) -> Self {
-        Self { epsilon, config }
+        Self {
+            epsilon: epsilon.max(1),
+            scaling_factor: None,
+            bidding: Bidding::default(),
+            config,
+            optimality_gap: None,
+            iterations: None,
+        }
+    }
+
+    /// Creates a new instance of the Auction struct which uses ε-scaling.
+    ///
+    /// # Arguments
+    /// - `epsilon`: The final epsilon value.
+    /// - `scaling_factor`: The factor by which epsilon is divided between runs, at least 2.
+    /// - `bidding`: How the unassigned agents bid.
+    /// - `config`: The configuration for the solver.
+    pub fn with_epsilon_scaling(
+        epsilon: i64,
+        scaling_factor: i64,
+        bidding: Bidding,
+        config: SolverConfig,
+    ) -> Self {
+        Self {
+            epsilon: epsilon.max(1),
+            scaling_factor: Some(scaling_factor.max(2)),
+            bidding,
+            config,
+            optimality_gap: None,
+            iterations: None,
+        }
+    }
+
+    /// An upper bound of the difference between the cost of the last solved assignment and the
+    /// optimal cost, computed from the duality gap. `None` if nothing was solved yet, or if the
+    /// auction ran out of its [`SolverConfig::time_budget`].
+    pub fn optimality_gap(&self) -> Option<i64> {
+        self.optimality_gap
     }
 }
 
-/// Finds the best and second-best tasks for a given agent.
+#[derive(Debug)]
+struct AuctionState {
+    /// The number of objects of each task.
+    capacity: usize,
+    /// The price of each object, the objects of task `j` are `j * capacity..(j + 1) * capacity`.
+    prices: Vec<i64>,
+    /// The agent holding each object.
+    holders: Vec<usize>,
+    /// The cheapest object of each task.
+    cheapest: Vec<usize>,
+    /// The object held by each agent.
+    assignment: Vec<usize>,
+    /// The `(price, object)` pairs of the objects, cheapest first. The prices only increase, so
+    /// the outdated pairs are dropped when they reach the top of the heap.
+    by_price: BinaryHeap<Reverse<(i64, usize)>>,
+}
+
+impl AuctionState {
+    fn objects(&self, task: usize) -> std::ops::Range<usize> {
+        task * self.capacity..(task + 1) * self.capacity
+    }
+
+    /// The price of the second cheapest object of the `task`, if it has more than one object.
+    fn second_cheapest_price(&self, task: usize) -> Option<i64> {
+        let cheapest = self.cheapest[task];
+        self.objects(task)
+            .filter(|&object| object != cheapest)
+            .map(|object| self.prices[object])
+            .min()
+    }
+
+    /// Pop the cheapest object and its price from the price heap.
+    fn pop_cheapest(&mut self) -> Option<(i64, usize)> {
+        while let Some(Reverse((price, object))) = self.by_price.pop() {
+            if self.prices[object] == price {
+                return Some((price, object));
+            }
+        }
+        None
+    }
+
+    /// Computes the bid of a dummy agent, for which all the tasks cost 0. It bids on the
+    /// cheapest object up to the price of the second cheapest one, which are read from the price
+    /// heap instead of scanning all the tasks.
+    fn dummy_bid(&mut self, epsilon: i64) -> Option<(usize, i64)> {
+        let (price, object) = self.pop_cheapest()?;
+        let second = self.pop_cheapest();
+        self.by_price.push(Reverse((price, object)));
+        let price = match second {
+            Some((second_price, second_object)) => {
+                self.by_price.push(Reverse((second_price, second_object)));
+                second_price
+            }
+            None => price,
+        };
+        Some((object, price + epsilon))
+    }
+
+    /// Give the `object` to the `agent` at the `price`, returns the agent which was outbid.
+    fn award(&mut self, agent: usize, object: usize, price: i64) -> Option<usize> {
+        self.prices[object] = price;
+        self.by_price.push(Reverse((price, object)));
+        let outbid = std::mem::replace(&mut self.holders[object], agent);
+        self.assignment[agent] = object;
+        let task = object / self.capacity;
+        self.cheapest[task] = self
+            .objects(task)
+            .min_by_key(|&object| self.prices[object])
+            .expect("A task has at least one object");
+        (outbid != UNASSIGNED).then(|| {
+            self.assignment[outbid] = UNASSIGNED;
+            outbid
+        })
+    }
+}
+
+/// Finds the best and second-best tasks among the candidate `tasks` of an agent.
+///
+/// # Returns
+/// A tuple of the best task index, the best value, and the second-best value, or None if there
+/// are no candidate tasks. The second-best value is `i64::MIN` when there is a single candidate.
+fn find_best_and_second_best<I: Iterator<Item = (usize, i64)>>(
+    tasks: I,
+    state: &AuctionState,
+) -> Option<(usize, i64, i64)> {
+    let mut best_task = UNASSIGNED;
+    let mut best_value = i64::MIN;
+    let mut second_best_value = i64::MIN;
+
+    for (task, cost) in tasks {
+        let value = -cost - state.prices[state.cheapest[task]];
+        if value > best_value {
+            second_best_value = best_value;
+            best_value = value;
+            best_task = task;
+        } else if value > second_best_value {
+            second_best_value = value;
+        }
+    }
+
+    (best_task != UNASSIGNED).then_some((best_task, best_value, second_best_value))
+}
+
+/// Computes the bid of an agent.
 ///
 /// # Arguments
-/// - `agent`: The index of the agent.
+/// - `agent`: The index of the agent, a row of the `distance_matrix`.
 /// - `distance_matrix`: The distance matrix.
-/// - `prices`: The prices for each task.
-/// - `task_assigned`: The number of times each task has been assigned.
-/// - `max_tile_occurrences`: The maximum number of times a task can be assigned.
+/// - `state`: The current prices and assignments.
+/// - `epsilon`: The minimum bid increment.
 ///
 /// # Returns
-/// An option containing a tuple of the best task index, the best value, and the second-best value, or None if there are fewer than two tasks to compare.
-fn find_best_and_second_best(
+/// The object the agent bids on and its bid price, or None if the agent has no candidate task.
+fn bid<C: CostMatrix>(
     agent: usize,
-    distance_matrix: &DistanceMatrix,
-    prices: &[i64],
-    task_assigned: &[usize],
-    max_tile_occurrences: usize,
-) -> Option<(usize, i64, i64)> {
-    let mut best_task = usize::MAX;
-    let mut best_value = i64::MIN;
-    let mut second_best_value = i64::MIN;
+    distance_matrix: &C,
+    state: &AuctionState,
+    epsilon: i64,
+) -> Option<(usize, i64)> {
+    let (best_task, best_value, mut second_best_value) =
+        find_best_and_second_best(distance_matrix.row_costs(agent), state)?;
+
+    // The other objects of the best task compete with the other tasks
+    let object = state.cheapest[best_task];
+    if let Some(price) = state.second_cheapest_price(best_task) {
+        second_best_value = second_best_value.max(best_value + state.prices[object] - price);
+    }
+    let increment = if second_best_value == i64::MIN {
+        // A single candidate object
+        epsilon
+    } else {
+        best_value - second_best_value + epsilon
+    };
+    Some((object, state.prices[object] + increment))
+}
+
+/// Computes the bids of the unassigned `dummies` as a group. The dummy agents are
+/// interchangeable, so they bid on distinct objects, the cheapest ones, up to the price of the
+/// next cheapest object.
+fn dummy_bids(dummies: &[usize], state: &AuctionState, epsilon: i64) -> Vec<(usize, usize, i64)> {
+    if dummies.is_empty() {
+        return Vec::new();
+    }
+    let mut objects = state
+        .prices
+        .iter()
+        .enumerate()
+        .map(|(object, &price)| (price, object))
+        .collect::<Vec<_>>();
+    let n = dummies.len().min(objects.len() - 1);
+    objects.select_nth_unstable(n);
+    let price = objects[n].0 + epsilon;
+    dummies
+        .iter()
+        .zip(objects[..n].iter())
+        .map(|(&dummy, &(_, object))| (dummy, object, price))
+        .collect()
+}
+
+/// Run the auction until all the agents are assigned, with a fixed `epsilon`.
+///
+/// Returns the number of rounds, and whether all the agents were assigned before the `deadline`.
+fn run_auction<C: CostMatrix>(
+    distance_matrix: &C,
+    state: &mut AuctionState,
+    epsilon: i64,
+    bidding: Bidding,
+    price_limit: i64,
+    monitor: &Monitor,
+    deadline: Deadline,
+) -> Result<(usize, bool), PhomoError> {
+    state.holders.fill(UNASSIGNED);
+    state.assignment.fill(UNASSIGNED);
+    let mut unassigned = (0..state.assignment.len()).rev().collect::<Vec<_>>();
+    let mut n_rounds = 0;
+
+    match bidding {
+        Bidding::GaussSeidel => {
+            while let Some(agent) = unassigned.pop() {
+                monitor.check()?;
+                if n_rounds % 64 == 0 && deadline.is_past() {
+                    return Ok((n_rounds, false));
+                }
+                n_rounds += 1;
+                let bid = if agent < distance_matrix.n_rows() {
+                    bid(agent, distance_matrix, state, epsilon)
+                } else {
+                    state.dummy_bid(epsilon)
+                };
+                let (object, price) =
+                    bid.ok_or(SolverError::from(AuctionError::UnassignedAgents))?;
+                if price > price_limit {
+                    return Err(SolverError::from(AuctionError::UnassignedAgents).into());
+                }
+                if let Some(outbid) = state.award(agent, object, price) {
+                    unassigned.push(outbid);
+                }
+            }
+        }
+        Bidding::Jacobi => {
+            let mut best_bids = vec![(i64::MIN, UNASSIGNED); state.prices.len()];
+            while !unassigned.is_empty() {
+                monitor.check()?;
+                if deadline.is_past() {
+                    return Ok((n_rounds, false));
+                }
+                n_rounds += 1;
+                let (dummies, agents): (Vec<usize>, Vec<usize>) = unassigned
+                    .iter()
+                    .partition(|&&agent| agent >= distance_matrix.n_rows());
+                let mut bids = macros::iter_or_par_iter!(agents)
+                    .map(|&agent| {
+                        bid(agent, distance_matrix, state, epsilon)
+                            .map(|(object, price)| (agent, object, price))
+                    })
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or(SolverError::from(AuctionError::UnassignedAgents))?;
+                bids.extend(dummy_bids(&dummies, state, epsilon));
 
-    for (task, &assigned_count) in task_assigned.iter().enumerate() {
-        if assigned_count < max_tile_occurrences {
-            let value = -distance_matrix.get(agent, task) - prices[task];
+                for &(agent, object, price) in bids.iter() {
+                    if price > price_limit {
+                        return Err(SolverError::from(AuctionError::UnassignedAgents).into());
+                    }
+                    if price > best_bids[object].0 {
+                        best_bids[object] = (price, agent);
+                    }
+                }
 
-            if value > best_value {
-                second_best_value = best_value;
-                best_value = value;
-                best_task = task;
-            } else if value > second_best_value {
-                second_best_value = value;
+                let mut next_unassigned = Vec::new();
+                for &(agent, object, _) in bids.iter() {
+                    let (price, winner) = best_bids[object];
+                    if winner == UNASSIGNED {
+                        // The object was already awarded this round
+                        next_unassigned.push(agent);
+                        continue;
+                    }
+                    if winner != agent {
+                        next_unassigned.push(agent);
+                        continue;
+                    }
+                    best_bids[object] = (i64::MIN, UNASSIGNED);
+                    if let Some(outbid) = state.award(agent, object, price) {
+                        next_unassigned.push(outbid);
+                    }
+                }
+                unassigned = next_unassigned;
             }
         }
     }
+    Ok((n_rounds, true))
+}
+
+/// The difference between the cost of the assignment and the best lower bound of the optimal cost
+/// given by the prices, using the dual of the assignment problem.
+fn duality_gap<C: CostMatrix>(
+    distance_matrix: &C,
+    state: &AuctionState,
+    assignments: &[usize],
+) -> i64 {
+    let primal: i64 = assignments
+        .iter()
+        .enumerate()
+        .map(|(row, &column)| {
+            distance_matrix
+                .cost(row, column)
+                .expect("The assigned column should be a candidate")
+        })
+        .sum();
 
-    if best_task == usize::MAX || second_best_value == i64::MIN {
-        return None; // Not enough tasks to compare
+    // Any non negative prices give a lower bound, the prices are also tried lowered by the
+    // minimum price, which is the price paid by the dummy agents
+    let lower_bound = |shift: i64| -> i64 {
+        let agents: i64 = (0..distance_matrix.n_rows())
+            .map(|row| {
+                distance_matrix
+                    .row_costs(row)
+                    .map(|(column, cost)| cost + state.prices[state.cheapest[column]] - shift)
+                    .min()
+                    .unwrap_or(0)
+            })
+            .sum();
+        let objects: i64 = state.prices.iter().map(|price| price - shift).sum();
+        agents - objects
+    };
+    let min_price = state.prices.iter().copied().min().unwrap_or(0);
+
+    primal - lower_bound(0).max(lower_bound(min_price))
+}
+
+impl Auction {
+    /// Run the auction on the `distance_matrix`, each column being assigned at most `capacity`
+    /// times, and store the optimality gap of the assignments.
+    fn assign<C: CostMatrix>(
+        &mut self,
+        distance_matrix: &C,
+        capacity: usize,
+        deadline: Deadline,
+    ) -> Result<Outcome, PhomoError> {
+        let num_agents = distance_matrix.n_rows();
+        let num_tasks = distance_matrix.n_columns();
+
+        let mut state = AuctionState {
+            capacity,
+            prices: vec![0; num_tasks * capacity],
+            holders: vec![UNASSIGNED; num_tasks * capacity],
+            cheapest: (0..num_tasks).map(|task| task * capacity).collect(),
+            // When there are more objects than agents, dummy agents are added to make the
+            // problem square, otherwise ε-scaling leaves stale prices on the unassigned objects
+            assignment: vec![UNASSIGNED; num_agents.max(num_tasks * capacity)],
+            by_price: (0..num_tasks * capacity)
+                .map(|object| Reverse((0, object)))
+                .collect(),
+        };
+
+        let max_cost = (0..num_agents)
+            .flat_map(|row| distance_matrix.row_costs(row).map(|(_, cost)| cost.abs()))
+            .max()
+            .unwrap_or(0);
+        let mut epsilon = match self.scaling_factor {
+            Some(_) => (max_cost / 2).max(self.epsilon),
+            None => self.epsilon,
+        };
+        let mut n_rounds = 0;
+        // The assignments of the last completed ε-scaling phase
+        let mut assignments = None;
+        let complete = loop {
+            // The prices of a feasible problem stay well below this limit, past it some agents
+            // can't be assigned
+            let price_limit = (state.assignment.len() as i64 + 1)
+                .saturating_mul(2 * max_cost + epsilon)
+                .saturating_add(state.prices.iter().copied().max().unwrap_or(0));
+            let (rounds, complete) = run_auction(
+                distance_matrix,
+                &mut state,
+                epsilon,
+                self.bidding,
+                price_limit,
+                &self.config.monitor,
+                deadline,
+            )?;
+            n_rounds += rounds;
+            if !complete {
+                break false;
+            }
+            assignments = Some(
+                state.assignment[..num_agents]
+                    .iter()
+                    .map(|&object| object / capacity)
+                    .collect::<Vec<_>>(),
+            );
+            match self.scaling_factor {
+                Some(factor) if epsilon > self.epsilon => {
+                    epsilon = (epsilon / factor).max(self.epsilon);
+                }
+                _ => break true,
+            }
+        };
+        self.iterations = Some(n_rounds);
+
+        match assignments {
+            Some(assignments) if complete => {
+                let gap = duality_gap(distance_matrix, &state, &assignments);
+                info!("Auction optimality gap: {gap}");
+                self.optimality_gap = Some(gap);
+                Ok(Outcome::Complete(assignments))
+            }
+            assignments => {
+                self.optimality_gap = None;
+                Ok(Outcome::OutOfTime(assignments))
+            }
+        }
+    }
+
+    /// Run the auction on the `distance_matrix` until the `deadline`.
+    fn solve_exact<C: CostMatrix>(
+        &mut self,
+        distance_matrix: &C,
+        deadline: Deadline,
+    ) -> Result<Outcome, PhomoError> {
+        // The pinned rows only have their pinned column as candidate
+        let pinned = Pinned::new(distance_matrix, &self.config);
+        // The ties between equal costs are broken in the seeded order of the columns
+        let distance_matrix = &Shuffled::new(&pinned, &self.config);
+        let outcome = if self.config.min_tile_occurrences > 0 {
+            // Minimum usages are handled by a square view of the matrix, whose complete
+            // assignments use each column at least the minimum number of times
+            let min_usage = MinUsage::new(distance_matrix, &self.config);
+            self.assign(&min_usage, 1, deadline)?
+                .map(|assignments| min_usage.inner_assignments(assignments))
+        } else {
+            self.assign(distance_matrix, self.config.max_tile_occurrences, deadline)?
+        };
+        Ok(outcome.map(|assignments| distance_matrix.inner_assignments(assignments)))
     }
+}
 
-    Some((best_task, best_value, second_best_value))
+/// The assignments of an auction, which may have been stopped by its deadline.
+enum Outcome {
+    /// All the ε-scaling phases completed.
+    Complete(Vec<usize>),
+    /// The deadline passed, with the assignments of the last completed phase, if any.
+    OutOfTime(Option<Vec<usize>>),
+}
+
+impl Outcome {
+    fn map<F: Fn(Vec<usize>) -> Vec<usize>>(self, f: F) -> Self {
+        match self {
+            Outcome::Complete(assignments) => Outcome::Complete(f(assignments)),
+            Outcome::OutOfTime(assignments) => Outcome::OutOfTime(assignments.map(f)),
+        }
+    }
 }
 
 impl Solve for Auction {
     fn solve(&mut self, distance_matrix: &DistanceMatrix) -> Result<Vec<usize>, PhomoError> {
+        self.solve_cost_matrix(distance_matrix)
+    }
+
+    fn solve_cost_matrix<C: CostMatrix>(
+        &mut self,
+        distance_matrix: &C,
+    ) -> Result<Vec<usize>, PhomoError> {
         // Check if the number of columns is less than the number of rows
-        if distance_matrix.columns * self.config.max_tile_occurrences < distance_matrix.rows {
-            return Err(SolverError::TooFewColumns {
-                rows: distance_matrix.rows,
-                columns: distance_matrix.columns,
+        check_capacity(distance_matrix, &self.config)?;
+        // Half of the time budget is left to refine the assignments of an unfinished auction
+        let deadline = Deadline::new(self.config.time_budget);
+        let exact_deadline = Deadline::new(self.config.time_budget.map(|budget| budget / 2));
+        match self.solve_exact(distance_matrix, exact_deadline)? {
+            Outcome::Complete(assignments) => Ok(assignments),
+            Outcome::OutOfTime(assignments) => {
+                fallback(distance_matrix, &self.config, deadline, assignments)
             }
-            .into());
         }
+    }
 
-        let num_agents = distance_matrix.rows;
-        let num_tasks = distance_matrix.columns;
+    /// The number of bidding rounds, summed over the ε-scaling phases. A round is a single bid
+    /// with the Gauss-Seidel bidding, or the bids of all the unassigned agents with the Jacobi
+    /// bidding.
+    fn iterations(&self) -> Option<usize> {
+        self.iterations
+    }
 
-        let mut prices = vec![0; num_tasks]; // Prices for each task
-        let mut assignment = vec![None; num_agents]; // Assignment of tasks to agents
-        let mut task_assigned = vec![0; num_tasks]; // Track how many times each task is assigned
+    /// Whether the auction completed with a duality gap of 0.
+    fn is_optimal(&self) -> bool {
+        self.optimality_gap == Some(0)
+    }
+}
 
-        // Continue until all agents are assigned a task
-        while assignment.iter().any(|&a| a.is_none()) {
-            for (agent, assigned_task) in assignment.iter_mut().enumerate().take(num_agents) {
-                if assigned_task.is_some() {
-                    continue; // Skip already assigned agents
-                }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solvers::test_utils::{distance_matrix, total_cost};
+    use crate::Hungarian;
 
-                // Find the best and second-best tasks for the current agent
-                if let Some((best_task, best_value, second_best_value)) = find_best_and_second_best(
-                    agent,
-                    distance_matrix,
-                    &prices,
-                    &task_assigned,
-                    self.config.max_tile_occurrences,
-                ) {
-                    // Calculate the bid for the best task
-                    let bid = best_value - second_best_value + self.epsilon;
-                    prices[best_task] += bid; // Update the price for the best task
-                    *assigned_task = Some(best_task); // Assign the task to the agent
-                    task_assigned[best_task] += 1; // Increment the assignment count for the task
-                } else {
-                    // No available task for the agent
-                    return Err(SolverError::from(AuctionError::UnassignedAgents).into());
-                }
+    #[test]
+    fn test_optimality_gap() {
+        for (rows, columns, max_tile_occurrences) in [(20, 20, 1), (20, 30, 1), (30, 8, 4)] {
+            let d_matrix = distance_matrix(rows, columns);
+            let config = SolverConfig {
+                max_tile_occurrences,
+                ..Default::default()
+            };
+            let optimal = total_cost(
+                &d_matrix,
+                &Hungarian::new(config.clone()).solve(&d_matrix).unwrap(),
+            );
+            for mut solver in [
+                Auction::new(1, config.clone()),
+                Auction::with_epsilon_scaling(1, 4, Bidding::GaussSeidel, config.clone()),
+                Auction::with_epsilon_scaling(1, 4, Bidding::Jacobi, config.clone()),
+            ] {
+                let assignments = solver.solve(&d_matrix).unwrap();
+                let mut counts = vec![0; columns];
+                assignments.iter().for_each(|&column| counts[column] += 1);
+                assert!(counts.iter().all(|&count| count <= max_tile_occurrences));
+
+                let gap = solver.optimality_gap().unwrap();
+                let cost = total_cost(&d_matrix, &assignments);
+                assert!(cost >= optimal);
+                assert!(cost - optimal <= gap);
             }
         }
+    }
+
+    #[test]
+    fn test_square_gap() {
+        // ε-complementary slackness bounds the gap of square problems by n * ε
+        let d_matrix = distance_matrix(20, 20);
+        let mut solver =
+            Auction::with_epsilon_scaling(1, 4, Bidding::GaussSeidel, SolverConfig::default());
+        solver.solve(&d_matrix).unwrap();
+        assert!(solver.optimality_gap().unwrap() <= 20);
+    }
 
-        Ok(assignment.into_iter().map(|a| a.unwrap()).collect())
+    #[test]
+    fn test_dummy_bid() {
+        // 2 tasks of 2 objects
+        let mut state = AuctionState {
+            capacity: 2,
+            prices: vec![0; 4],
+            holders: vec![UNASSIGNED; 4],
+            cheapest: vec![0, 2],
+            assignment: vec![UNASSIGNED; 4],
+            by_price: (0..4).map(|object| Reverse((0, object))).collect(),
+        };
+        assert_eq!(state.dummy_bid(1), Some((0, 1)));
+        state.award(0, 0, 5);
+        state.award(1, 1, 3);
+        state.award(2, 2, 4);
+        // The cheapest object is 3 and the second cheapest is 1, of the other task
+        assert_eq!(state.dummy_bid(2), Some((3, 5)));
+        state.award(3, 3, 6);
+        assert_eq!(state.dummy_bid(2), Some((1, 6)));
     }
 }
@@ -6,11 +6,46 @@ pub enum SolverError {
     HungarianError(#[from] HungarianError),
     #[error("Auction error: {0}")]
     AuctionError(#[from] AuctionError),
+    #[error("Lapjv error: {0}")]
+    LapjvError(#[from] LapjvError),
+    #[error("Min cost flow error: {0}")]
+    MinCostFlowError(#[from] MinCostFlowError),
 
     #[error(
         "Too few columns in the distance matrix, expected at least {rows}, but found {columns}"
     )]
     TooFewColumns { rows: usize, columns: usize },
+
+    #[error("No candidate column left to assign to row {row}")]
+    NoCandidateLeft { row: usize },
+
+    #[error("Invalid assignment of row {row}")]
+    InvalidAssignment { row: usize },
+
+    #[error("No tile can be assigned to row {row} without a repeat within a radius of {radius}")]
+    RepeatWithinRadius { row: usize, radius: u32 },
+
+    #[error("Too few cells ({rows}) to use each of the {columns} tiles at least {min} times")]
+    TooFewRows {
+        rows: usize,
+        columns: usize,
+        min: usize,
+    },
+
+    #[error("The minimum tile occurrences ({min}) exceed the maximum tile occurrences ({max})")]
+    InvalidOccurrences { min: usize, max: usize },
+
+    #[error("Tile {column} can't be assigned its minimum number of times")]
+    UnderusedColumn { column: usize },
+
+    #[error("Invalid pin of tile {column} to cell {row}")]
+    InvalidPin { row: usize, column: usize },
+
+    #[error("Tile {column} is pinned to more than {capacity} cells")]
+    TooManyPins { column: usize, capacity: usize },
+
+    #[error("Tile edges length mismatch: expected {expected}, but found {found}")]
+    InvalidTileEdges { expected: usize, found: usize },
 }
 
 #[derive(Debug, Error)]
@@ -24,3 +59,15 @@ pub enum AuctionError {
     #[error("Unassigned agents.")]
     UnassignedAgents,
 }
+
+#[derive(Debug, Error)]
+pub enum LapjvError {
+    #[error("Infeasible")]
+    Infeasible,
+}
+
+#[derive(Debug, Error)]
+pub enum MinCostFlowError {
+    #[error("No augmenting path to a tile with remaining capacity.")]
+    NoAugmentingPath,
+}
@@ -1,9 +1,10 @@
 use std::{cmp::Reverse, collections::BinaryHeap};
 
-use crate::solvers::Solve;
+use crate::cost_matrix::Shuffled;
 use crate::solvers::SolverConfig;
-use crate::DistanceMatrix;
+use crate::solvers::{check_capacity, Solve};
 use crate::{error::PhomoError, solvers::error::SolverError};
+use crate::{CostMatrix, DistanceMatrix};
 
 /// The Greedy struct represents the greedy algorithm solver.
 #[derive(Debug, Default)]
@@ -23,36 +24,80 @@ impl Greedy {
 
 impl Solve for Greedy {
     fn solve(&mut self, distance_matrix: &DistanceMatrix) -> Result<Vec<usize>, PhomoError> {
-        if distance_matrix.columns * self.config.max_tile_occurrences < distance_matrix.rows {
-            return Err(SolverError::TooFewColumns {
-                rows: distance_matrix.rows,
-                columns: distance_matrix.columns,
-            }
-            .into());
-        }
+        self.solve_cost_matrix(distance_matrix)
+    }
 
-        let n_cells = distance_matrix.rows;
-        let n_tiles = distance_matrix.columns;
+    fn solve_cost_matrix<C: CostMatrix>(
+        &mut self,
+        distance_matrix: &C,
+    ) -> Result<Vec<usize>, PhomoError> {
+        check_capacity(distance_matrix, &self.config)?;
+        // The ties between equal costs are broken in the seeded order of the tiles
+        let shuffled = Shuffled::new(distance_matrix, &self.config);
+        let distance_matrix = &shuffled;
+
+        let n_cells = distance_matrix.n_rows();
+        let n_tiles = distance_matrix.n_columns();
 
         let mut n_appearances = vec![0; n_tiles];
         let mut heap = BinaryHeap::with_capacity(n_cells);
+        let mut assignments = vec![0; n_cells];
+        let mut filled_count = 0;
 
-        // Initialize the heap with the best tile for each cell
-        for row_idx in 0..n_cells {
-            let mut best_tile = 0;
-            let mut best_distance = distance_matrix.get(row_idx, best_tile);
-            for col_idx in 1..n_tiles {
-                let distance = distance_matrix.get(row_idx, col_idx);
-                if distance < best_distance {
-                    best_distance = distance;
-                    best_tile = col_idx;
-                }
+        // The pinned cells are assigned first, they count towards the minimum usages
+        let pins = self.config.pinned_columns(n_cells);
+        for (cell_idx, &tile_idx) in pins.iter().enumerate() {
+            if let Some(tile_idx) = tile_idx.map(|tile_idx| shuffled.column(tile_idx)) {
+                assignments[cell_idx] = tile_idx;
+                n_appearances[tile_idx] += 1;
+                filled_count += 1;
             }
-            heap.push(Reverse((best_distance, row_idx, best_tile)));
         }
 
-        let mut assignments = vec![0; n_cells];
-        let mut filled_count = 0;
+        // Then each tile is assigned to its best free cells until it reaches its minimum usage
+        let min_occurrences = self.config.min_tile_occurrences;
+        let mut is_assigned = pins.iter().map(Option::is_some).collect::<Vec<_>>();
+        let best_free_cell = |tile_idx: usize, is_assigned: &[bool]| {
+            (0..n_cells)
+                .filter(|&cell_idx| !is_assigned[cell_idx])
+                .filter_map(|cell_idx| {
+                    distance_matrix
+                        .cost(cell_idx, tile_idx)
+                        .map(|distance| (distance, cell_idx))
+                })
+                .min()
+                .ok_or(SolverError::UnderusedColumn { column: tile_idx })
+        };
+        let mut tile_heap = BinaryHeap::new();
+        for tile_idx in (0..n_tiles).filter(|&tile_idx| n_appearances[tile_idx] < min_occurrences) {
+            let (best_distance, best_cell) = best_free_cell(tile_idx, &is_assigned)?;
+            tile_heap.push(Reverse((best_distance, tile_idx, best_cell)));
+        }
+        while let Some(Reverse((_, tile_idx, cell_idx))) = tile_heap.pop() {
+            if !is_assigned[cell_idx] {
+                assignments[cell_idx] = tile_idx;
+                is_assigned[cell_idx] = true;
+                n_appearances[tile_idx] += 1;
+                filled_count += 1;
+            }
+            if n_appearances[tile_idx] < min_occurrences {
+                let (next_best_distance, next_best_cell) = best_free_cell(tile_idx, &is_assigned)?;
+                tile_heap.push(Reverse((next_best_distance, tile_idx, next_best_cell)));
+            }
+        }
+
+        let step = self
+            .config
+            .monitor
+            .step("Computing assignments", n_cells - filled_count);
+        // Initialize the heap with the best tile for each other cell
+        for row_idx in (0..n_cells).filter(|&row_idx| !is_assigned[row_idx]) {
+            let (best_tile, best_distance) = distance_matrix
+                .row_costs(row_idx)
+                .min_by_key(|&(_, distance)| distance)
+                .ok_or(SolverError::NoCandidateLeft { row: row_idx })?;
+            heap.push(Reverse((best_distance, row_idx, best_tile)));
+        }
 
         while let Some(Reverse((_, cell_idx, tile_idx))) = heap.pop() {
             if filled_count == n_cells {
@@ -64,23 +109,20 @@ impl Solve for Greedy {
                 assignments[cell_idx] = tile_idx;
                 n_appearances[tile_idx] += 1;
                 filled_count += 1;
+                step.tick()?;
             } else {
                 // Tile is no longer available, find the next best tile for this cell
-                let mut next_best_tile = 0;
-                let mut next_best_distance = i64::MAX;
-                for (tile_idx, n) in n_appearances.iter().enumerate() {
-                    if *n < self.config.max_tile_occurrences {
-                        let distance = distance_matrix.get(cell_idx, tile_idx);
-                        if distance < next_best_distance {
-                            next_best_distance = distance;
-                            next_best_tile = tile_idx;
-                        }
-                    }
-                }
+                let (next_best_tile, next_best_distance) = distance_matrix
+                    .row_costs(cell_idx)
+                    .filter(|&(tile_idx, _)| {
+                        n_appearances[tile_idx] < self.config.max_tile_occurrences
+                    })
+                    .min_by_key(|&(_, distance)| distance)
+                    .ok_or(SolverError::NoCandidateLeft { row: cell_idx })?;
                 heap.push(Reverse((next_best_distance, cell_idx, next_best_tile)));
             }
         }
 
-        Ok(assignments)
+        Ok(shuffled.inner_assignments(assignments))
     }
 }
@@ -1,10 +1,12 @@
+use crate::cost_matrix::{Pinned, Repeated, Shuffled};
 use crate::error::PhomoError;
-use crate::macros::maybe_progress_bar;
+use crate::progress::Monitor;
 use crate::solvers::error::HungarianError;
 use crate::solvers::error::SolverError;
-use crate::solvers::Solve;
+use crate::solvers::min_cost_flow::solve_min_usage;
 use crate::solvers::SolverConfig;
-use crate::DistanceMatrix;
+use crate::solvers::{check_capacity, fallback, Deadline, Solve};
+use crate::{CostMatrix, DistanceMatrix};
 
 const UNASSIGNED: usize = usize::MAX;
 
@@ -24,24 +26,27 @@ struct HungarianState {
 #[derive(Debug, Default)]
 pub struct Hungarian {
     config: SolverConfig,
+    optimal: bool,
 }
 
 impl Hungarian {
     /// Creates a new instance of the solver with the given distance matrix.
     pub fn new(config: SolverConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            optimal: false,
+        }
     }
 }
 
 /// Finds an augmenting path starting from the given row.
 /// Returns the sink column and the minimum value found.
-fn find_augmenting_path(
+fn find_augmenting_path<C: CostMatrix>(
     mut current_row: usize,
-    distance_matrix: &DistanceMatrix,
+    distance_matrix: &C,
     state: &mut HungarianState,
 ) -> Result<(usize, i64), SolverError> {
-    let nc = distance_matrix.columns;
-    let cost = &distance_matrix.data;
+    let nc = distance_matrix.n_columns();
     let mut min_val = 0;
 
     let mut num_remaining = nc;
@@ -66,6 +71,19 @@ fn find_augmenting_path(
         let mut lowest = i64::MAX;
         state.selected_rows[current_row] = true;
 
+        // Relax the candidate columns of the current row which are not yet selected
+        for (j, cost) in distance_matrix.row_costs(current_row) {
+            if state.selected_columns[j] {
+                continue;
+            }
+            let r: i64 =
+                min_val + cost - state.dual_row_values[current_row] - state.dual_column_values[j];
+            if r < state.shortest_path_costs[j] {
+                state.augmentation_path[j] = current_row;
+                state.shortest_path_costs[j] = r;
+            }
+        }
+
         // Iterate over remaining columns to find the shortest path
         state
             .remaining_columns
@@ -73,14 +91,6 @@ fn find_augmenting_path(
             .take(num_remaining)
             .enumerate()
             .for_each(|(it, &j)| {
-                let r: i64 = min_val + cost[current_row * nc + j]
-                    - state.dual_row_values[current_row]
-                    - state.dual_column_values[j];
-                if r < state.shortest_path_costs[j] {
-                    state.augmentation_path[j] = current_row;
-                    state.shortest_path_costs[j] = r;
-                }
-
                 // Update the lowest cost and index
                 if state.shortest_path_costs[j] < lowest
                     || (state.shortest_path_costs[j] == lowest
@@ -146,38 +156,104 @@ fn augment_solution(current_row: usize, sink_column: usize, state: &mut Hungaria
     }
 }
 
-impl Solve for Hungarian {
-    fn solve(&mut self, distance_matrix: &DistanceMatrix) -> Result<Vec<usize>, PhomoError> {
-        let d_matrix = if self.config.max_tile_occurrences > 1 {
-            &distance_matrix.tile(self.config.max_tile_occurrences)
+impl Hungarian {
+    /// Solve the assignment problem exactly, or return `None` when the `deadline` passes first.
+    fn solve_exact<C: CostMatrix>(
+        &self,
+        distance_matrix: &C,
+        deadline: Deadline,
+    ) -> Result<Option<Vec<usize>>, PhomoError> {
+        if self.config.min_tile_occurrences > 0 {
+            // Minimum usages are lower bounds of the min-cost flow, which avoids repeating the
+            // columns of the matrix as many times as their capacity
+            return solve_min_usage(distance_matrix, &self.config, deadline);
+        }
+        let monitor = &self.config.monitor;
+        // The pinned rows only have their pinned column as candidate
+        let pinned = Pinned::new(distance_matrix, &self.config);
+        // The ties between equal costs are broken in the seeded order of the columns
+        let distance_matrix = &Shuffled::new(&pinned, &self.config);
+        let assignments = if self.config.max_tile_occurrences > 1 {
+            // Repeated tiles are handled by a view of the matrix with each column repeated as many
+            // times as its capacity, the costs are not copied
+            let repeated = Repeated::new(distance_matrix, self.config.max_tile_occurrences);
+            let n_columns = distance_matrix.n_columns();
+            solve_lsap(&repeated, monitor, deadline)?.map(|assignments| {
+                assignments
+                    .into_iter()
+                    .map(|column| column % n_columns)
+                    .collect()
+            })
         } else {
-            distance_matrix
+            solve_lsap(distance_matrix, monitor, deadline)?
         };
-        if d_matrix.columns < d_matrix.rows {
-            return Err(SolverError::TooFewColumns {
-                rows: d_matrix.rows,
-                columns: d_matrix.columns,
-            }
-            .into());
+        Ok(assignments.map(|assignments| distance_matrix.inner_assignments(assignments)))
+    }
+}
+
+impl Solve for Hungarian {
+    fn solve(&mut self, distance_matrix: &DistanceMatrix) -> Result<Vec<usize>, PhomoError> {
+        self.solve_cost_matrix(distance_matrix)
+    }
+
+    fn solve_cost_matrix<C: CostMatrix>(
+        &mut self,
+        distance_matrix: &C,
+    ) -> Result<Vec<usize>, PhomoError> {
+        check_capacity(distance_matrix, &self.config)?;
+        // Half of the time budget is left for the fallback
+        let deadline = Deadline::new(self.config.time_budget);
+        let exact_deadline = Deadline::new(self.config.time_budget.map(|budget| budget / 2));
+        let assignments = self.solve_exact(distance_matrix, exact_deadline)?;
+        self.optimal = assignments.is_some();
+        match assignments {
+            Some(assignments) => Ok(assignments),
+            None => fallback(distance_matrix, &self.config, deadline, None),
         }
-        let mut state = HungarianState {
-            dual_row_values: vec![0; d_matrix.rows],
-            dual_column_values: vec![0; d_matrix.columns],
-            shortest_path_costs: vec![i64::MAX; d_matrix.columns],
-            augmentation_path: vec![UNASSIGNED; d_matrix.columns],
-            column_assigned_to_row: vec![UNASSIGNED; d_matrix.rows],
-            row_assigned_to_column: vec![UNASSIGNED; d_matrix.columns],
-            selected_rows: vec![false; d_matrix.rows],
-            selected_columns: vec![false; d_matrix.columns],
-            remaining_columns: (0..d_matrix.columns).collect(),
-        };
+    }
 
-        for current_row in maybe_progress_bar!(0..d_matrix.rows, "Computing assignments") {
-            let (sink_column, min_value) = find_augmenting_path(current_row, d_matrix, &mut state)?;
-            update_dual_variables(current_row, min_value, &mut state);
-            augment_solution(current_row, sink_column, &mut state);
+    fn is_optimal(&self) -> bool {
+        self.optimal
+    }
+}
+
+/// Solve the linear sum assignment problem, each column can only be assigned once. Returns `None`
+/// when the `deadline` passes before all the rows are assigned.
+fn solve_lsap<C: CostMatrix>(
+    d_matrix: &C,
+    monitor: &Monitor,
+    deadline: Deadline,
+) -> Result<Option<Vec<usize>>, PhomoError> {
+    let (n_rows, n_columns) = (d_matrix.n_rows(), d_matrix.n_columns());
+    if n_columns < n_rows {
+        return Err(SolverError::TooFewColumns {
+            rows: n_rows,
+            columns: n_columns,
         }
+        .into());
+    }
+    let mut state = HungarianState {
+        dual_row_values: vec![0; n_rows],
+        dual_column_values: vec![0; n_columns],
+        shortest_path_costs: vec![i64::MAX; n_columns],
+        augmentation_path: vec![UNASSIGNED; n_columns],
+        column_assigned_to_row: vec![UNASSIGNED; n_rows],
+        row_assigned_to_column: vec![UNASSIGNED; n_columns],
+        selected_rows: vec![false; n_rows],
+        selected_columns: vec![false; n_columns],
+        remaining_columns: (0..n_columns).collect(),
+    };
 
-        Ok(state.column_assigned_to_row)
+    let step = monitor.step("Computing assignments", n_rows);
+    for current_row in 0..n_rows {
+        if deadline.is_past() {
+            return Ok(None);
+        }
+        let (sink_column, min_value) = find_augmenting_path(current_row, d_matrix, &mut state)?;
+        update_dual_variables(current_row, min_value, &mut state);
+        augment_solution(current_row, sink_column, &mut state);
+        step.tick()?;
     }
+
+    Ok(Some(state.column_assigned_to_row))
 }
@@ -0,0 +1,452 @@
+use crate::cost_matrix::{Pinned, Repeated, Shuffled};
+use crate::error::PhomoError;
+use crate::progress::Monitor;
+use crate::solvers::error::LapjvError;
+use crate::solvers::error::SolverError;
+use crate::solvers::min_cost_flow::solve_min_usage;
+use crate::solvers::SolverConfig;
+use crate::solvers::{check_capacity, fallback, Deadline, Solve};
+use crate::{CostMatrix, DistanceMatrix};
+
+const UNASSIGNED: usize = usize::MAX;
+
+/// The Lapjv struct represents the Jonker-Volgenant solver.
+///
+/// Like the [`Hungarian`](crate::Hungarian) solver, it finds the optimal assignment with shortest
+/// augmenting paths, but most rows are assigned beforehand by the cheap initialisation phases of
+/// LAPJV: a column reduction, a reduction transfer and two rounds of augmenting row reduction.
+/// When there are more columns than rows, the column reduction is replaced by a row reduction,
+/// which keeps the duals of the unassigned columns at 0.
+///
+/// See:
+///     <https://doi.org/10.1007/BF02278710>
+#[derive(Debug, Default)]
+pub struct Lapjv {
+    config: SolverConfig,
+    optimal: bool,
+}
+
+impl Lapjv {
+    /// Creates a new instance of the Lapjv struct with the given configuration.
+    ///
+    /// # Arguments
+    /// - `config`: The configuration for the solver.
+    pub fn new(config: SolverConfig) -> Self {
+        Self {
+            config,
+            optimal: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct LapjvState {
+    /// The dual values of the columns, the dual values of the rows are implied by the
+    /// assignments.
+    dual_column_values: Vec<i64>,
+    column_assigned_to_row: Vec<usize>,
+    row_assigned_to_column: Vec<usize>,
+}
+
+impl LapjvState {
+    fn assign(&mut self, row: usize, column: usize) {
+        self.column_assigned_to_row[row] = column;
+        self.row_assigned_to_column[column] = row;
+    }
+}
+
+/// Assign each column to its cheapest row, if that row is not already assigned to a cheaper
+/// column. Only used when the matrix is square.
+///
+/// Returns the rows which were assigned exactly one column.
+fn column_reduction<C: CostMatrix>(distance_matrix: &C, state: &mut LapjvState) -> Vec<usize> {
+    let (n_rows, n_columns) = (distance_matrix.n_rows(), distance_matrix.n_columns());
+    let mut column_minimums = vec![(i64::MAX, UNASSIGNED); n_columns];
+    for row in 0..n_rows {
+        for (column, cost) in distance_matrix.row_costs(row) {
+            if cost < column_minimums[column].0 {
+                column_minimums[column] = (cost, row);
+            }
+        }
+    }
+
+    let mut matches = vec![0; n_rows];
+    for (column, &(minimum, row)) in column_minimums.iter().enumerate().rev() {
+        if row == UNASSIGNED {
+            continue;
+        }
+        state.dual_column_values[column] = minimum;
+        matches[row] += 1;
+        let assigned_column = state.column_assigned_to_row[row];
+        if assigned_column == UNASSIGNED {
+            state.assign(row, column);
+        } else if minimum < state.dual_column_values[assigned_column] {
+            state.row_assigned_to_column[assigned_column] = UNASSIGNED;
+            state.assign(row, column);
+        }
+    }
+    (0..n_rows).filter(|&row| matches[row] == 1).collect()
+}
+
+/// Assign each row to its cheapest column, if that column is not already assigned.
+///
+/// Returns the rows which were assigned.
+fn row_reduction<C: CostMatrix>(distance_matrix: &C, state: &mut LapjvState) -> Vec<usize> {
+    let mut assigned_rows = Vec::new();
+    for row in 0..distance_matrix.n_rows() {
+        let Some((column, _)) = distance_matrix.row_costs(row).min_by_key(|&(_, cost)| cost) else {
+            continue;
+        };
+        if state.row_assigned_to_column[column] == UNASSIGNED {
+            state.assign(row, column);
+            assigned_rows.push(row);
+        }
+    }
+    assigned_rows
+}
+
+/// Transfer the slack of the assigned rows to the dual values of their columns, which makes
+/// these columns less attractive to the other rows.
+fn reduction_transfer<C: CostMatrix>(rows: &[usize], distance_matrix: &C, state: &mut LapjvState) {
+    for &row in rows {
+        let assigned_column = state.column_assigned_to_row[row];
+        let second_minimum = distance_matrix
+            .row_costs(row)
+            .filter(|&(column, _)| column != assigned_column)
+            .map(|(column, cost)| cost - state.dual_column_values[column])
+            .min();
+        if let Some(second_minimum) = second_minimum {
+            let cost = distance_matrix
+                .cost(row, assigned_column)
+                .expect("The assigned column should be a candidate");
+            state.dual_column_values[assigned_column] = cost - second_minimum;
+        }
+    }
+}
+
+/// Assign the free rows to their cheapest column, taking it from its row if needed, and lower
+/// the dual value of the column by the difference between the two cheapest columns of the row.
+///
+/// Returns the rows which are still free.
+fn augmenting_row_reduction<C: CostMatrix>(
+    mut free_rows: Vec<usize>,
+    distance_matrix: &C,
+    state: &mut LapjvState,
+) -> Vec<usize> {
+    let n_rows = distance_matrix.n_rows();
+    for _ in 0..2 {
+        let n_free = free_rows.len();
+        let mut still_free = Vec::new();
+        let mut k = 0;
+        // Bound the number of immediate reassignments, the rows left over are assigned by the
+        // augmentation phase, which also detects infeasible sparse matrices
+        let mut n_reassignments = 0;
+        while k < n_free {
+            let row = free_rows[k];
+            k += 1;
+
+            let mut minimum = (i64::MAX, UNASSIGNED);
+            let mut second_minimum = (i64::MAX, UNASSIGNED);
+            for (column, cost) in distance_matrix.row_costs(row) {
+                let reduced_cost = cost - state.dual_column_values[column];
+                if reduced_cost < minimum.0 {
+                    second_minimum = minimum;
+                    minimum = (reduced_cost, column);
+                } else if reduced_cost < second_minimum.0 {
+                    second_minimum = (reduced_cost, column);
+                }
+            }
+
+            let (minimum, mut column) = minimum;
+            let mut previous_row = state.row_assigned_to_column[column];
+            if second_minimum.1 == UNASSIGNED {
+                // A single candidate, only take it if it's free
+                if previous_row != UNASSIGNED {
+                    still_free.push(row);
+                    continue;
+                }
+            } else if minimum < second_minimum.0 {
+                state.dual_column_values[column] -= second_minimum.0 - minimum;
+            } else if previous_row != UNASSIGNED {
+                column = second_minimum.1;
+                previous_row = state.row_assigned_to_column[column];
+            }
+
+            state.assign(row, column);
+            if previous_row != UNASSIGNED {
+                state.column_assigned_to_row[previous_row] = UNASSIGNED;
+                if minimum < second_minimum.0 && n_reassignments < n_rows {
+                    // The row is reassigned right away
+                    n_reassignments += 1;
+                    k -= 1;
+                    free_rows[k] = previous_row;
+                } else {
+                    still_free.push(previous_row);
+                }
+            }
+        }
+        free_rows = still_free;
+    }
+    free_rows
+}
+
+/// Find the shortest augmenting path from the `free_row` to a free column with Dijkstra's
+/// algorithm, scanning all the columns at the current minimum distance at once, then update the
+/// dual values and the assignments along the path.
+fn augment<C: CostMatrix>(
+    free_row: usize,
+    distance_matrix: &C,
+    state: &mut LapjvState,
+    distances: &mut [i64],
+    predecessors: &mut [usize],
+    columns: &mut [usize],
+    positions: &mut [usize],
+) -> Result<(), SolverError> {
+    let n_columns = distance_matrix.n_columns();
+    distances.fill(i64::MAX);
+    for (column, cost) in distance_matrix.row_costs(free_row) {
+        distances[column] = cost - state.dual_column_values[column];
+        predecessors[column] = free_row;
+    }
+
+    // columns[..low] are ready, columns[low..up] are to scan, columns[up..] are to do
+    let mut low = 0;
+    let mut up = 0;
+    let mut minimum = 0;
+    let sink = 'search: loop {
+        if up == low {
+            minimum = columns[up..]
+                .iter()
+                .map(|&column| distances[column])
+                .min()
+                .unwrap_or(i64::MAX);
+            if minimum == i64::MAX {
+                return Err(LapjvError::Infeasible.into());
+            }
+            let todo_start = up;
+            for k in todo_start..n_columns {
+                let column = columns[k];
+                if distances[column] == minimum {
+                    if state.row_assigned_to_column[column] == UNASSIGNED {
+                        break 'search column;
+                    }
+                    columns.swap(k, up);
+                    positions[columns[k]] = k;
+                    positions[column] = up;
+                    up += 1;
+                }
+            }
+        }
+
+        let scanned_column = columns[low];
+        low += 1;
+        let row = state.row_assigned_to_column[scanned_column];
+        let offset = distance_matrix
+            .cost(row, scanned_column)
+            .expect("The assigned column should be a candidate")
+            - state.dual_column_values[scanned_column]
+            - minimum;
+        for (column, cost) in distance_matrix.row_costs(row) {
+            if positions[column] < up {
+                continue;
+            }
+            let distance = cost - state.dual_column_values[column] - offset;
+            if distance < distances[column] {
+                distances[column] = distance;
+                predecessors[column] = row;
+                if distance == minimum {
+                    if state.row_assigned_to_column[column] == UNASSIGNED {
+                        break 'search column;
+                    }
+                    let k = positions[column];
+                    columns.swap(k, up);
+                    positions[columns[k]] = k;
+                    positions[column] = up;
+                    up += 1;
+                }
+            }
+        }
+    };
+
+    // Update the dual values of the ready columns
+    for &column in columns[..low].iter() {
+        state.dual_column_values[column] += distances[column] - minimum;
+    }
+
+    // Augment the assignments along the path
+    let mut column = sink;
+    loop {
+        let row = predecessors[column];
+        let next_column = state.column_assigned_to_row[row];
+        state.assign(row, column);
+        if row == free_row {
+            break;
+        }
+        column = next_column;
+    }
+    Ok(())
+}
+
+impl Lapjv {
+    /// Solve the assignment problem exactly, or return `None` when the `deadline` passes first.
+    fn solve_exact<C: CostMatrix>(
+        &self,
+        distance_matrix: &C,
+        deadline: Deadline,
+    ) -> Result<Option<Vec<usize>>, PhomoError> {
+        if self.config.min_tile_occurrences > 0 {
+            // Minimum usages are lower bounds of the min-cost flow, which avoids repeating the
+            // columns of the matrix as many times as their capacity
+            return solve_min_usage(distance_matrix, &self.config, deadline);
+        }
+        let monitor = &self.config.monitor;
+        // The pinned rows only have their pinned column as candidate
+        let pinned = Pinned::new(distance_matrix, &self.config);
+        // The ties between equal costs are broken in the seeded order of the columns
+        let distance_matrix = &Shuffled::new(&pinned, &self.config);
+        let assignments = if self.config.max_tile_occurrences > 1 {
+            // Repeated tiles are handled by a view of the matrix with each column repeated as many
+            // times as its capacity, the costs are not copied
+            let repeated = Repeated::new(distance_matrix, self.config.max_tile_occurrences);
+            let n_columns = distance_matrix.n_columns();
+            solve_lapjv(&repeated, monitor, deadline)?.map(|assignments| {
+                assignments
+                    .into_iter()
+                    .map(|column| column % n_columns)
+                    .collect()
+            })
+        } else {
+            solve_lapjv(distance_matrix, monitor, deadline)?
+        };
+        Ok(assignments.map(|assignments| distance_matrix.inner_assignments(assignments)))
+    }
+}
+
+impl Solve for Lapjv {
+    fn solve(&mut self, distance_matrix: &DistanceMatrix) -> Result<Vec<usize>, PhomoError> {
+        self.solve_cost_matrix(distance_matrix)
+    }
+
+    fn solve_cost_matrix<C: CostMatrix>(
+        &mut self,
+        distance_matrix: &C,
+    ) -> Result<Vec<usize>, PhomoError> {
+        check_capacity(distance_matrix, &self.config)?;
+        // Half of the time budget is left for the fallback
+        let deadline = Deadline::new(self.config.time_budget);
+        let exact_deadline = Deadline::new(self.config.time_budget.map(|budget| budget / 2));
+        let assignments = self.solve_exact(distance_matrix, exact_deadline)?;
+        self.optimal = assignments.is_some();
+        match assignments {
+            Some(assignments) => Ok(assignments),
+            None => fallback(distance_matrix, &self.config, deadline, None),
+        }
+    }
+
+    fn is_optimal(&self) -> bool {
+        self.optimal
+    }
+}
+
+/// Solve the linear sum assignment problem, each column can only be assigned once. Returns `None`
+/// when the `deadline` passes before all the rows are assigned.
+fn solve_lapjv<C: CostMatrix>(
+    d_matrix: &C,
+    monitor: &Monitor,
+    deadline: Deadline,
+) -> Result<Option<Vec<usize>>, PhomoError> {
+    let (n_rows, n_columns) = (d_matrix.n_rows(), d_matrix.n_columns());
+    let mut state = LapjvState {
+        dual_column_values: vec![0; n_columns],
+        column_assigned_to_row: vec![UNASSIGNED; n_rows],
+        row_assigned_to_column: vec![UNASSIGNED; n_columns],
+    };
+
+    let assigned_rows = if n_rows == n_columns {
+        column_reduction(d_matrix, &mut state)
+    } else {
+        row_reduction(d_matrix, &mut state)
+    };
+    reduction_transfer(&assigned_rows, d_matrix, &mut state);
+    let free_rows = (0..n_rows)
+        .filter(|&row| state.column_assigned_to_row[row] == UNASSIGNED)
+        .collect();
+    let free_rows = augmenting_row_reduction(free_rows, d_matrix, &mut state);
+
+    // The reductions can leave no free row to augment
+    monitor.check()?;
+    let mut distances = vec![i64::MAX; n_columns];
+    let mut predecessors = vec![UNASSIGNED; n_columns];
+    let mut columns = (0..n_columns).collect::<Vec<_>>();
+    let mut positions = (0..n_columns).collect::<Vec<_>>();
+    let step = monitor.step("Computing assignments", free_rows.len());
+    for free_row in free_rows {
+        if deadline.is_past() {
+            return Ok(None);
+        }
+        augment(
+            free_row,
+            d_matrix,
+            &mut state,
+            &mut distances,
+            &mut predecessors,
+            &mut columns,
+            &mut positions,
+        )?;
+        step.tick()?;
+    }
+
+    Ok(Some(state.column_assigned_to_row))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solvers::test_utils::{random_costs, total_cost};
+    use crate::{DistanceMatrix, Hungarian, SparseDistanceMatrix};
+
+    #[test]
+    fn test_same_cost_as_hungarian() {
+        for (rows, columns, max_tile_occurrences) in
+            [(20, 30, 1), (20, 20, 1), (30, 8, 4), (32, 8, 4)]
+        {
+            for max in [5, 1000] {
+                let d_matrix =
+                    DistanceMatrix::new(rows, columns, random_costs(rows * columns, max)).unwrap();
+                let config = SolverConfig {
+                    max_tile_occurrences,
+                    ..Default::default()
+                };
+                let expected = Hungarian::new(config.clone()).solve(&d_matrix).unwrap();
+                let assignments = Lapjv::new(config).solve(&d_matrix).unwrap();
+
+                let mut counts = vec![0; columns];
+                assignments.iter().for_each(|&column| counts[column] += 1);
+                assert!(counts.iter().all(|&count| count <= max_tile_occurrences));
+                assert_eq!(
+                    total_cost(&d_matrix, &assignments),
+                    total_cost(&d_matrix, &expected)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_sparse() {
+        let costs = random_costs(12 * 3, 100);
+        let candidates = (0..12)
+            .map(|row| {
+                (0..3)
+                    .map(|k| ((row + 2 * k) % 12, costs[row * 3 + k]))
+                    .collect()
+            })
+            .collect();
+        let d_matrix = SparseDistanceMatrix::new(12, 12, candidates).unwrap();
+        let expected = Hungarian::default().solve_cost_matrix(&d_matrix).unwrap();
+        let assignments = Lapjv::default().solve_cost_matrix(&d_matrix).unwrap();
+        assert_eq!(
+            total_cost(&d_matrix, &assignments),
+            total_cost(&d_matrix, &expected)
+        );
+    }
+}
@@ -0,0 +1,354 @@
+use std::time::Duration;
+
+use log::info;
+
+use crate::cost_matrix::Pinned;
+use crate::error::PhomoError;
+use crate::solvers::error::SolverError;
+use crate::solvers::{check_capacity, Deadline, Solve, SolverConfig};
+use crate::{CostMatrix, DistanceMatrix};
+
+/// The LocalSearch struct improves an existing assignment with local moves.
+///
+/// Each cell is in turn moved to a cheaper tile which can still be used, or swapped with a cell
+/// holding a cheaper tile when the swap lowers the total cost. The passes over the cells are
+/// repeated until no move improves the assignment, or the time budget is spent. The pinned cells
+/// of the configuration are never moved, and no tile is brought under its minimum usage.
+#[derive(Debug, Clone, Default)]
+pub struct LocalSearch {
+    config: SolverConfig,
+    time_budget: Option<Duration>,
+}
+
+impl LocalSearch {
+    /// Creates a new instance of the LocalSearch struct.
+    ///
+    /// # Arguments
+    /// - `config`: The configuration the refined assignments must respect.
+    /// - `time_budget`: The maximum time spent refining, on top of the
+    ///   [`SolverConfig::time_budget`]. The passes are only limited by the convergence when both
+    ///   are `None`.
+    pub fn new(config: SolverConfig, time_budget: Option<Duration>) -> Self {
+        Self {
+            config,
+            time_budget,
+        }
+    }
+
+    /// Refine the `assignments` of the `distance_matrix`.
+    ///
+    /// # Arguments
+    /// - `distance_matrix`: The cost matrix of the assignments.
+    /// - `assignments`: The tile index of each cell, as returned by a [`Solve`] implementation.
+    ///
+    /// # Returns
+    /// The refined assignments, whose total cost is at most the cost of the `assignments`.
+    ///
+    /// # Errors
+    /// - [`PhomoError::SolverError`]: The `assignments` don't match the `distance_matrix` or
+    ///   don't respect the configuration.
+    pub fn refine<C: CostMatrix>(
+        &self,
+        distance_matrix: &C,
+        assignments: Vec<usize>,
+    ) -> Result<Vec<usize>, PhomoError> {
+        self.refine_with_passes(distance_matrix, assignments, self.deadline())
+            .map(|(assignments, _)| assignments)
+    }
+
+    /// The deadline of a refinement starting now.
+    fn deadline(&self) -> Deadline {
+        Deadline::new(self.time_budget).min(Deadline::new(self.config.time_budget))
+    }
+
+    /// Refine the `assignments` of the `distance_matrix` until the `deadline`, and return the
+    /// number of passes over the cells.
+    pub(crate) fn refine_with_passes<C: CostMatrix>(
+        &self,
+        distance_matrix: &C,
+        mut assignments: Vec<usize>,
+        deadline: Deadline,
+    ) -> Result<(Vec<usize>, usize), PhomoError> {
+        // The pinned rows only have their pinned column as candidate, so they are never moved
+        let distance_matrix = &Pinned::new(distance_matrix, &self.config);
+        let n_columns = distance_matrix.n_columns();
+        let capacity = self.config.max_tile_occurrences;
+        if assignments.len() != distance_matrix.n_rows() {
+            return Err(SolverError::InvalidAssignment {
+                row: assignments.len().min(distance_matrix.n_rows()),
+            }
+            .into());
+        }
+
+        // The cells holding each tile
+        let mut holders = vec![Vec::new(); n_columns];
+        let mut costs = Vec::with_capacity(assignments.len());
+        for (row, &column) in assignments.iter().enumerate() {
+            let cost = (column < n_columns)
+                .then(|| distance_matrix.cost(row, column))
+                .flatten()
+                .ok_or(SolverError::InvalidAssignment { row })?;
+            holders[column].push(row);
+            if holders[column].len() > capacity {
+                return Err(SolverError::InvalidAssignment { row }.into());
+            }
+            costs.push(cost);
+        }
+
+        let initial_cost: i64 = costs.iter().sum();
+        let mut n_passes = 0;
+        let mut improved = true;
+        'passes: while improved {
+            improved = false;
+            n_passes += 1;
+            for row in 0..assignments.len() {
+                if row % 64 == 0 {
+                    self.config.monitor.check()?;
+                    if deadline.is_past() {
+                        break 'passes;
+                    }
+                }
+                let Some(best_move) = best_move(
+                    row,
+                    distance_matrix,
+                    &assignments,
+                    &costs,
+                    &holders,
+                    capacity,
+                    self.config.min_tile_occurrences,
+                ) else {
+                    continue;
+                };
+                improved = true;
+                let column = assignments[row];
+                let position = holders[column]
+                    .iter()
+                    .position(|&r| r == row)
+                    .expect("The row should hold its column");
+                holders[column].swap_remove(position);
+                match best_move {
+                    Move::Substitute {
+                        column: new_column,
+                        cost,
+                    } => {
+                        assignments[row] = new_column;
+                        costs[row] = cost;
+                        holders[new_column].push(row);
+                    }
+                    Move::Swap {
+                        other_row,
+                        cost,
+                        other_cost,
+                    } => {
+                        let other_column = assignments[other_row];
+                        let position = holders[other_column]
+                            .iter()
+                            .position(|&r| r == other_row)
+                            .expect("The row should hold its column");
+                        holders[other_column][position] = row;
+                        holders[column].push(other_row);
+                        assignments.swap(row, other_row);
+                        costs[row] = cost;
+                        costs[other_row] = other_cost;
+                    }
+                }
+            }
+        }
+
+        info!(
+            "Local search: cost {} -> {} in {} passes",
+            initial_cost,
+            costs.iter().sum::<i64>(),
+            n_passes
+        );
+        Ok((assignments, n_passes))
+    }
+}
+
+/// An improving move of a row.
+#[derive(Debug)]
+enum Move {
+    /// Assign a tile which is still available to the row.
+    Substitute { column: usize, cost: i64 },
+    /// Swap the tiles of the row and of the `other_row`.
+    Swap {
+        other_row: usize,
+        cost: i64,
+        other_cost: i64,
+    },
+}
+
+/// Find the move of the `row` which lowers the total cost the most.
+///
+/// Only the tiles cheaper than the current tile of the row are considered: an improving swap
+/// lowers the cost of at least one of its two rows, so it is found from that row.
+fn best_move<C: CostMatrix>(
+    row: usize,
+    distance_matrix: &C,
+    assignments: &[usize],
+    costs: &[i64],
+    holders: &[Vec<usize>],
+    capacity: usize,
+    min_occurrences: usize,
+) -> Option<Move> {
+    let column = assignments[row];
+    let current_cost = costs[row];
+    // A substitution can't bring the current tile under its minimum usage
+    let can_substitute = holders[column].len() > min_occurrences;
+    let mut best_gain = 0;
+    let mut best_move = None;
+    for (new_column, cost) in distance_matrix.row_costs(row) {
+        let gain = current_cost - cost;
+        if gain <= 0 {
+            continue;
+        }
+        if can_substitute && holders[new_column].len() < capacity {
+            if gain > best_gain {
+                best_gain = gain;
+                best_move = Some(Move::Substitute {
+                    column: new_column,
+                    cost,
+                });
+            }
+            continue;
+        }
+        for &other_row in holders[new_column].iter() {
+            let Some(other_cost) = distance_matrix.cost(other_row, column) else {
+                continue;
+            };
+            let swap_gain = gain + costs[other_row] - other_cost;
+            if swap_gain > best_gain {
+                best_gain = swap_gain;
+                best_move = Some(Move::Swap {
+                    other_row,
+                    cost,
+                    other_cost,
+                });
+            }
+        }
+    }
+    best_move
+}
+
+/// The Refined struct wraps a solver, and refines its assignments with a [`LocalSearch`].
+///
+/// For example, `Refined::new(Greedy::default(), LocalSearch::default())` gets close to the
+/// quality of the optimal solvers on large grids, for a fraction of the time.
+#[derive(Debug, Default)]
+pub struct Refined<S: Solve> {
+    solver: S,
+    local_search: LocalSearch,
+    n_passes: Option<usize>,
+}
+
+impl<S: Solve> Refined<S> {
+    /// Creates a new instance of the Refined struct.
+    ///
+    /// # Arguments
+    /// - `solver`: The solver computing the initial assignments.
+    /// - `local_search`: The local search refining the assignments.
+    pub fn new(solver: S, local_search: LocalSearch) -> Self {
+        Self {
+            solver,
+            local_search,
+            n_passes: None,
+        }
+    }
+}
+
+impl<S: Solve> Solve for Refined<S> {
+    fn solve(&mut self, distance_matrix: &DistanceMatrix) -> Result<Vec<usize>, PhomoError> {
+        self.solve_cost_matrix(distance_matrix)
+    }
+
+    fn solve_cost_matrix<C: CostMatrix>(
+        &mut self,
+        distance_matrix: &C,
+    ) -> Result<Vec<usize>, PhomoError> {
+        check_capacity(distance_matrix, &self.local_search.config)?;
+        // The time budget of the configuration covers both the solver and the refinement
+        let deadline = Deadline::new(self.local_search.config.time_budget);
+        let assignments = self.solver.solve_cost_matrix(distance_matrix)?;
+        let deadline = deadline.min(Deadline::new(self.local_search.time_budget));
+        let (assignments, n_passes) =
+            self.local_search
+                .refine_with_passes(distance_matrix, assignments, deadline)?;
+        self.n_passes = Some(n_passes);
+        Ok(assignments)
+    }
+
+    /// The number of local search passes over the cells.
+    fn iterations(&self) -> Option<usize> {
+        self.n_passes
+    }
+
+    /// The refinement never worsens the assignments, so they are optimal when those of the
+    /// wrapped solver are.
+    fn is_optimal(&self) -> bool {
+        self.solver.is_optimal()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solvers::test_utils::{distance_matrix, total_cost};
+    use crate::{DistanceMatrix, Greedy, Hungarian};
+
+    #[test]
+    fn test_refine_greedy() {
+        for (rows, columns, max_tile_occurrences) in [(40, 40, 1), (40, 60, 1), (60, 20, 3)] {
+            let d_matrix = distance_matrix(rows, columns);
+            let config = SolverConfig {
+                max_tile_occurrences,
+                ..Default::default()
+            };
+            let greedy = Greedy::new(config.clone()).solve(&d_matrix).unwrap();
+            let optimal = Hungarian::new(config.clone()).solve(&d_matrix).unwrap();
+            let refined = LocalSearch::new(config, None)
+                .refine(&d_matrix, greedy.clone())
+                .unwrap();
+
+            let mut counts = vec![0; columns];
+            refined.iter().for_each(|&column| counts[column] += 1);
+            assert!(counts.iter().all(|&count| count <= max_tile_occurrences));
+            assert!(total_cost(&d_matrix, &refined) <= total_cost(&d_matrix, &greedy));
+            assert!(total_cost(&d_matrix, &refined) >= total_cost(&d_matrix, &optimal));
+        }
+    }
+
+    #[test]
+    fn test_refine_swap() {
+        // Swapping the tiles of the two cells is the only improvement
+        let d_matrix = DistanceMatrix::new(2, 2, vec![5, 1, 1, 5]).unwrap();
+        let refined = LocalSearch::default()
+            .refine(&d_matrix, vec![0, 1])
+            .unwrap();
+        assert_eq!(refined, vec![1, 0]);
+    }
+
+    #[test]
+    fn test_refine_pinned() {
+        // The pinned cell keeps its tile even though swapping would be cheaper
+        let d_matrix = DistanceMatrix::new(2, 2, vec![5, 1, 1, 5]).unwrap();
+        let config = SolverConfig {
+            pinned: vec![(0, 0)],
+            ..Default::default()
+        };
+        let local_search = LocalSearch::new(config, None);
+        assert_eq!(
+            local_search.refine(&d_matrix, vec![0, 1]).unwrap(),
+            vec![0, 1]
+        );
+        assert!(local_search.refine(&d_matrix, vec![1, 0]).is_err());
+    }
+
+    #[test]
+    fn test_refine_invalid() {
+        let d_matrix = distance_matrix(3, 3);
+        let local_search = LocalSearch::default();
+        assert!(local_search.refine(&d_matrix, vec![0, 1]).is_err());
+        assert!(local_search.refine(&d_matrix, vec![0, 1, 3]).is_err());
+        assert!(local_search.refine(&d_matrix, vec![0, 1, 1]).is_err());
+    }
+}
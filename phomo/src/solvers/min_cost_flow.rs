@@ -0,0 +1,393 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use crate::cost_matrix::{LowerBounded, Pinned, Shuffled};
+use crate::error::PhomoError;
+use crate::solvers::error::MinCostFlowError;
+use crate::solvers::error::SolverError;
+use crate::solvers::SolverConfig;
+use crate::solvers::{check_capacity, fallback, Deadline, Solve};
+use crate::Monitor;
+use crate::{CostMatrix, DistanceMatrix};
+
+const UNASSIGNED: usize = usize::MAX;
+
+/// The MinCostFlow struct represents the min-cost flow solver.
+///
+/// The assignment is modelled as a flow network, from a source to each cell, from each cell to
+/// its candidate tiles, and from each tile to a sink. The tile to sink arcs have a capacity of
+/// [`SolverConfig::max_tile_occurrences`], so repeated tiles don't require repeating the columns
+/// of the cost matrix like the [`Hungarian`](crate::Hungarian) solver does. The flow is found
+/// with successive shortest paths, using Dijkstra's algorithm on the reduced costs.
+///
+/// The [`SolverConfig::min_tile_occurrences`] are lower bounds on the flow of the tile to sink
+/// arcs, each tile is split into a mandatory part with a capacity of the minimum usage, and an
+/// optional part with the rest of the capacity. The [`Hungarian`](crate::Hungarian) and
+/// [`Lapjv`](crate::Lapjv) solvers also use it to handle the minimum usages.
+///
+/// It finds the same optimal total cost as the [`Hungarian`](crate::Hungarian) solver.
+#[derive(Debug, Default)]
+pub struct MinCostFlow {
+    config: SolverConfig,
+    optimal: bool,
+}
+
+impl MinCostFlow {
+    /// Creates a new instance of the MinCostFlow struct with the given configuration.
+    ///
+    /// # Arguments
+    /// - `config`: The configuration for the solver.
+    pub fn new(config: SolverConfig) -> Self {
+        Self {
+            config,
+            optimal: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct FlowState {
+    /// The potential of each row node.
+    row_potentials: Vec<i64>,
+    /// The potential of each column node.
+    column_potentials: Vec<i64>,
+    /// The column each row sends its flow to.
+    column_assigned_to_row: Vec<usize>,
+    /// The rows which send their flow to each column.
+    rows_assigned_to_column: Vec<Vec<usize>>,
+    /// The reduced distance of each column from the source row of the current path.
+    distances: Vec<i64>,
+    /// The row from which each column was reached.
+    predecessors: Vec<usize>,
+    /// The columns whose distance is final.
+    settled_columns: Vec<bool>,
+    /// The rows which were reached, with the distance at which they were reached.
+    reached_rows: Vec<(usize, i64)>,
+}
+
+/// Find the shortest augmenting path from the unassigned `source_row` to a column with some
+/// remaining capacity, the `capacities` being those of each column.
+///
+/// Returns the sink column and its distance.
+fn find_shortest_path<C: CostMatrix>(
+    source_row: usize,
+    distance_matrix: &C,
+    capacities: &[usize],
+    state: &mut FlowState,
+) -> Result<(usize, i64), SolverError> {
+    state.distances.fill(i64::MAX);
+    state.settled_columns.fill(false);
+    state.reached_rows.clear();
+
+    let mut heap = BinaryHeap::new();
+    let mut row = source_row;
+    let mut row_distance = 0;
+    loop {
+        state.reached_rows.push((row, row_distance));
+
+        // Relax the arcs from the row to its candidate columns
+        for (column, cost) in distance_matrix.row_costs(row) {
+            if state.settled_columns[column] {
+                continue;
+            }
+            let distance =
+                row_distance + cost - state.row_potentials[row] - state.column_potentials[column];
+            if distance < state.distances[column] {
+                state.distances[column] = distance;
+                state.predecessors[column] = row;
+                // Prefer columns with remaining capacity when the distances are tied
+                let full = state.rows_assigned_to_column[column].len() >= capacities[column];
+                heap.push(Reverse((distance, full, column)));
+            }
+        }
+
+        // Settle the closest column
+        let (distance, column) = loop {
+            let Reverse((distance, _, column)) =
+                heap.pop().ok_or(MinCostFlowError::NoAugmentingPath)?;
+            if !state.settled_columns[column] && distance == state.distances[column] {
+                break (distance, column);
+            }
+        };
+        state.settled_columns[column] = true;
+
+        if state.rows_assigned_to_column[column].len() < capacities[column] {
+            return Ok((column, distance));
+        }
+
+        // The column is full, the path continues through the rows it is assigned to, the arcs
+        // of the assigned rows have a reduced cost of 0.
+        for &assigned_row in state.rows_assigned_to_column[column].iter().skip(1) {
+            state.reached_rows.push((assigned_row, distance));
+            for (next_column, cost) in distance_matrix.row_costs(assigned_row) {
+                if state.settled_columns[next_column] {
+                    continue;
+                }
+                let next_distance = distance + cost
+                    - state.row_potentials[assigned_row]
+                    - state.column_potentials[next_column];
+                if next_distance < state.distances[next_column] {
+                    state.distances[next_column] = next_distance;
+                    state.predecessors[next_column] = assigned_row;
+                    let full =
+                        state.rows_assigned_to_column[next_column].len() >= capacities[next_column];
+                    heap.push(Reverse((next_distance, full, next_column)));
+                }
+            }
+        }
+        row = state.rows_assigned_to_column[column][0];
+        row_distance = distance;
+    }
+}
+
+/// Update the potentials so that the reduced costs stay non negative, and the arcs along the
+/// augmenting path have a reduced cost of 0.
+fn update_potentials(sink_distance: i64, state: &mut FlowState) {
+    for &(row, distance) in state.reached_rows.iter() {
+        state.row_potentials[row] += sink_distance - distance;
+    }
+    for (column, potential) in state.column_potentials.iter_mut().enumerate() {
+        if state.settled_columns[column] {
+            *potential -= sink_distance - state.distances[column];
+        }
+    }
+}
+
+/// Send one unit of flow along the augmenting path ending at the `sink_column`.
+fn augment(source_row: usize, sink_column: usize, state: &mut FlowState) {
+    let mut column = sink_column;
+    loop {
+        let row = state.predecessors[column];
+        let previous_column = state.column_assigned_to_row[row];
+        state.column_assigned_to_row[row] = column;
+        state.rows_assigned_to_column[column].push(row);
+
+        if row == source_row {
+            break;
+        }
+        let rows = &mut state.rows_assigned_to_column[previous_column];
+        let position = rows
+            .iter()
+            .position(|&r| r == row)
+            .expect("The row should be assigned to its column");
+        rows.swap_remove(position);
+        column = previous_column;
+    }
+}
+
+/// Assign the rows of the `distance_matrix` by successive shortest paths, each column being
+/// assigned at most its number of `capacities` times. Returns `None` when the `deadline` passes
+/// before all the rows are assigned.
+fn assign<C: CostMatrix>(
+    distance_matrix: &C,
+    capacities: &[usize],
+    monitor: &Monitor,
+    deadline: Deadline,
+) -> Result<Option<Vec<usize>>, PhomoError> {
+    let (n_rows, n_columns) = (distance_matrix.n_rows(), distance_matrix.n_columns());
+    let mut state = FlowState {
+        row_potentials: vec![0; n_rows],
+        column_potentials: vec![0; n_columns],
+        column_assigned_to_row: vec![UNASSIGNED; n_rows],
+        rows_assigned_to_column: vec![Vec::new(); n_columns],
+        distances: vec![i64::MAX; n_columns],
+        predecessors: vec![UNASSIGNED; n_columns],
+        settled_columns: vec![false; n_columns],
+        reached_rows: Vec::new(),
+    };
+
+    let step = monitor.step("Computing assignments", n_rows);
+    for source_row in 0..n_rows {
+        if deadline.is_past() {
+            return Ok(None);
+        }
+        let (sink_column, sink_distance) =
+            find_shortest_path(source_row, distance_matrix, capacities, &mut state)?;
+        update_potentials(sink_distance, &mut state);
+        augment(source_row, sink_column, &mut state);
+        step.tick()?;
+    }
+
+    Ok(Some(state.column_assigned_to_row))
+}
+
+/// Solve the assignment problem of the `distance_matrix` exactly, each column being assigned at
+/// least [`SolverConfig::min_tile_occurrences`] times, or return `None` when the `deadline` passes
+/// first. The pinned rows and the seeded order of the columns of the `config` are applied.
+///
+/// # Errors
+/// - [`PhomoError::SolverError`]: A column can't be assigned its minimum number of times, or no
+///   complete assignment exists.
+pub(crate) fn solve_min_usage<C: CostMatrix>(
+    distance_matrix: &C,
+    config: &SolverConfig,
+    deadline: Deadline,
+) -> Result<Option<Vec<usize>>, PhomoError> {
+    // The pinned rows only have their pinned column as candidate
+    let pinned = Pinned::new(distance_matrix, config);
+    // The ties between equal costs are broken in the seeded order of the columns
+    let shuffled = Shuffled::new(&pinned, config);
+    let lower_bounded = LowerBounded::new(&shuffled, config);
+    let Some(assignments) = assign(
+        &lower_bounded,
+        &lower_bounded.capacities(),
+        &config.monitor,
+        deadline,
+    )?
+    else {
+        return Ok(None);
+    };
+    let assignments = shuffled.inner_assignments(lower_bounded.inner_assignments(assignments));
+
+    // The mandatory parts are all filled when the minimum usages can be met
+    let mut counts = vec![0; distance_matrix.n_columns()];
+    assignments.iter().for_each(|&column| counts[column] += 1);
+    if let Some(column) = counts
+        .iter()
+        .position(|&count| count < config.min_tile_occurrences)
+    {
+        return Err(SolverError::UnderusedColumn { column }.into());
+    }
+    Ok(Some(assignments))
+}
+
+impl MinCostFlow {
+    /// Solve the assignment problem exactly, or return `None` when the `deadline` passes first.
+    fn solve_exact<C: CostMatrix>(
+        &self,
+        distance_matrix: &C,
+        deadline: Deadline,
+    ) -> Result<Option<Vec<usize>>, PhomoError> {
+        if self.config.min_tile_occurrences > 0 {
+            return solve_min_usage(distance_matrix, &self.config, deadline);
+        }
+        // The pinned rows only have their pinned column as candidate
+        let pinned = Pinned::new(distance_matrix, &self.config);
+        // The ties between equal costs are broken in the seeded order of the columns
+        let distance_matrix = &Shuffled::new(&pinned, &self.config);
+        let capacities = vec![self.config.max_tile_occurrences; distance_matrix.n_columns()];
+        let assignments = assign(distance_matrix, &capacities, &self.config.monitor, deadline)?;
+        Ok(assignments.map(|assignments| distance_matrix.inner_assignments(assignments)))
+    }
+}
+
+impl Solve for MinCostFlow {
+    fn solve(&mut self, distance_matrix: &DistanceMatrix) -> Result<Vec<usize>, PhomoError> {
+        self.solve_cost_matrix(distance_matrix)
+    }
+
+    fn solve_cost_matrix<C: CostMatrix>(
+        &mut self,
+        distance_matrix: &C,
+    ) -> Result<Vec<usize>, PhomoError> {
+        check_capacity(distance_matrix, &self.config)?;
+        // Half of the time budget is left for the fallback
+        let deadline = Deadline::new(self.config.time_budget);
+        let exact_deadline = Deadline::new(self.config.time_budget.map(|budget| budget / 2));
+        let assignments = self.solve_exact(distance_matrix, exact_deadline)?;
+        self.optimal = assignments.is_some();
+        match assignments {
+            Some(assignments) => Ok(assignments),
+            None => fallback(distance_matrix, &self.config, deadline, None),
+        }
+    }
+
+    fn is_optimal(&self) -> bool {
+        self.optimal
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solvers::test_utils::{distance_matrix, total_cost};
+    use crate::{Hungarian, SparseDistanceMatrix};
+
+    #[test]
+    fn test_same_cost_as_hungarian() {
+        for (rows, columns, max_tile_occurrences) in [(20, 30, 1), (20, 20, 1), (30, 8, 4)] {
+            let d_matrix = distance_matrix(rows, columns);
+            let config = SolverConfig {
+                max_tile_occurrences,
+                ..Default::default()
+            };
+            let expected = Hungarian::new(config.clone()).solve(&d_matrix).unwrap();
+            let assignments = MinCostFlow::new(config).solve(&d_matrix).unwrap();
+
+            let mut counts = vec![0; columns];
+            assignments.iter().for_each(|&column| counts[column] += 1);
+            assert!(counts.iter().all(|&count| count <= max_tile_occurrences));
+            assert_eq!(
+                total_cost(&d_matrix, &assignments),
+                total_cost(&d_matrix, &expected)
+            );
+        }
+    }
+
+    #[test]
+    fn test_min_usage() {
+        // Compare with all the 3^7 assignments of the cells
+        let d_matrix = distance_matrix(7, 3);
+        let config = SolverConfig {
+            max_tile_occurrences: 3,
+            min_tile_occurrences: 2,
+            pinned: vec![(0, 1)],
+            ..Default::default()
+        };
+        let expected = (0..3usize.pow(7))
+            .map(|index| {
+                (0..7)
+                    .map(|row| index / 3usize.pow(row) % 3)
+                    .collect::<Vec<_>>()
+            })
+            .filter(|assignments| {
+                assignments[0] == 1
+                    && (0..3).all(|column| {
+                        let count = assignments.iter().filter(|&&c| c == column).count();
+                        (2..=3).contains(&count)
+                    })
+            })
+            .map(|assignments| total_cost(&d_matrix, &assignments))
+            .min()
+            .unwrap();
+        let assignments = MinCostFlow::new(config).solve(&d_matrix).unwrap();
+        assert_eq!(assignments[0], 1);
+        assert_eq!(total_cost(&d_matrix, &assignments), expected);
+    }
+
+    #[test]
+    fn test_min_usage_sparse() {
+        // Tile 2 is only a candidate of the cell pinned to tile 0
+        let d_matrix = SparseDistanceMatrix::new(
+            3,
+            3,
+            vec![
+                vec![(0, 1), (2, 1)],
+                vec![(0, 1), (1, 2)],
+                vec![(0, 2), (1, 1)],
+            ],
+        )
+        .unwrap();
+        let config = SolverConfig {
+            max_tile_occurrences: 2,
+            min_tile_occurrences: 1,
+            pinned: vec![(0, 0)],
+            ..Default::default()
+        };
+        assert!(matches!(
+            MinCostFlow::new(config).solve_cost_matrix(&d_matrix),
+            Err(PhomoError::SolverError(SolverError::UnderusedColumn {
+                column: 2
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_too_few_columns() {
+        let d_matrix = distance_matrix(10, 4);
+        let config = SolverConfig {
+            max_tile_occurrences: 2,
+            ..Default::default()
+        };
+        assert!(MinCostFlow::new(config).solve(&d_matrix).is_err());
+    }
+}
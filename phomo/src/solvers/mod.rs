@@ -1,22 +1,80 @@
 /// A module which contains structs which implement the [`Solve`](crate::solvers::Solve) trait. These structs are used to
 /// solve the assignment problem.
-use crate::{error::PhomoError, DistanceMatrix};
+use std::time::Duration;
+#[cfg(not(target_family = "wasm"))]
+use std::time::Instant;
+#[cfg(target_family = "wasm")]
+use web_time::Instant;
 
+use log::info;
+
+use crate::{error::PhomoError, progress::Monitor, CostMatrix, DistanceMatrix};
+use error::SolverError;
+use greedy::Greedy;
+use local_search::LocalSearch;
+
+pub mod annealing;
 pub mod auction;
 pub mod error;
 pub mod greedy;
 pub mod hungarian;
+pub mod lapjv;
+pub mod local_search;
+pub mod min_cost_flow;
+pub mod spacing;
+#[cfg(test)]
+pub(crate) mod test_utils;
 
 /// Common configuration for the solvers.
 #[derive(Debug, Clone)]
 pub struct SolverConfig {
+    /// The maximum number of times a tile can be assigned, the capacity of each column of the
+    /// cost matrix.
     pub max_tile_occurrences: usize,
+    /// The minimum number of times each tile must be assigned, 0 lets tiles go unused. Pinned
+    /// tiles count towards it.
+    pub min_tile_occurrences: usize,
+    /// The `(cell_index, tile_index)` pairs which are fixed in the assignments, the cells being
+    /// indexed in row major order. The pinned tiles count against the `max_tile_occurrences`.
+    pub pinned: Vec<(usize, usize)>,
+    /// The progress reporting and cancellation of the solvers. The cancelled solvers return a
+    /// [`PhomoError::Cancelled`] error.
+    pub monitor: Monitor,
+    /// The maximum time spent solving, `None` to solve until completion.
+    ///
+    /// The exact solvers which can't finish within half of the budget fall back to the
+    /// [`Greedy`] solver refined by a [`LocalSearch`] for the rest of the budget, the
+    /// [`Auction`](auction::Auction) solver refines the assignments of its last completed
+    /// ε-scaling phase instead. The single pass of the [`Greedy`] solver is not interrupted. See
+    /// [`Solve::is_optimal`].
+    pub time_budget: Option<Duration>,
+    /// The seed of the order in which the solvers break the ties between equal costs, for
+    /// example between flat tiles. The same seed gives the same assignments on every platform,
+    /// a different seed can give a different looking mosaic of the same cost. `None` breaks the
+    /// ties in the order of the tiles.
+    pub seed: Option<u64>,
+}
+
+impl SolverConfig {
+    /// The pinned column of each of the `n_rows` rows, if any.
+    pub(crate) fn pinned_columns(&self, n_rows: usize) -> Vec<Option<usize>> {
+        let mut pins = vec![None; n_rows];
+        for &(row, column) in self.pinned.iter().filter(|&&(row, _)| row < n_rows) {
+            pins[row] = Some(column);
+        }
+        pins
+    }
 }
 
 impl Default for SolverConfig {
     fn default() -> Self {
         SolverConfig {
             max_tile_occurrences: 1,
+            min_tile_occurrences: 0,
+            pinned: Vec::new(),
+            monitor: Monitor::default(),
+            time_budget: None,
+            seed: None,
         }
     }
 }
@@ -26,9 +84,420 @@ pub trait Solve {
     /// Solve the assignment problem using the solver.
     ///
     /// # Arguments
-    /// - `distance_matrix`: The distance matrix.
+    /// - `distance_matrix`: The dense cost matrix.
     ///
     /// # Errors
     /// - [`PhomoError::SolverError``]: An error occurred while solving the assignment problem.
     fn solve(&mut self, distance_matrix: &DistanceMatrix) -> Result<Vec<usize>, PhomoError>;
+
+    /// Solve the assignment problem of any [`CostMatrix`] using the solver.
+    ///
+    /// It requires a sized solver, so that [`Solve`] remains usable as a `dyn Solve` trait object
+    /// through [`Solve::solve`].
+    ///
+    /// # Arguments
+    /// - `distance_matrix`: The cost matrix, either a dense [`DistanceMatrix`] or a
+    ///   [`SparseDistanceMatrix`](crate::SparseDistanceMatrix).
+    ///
+    /// # Errors
+    /// - [`PhomoError::SolverError``]: An error occurred while solving the assignment problem.
+    fn solve_cost_matrix<C: CostMatrix>(
+        &mut self,
+        distance_matrix: &C,
+    ) -> Result<Vec<usize>, PhomoError>
+    where
+        Self: Sized;
+
+    /// The number of iterations of the last call to [`solve`](Solve::solve), for the solvers
+    /// which iterate until convergence. See [`SolveReport`](crate::SolveReport).
+    fn iterations(&self) -> Option<usize> {
+        None
+    }
+
+    /// Whether the assignments of the last call to [`solve`](Solve::solve) are proven optimal.
+    /// It is `false` for the heuristic solvers, and for the exact solvers which ran out of their
+    /// [`SolverConfig::time_budget`].
+    fn is_optimal(&self) -> bool {
+        false
+    }
+}
+
+/// The instant at which a solver runs out of its time budget.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Deadline {
+    instant: Option<Instant>,
+}
+
+impl Deadline {
+    /// The deadline in `time_budget` from now, `None` for no deadline.
+    pub(crate) fn new(time_budget: Option<Duration>) -> Self {
+        Self {
+            instant: time_budget.map(|budget| Instant::now() + budget),
+        }
+    }
+
+    /// The earliest of the two deadlines.
+    pub(crate) fn min(self, other: Self) -> Self {
+        Self {
+            instant: match (self.instant, other.instant) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            },
+        }
+    }
+
+    /// Whether the deadline has passed.
+    pub(crate) fn is_past(&self) -> bool {
+        self.instant
+            .is_some_and(|instant| Instant::now() >= instant)
+    }
+}
+
+/// The SplitMix64 pseudo random number generator, which draws the same numbers on every platform
+/// for a given seed.
+#[derive(Debug, Clone)]
+pub(crate) struct SplitMix64(u64);
+
+impl SplitMix64 {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..n`.
+    pub(crate) fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// A number in `[0, 1)`.
+    pub(crate) fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Refine the `assignments`, or the assignments of the [`Greedy`] solver when `None`, with a
+/// [`LocalSearch`] until the `deadline`. Used by the exact solvers which run out of their time
+/// budget.
+pub(crate) fn fallback<C: CostMatrix>(
+    distance_matrix: &C,
+    config: &SolverConfig,
+    deadline: Deadline,
+    assignments: Option<Vec<usize>>,
+) -> Result<Vec<usize>, PhomoError> {
+    let assignments = match assignments {
+        Some(assignments) => assignments,
+        None => {
+            info!("Out of time, falling back to the greedy assignments");
+            Greedy::new(config.clone()).solve_cost_matrix(distance_matrix)?
+        }
+    };
+    LocalSearch::new(config.clone(), None)
+        .refine_with_passes(distance_matrix, assignments, deadline)
+        .map(|(assignments, _)| assignments)
+}
+
+/// Check that the `distance_matrix` has enough column capacity to assign every row, enough rows to
+/// assign every column its minimum number of times, that every row has at least one candidate
+/// column, and that the pinned pairs can be honoured.
+pub(crate) fn check_capacity<C: CostMatrix>(
+    distance_matrix: &C,
+    config: &SolverConfig,
+) -> Result<(), SolverError> {
+    let mut pinned_rows = vec![false; distance_matrix.n_rows()];
+    let mut pin_counts = vec![0; distance_matrix.n_columns()];
+    for &(row, column) in config.pinned.iter() {
+        if row >= distance_matrix.n_rows()
+            || column >= distance_matrix.n_columns()
+            || pinned_rows[row]
+        {
+            return Err(SolverError::InvalidPin { row, column });
+        }
+        pinned_rows[row] = true;
+        pin_counts[column] += 1;
+        if pin_counts[column] > config.max_tile_occurrences {
+            return Err(SolverError::TooManyPins {
+                column,
+                capacity: config.max_tile_occurrences,
+            });
+        }
+    }
+    if config.min_tile_occurrences > config.max_tile_occurrences {
+        return Err(SolverError::InvalidOccurrences {
+            min: config.min_tile_occurrences,
+            max: config.max_tile_occurrences,
+        });
+    }
+    // The rows left once the pinned rows are assigned must complete the minimum usages
+    let required: usize = pin_counts
+        .iter()
+        .map(|&count| config.min_tile_occurrences.saturating_sub(count))
+        .sum();
+    if required > pinned_rows.iter().filter(|&&pinned| !pinned).count() {
+        return Err(SolverError::TooFewRows {
+            rows: distance_matrix.n_rows(),
+            columns: distance_matrix.n_columns(),
+            min: config.min_tile_occurrences,
+        });
+    }
+
+    if distance_matrix.n_columns() * config.max_tile_occurrences < distance_matrix.n_rows() {
+        return Err(SolverError::TooFewColumns {
+            rows: distance_matrix.n_rows(),
+            columns: distance_matrix.n_columns(),
+        });
+    }
+    if let Some(row) = (0..distance_matrix.n_rows())
+        .find(|&row| !pinned_rows[row] && distance_matrix.row_costs(row).next().is_none())
+    {
+        return Err(SolverError::NoCandidateLeft { row });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solvers::test_utils::{distance_matrix, total_cost};
+    use crate::{Auction, DistanceMatrix, Greedy, Hungarian, Lapjv, MinCostFlow};
+
+    fn assert_pinned(assignments: &[usize], config: &SolverConfig, columns: usize) {
+        for &(row, column) in config.pinned.iter() {
+            assert_eq!(assignments[row], column);
+        }
+        let mut counts = vec![0; columns];
+        assignments.iter().for_each(|&column| counts[column] += 1);
+        assert!(counts
+            .iter()
+            .all(|&count| count <= config.max_tile_occurrences));
+    }
+
+    #[test]
+    fn test_dyn_solve() {
+        let d_matrix = distance_matrix(10, 10);
+        let expected = Hungarian::default().solve(&d_matrix).unwrap();
+        let mut solvers: Vec<Box<dyn Solve>> = vec![
+            Box::new(Hungarian::default()),
+            Box::new(Lapjv::default()),
+            Box::new(MinCostFlow::default()),
+        ];
+        for solver in solvers.iter_mut() {
+            let assignments = solver.solve(&d_matrix).unwrap();
+            assert_eq!(
+                total_cost(&d_matrix, &assignments),
+                total_cost(&d_matrix, &expected)
+            );
+            assert!(solver.is_optimal());
+        }
+    }
+
+    #[test]
+    fn test_pinned() {
+        // Tile 3 is pinned to its full capacity, and tile 7 to one of its slots
+        let d_matrix = distance_matrix(30, 20);
+        let config = SolverConfig {
+            max_tile_occurrences: 2,
+            pinned: vec![(0, 3), (29, 3), (12, 7)],
+            ..Default::default()
+        };
+        assert_pinned(
+            &Greedy::new(config.clone()).solve(&d_matrix).unwrap(),
+            &config,
+            20,
+        );
+        assert_pinned(
+            &Hungarian::new(config.clone()).solve(&d_matrix).unwrap(),
+            &config,
+            20,
+        );
+        assert_pinned(
+            &Auction::new(1, config.clone()).solve(&d_matrix).unwrap(),
+            &config,
+            20,
+        );
+        assert_pinned(
+            &Lapjv::new(config.clone()).solve(&d_matrix).unwrap(),
+            &config,
+            20,
+        );
+        assert_pinned(
+            &MinCostFlow::new(config.clone()).solve(&d_matrix).unwrap(),
+            &config,
+            20,
+        );
+    }
+
+    #[test]
+    fn test_min_occurrences() {
+        let d_matrix = distance_matrix(30, 20);
+        let config = SolverConfig {
+            max_tile_occurrences: 2,
+            min_tile_occurrences: 1,
+            pinned: vec![(4, 11)],
+            ..Default::default()
+        };
+        let assert_used = |assignments: &[usize]| {
+            assert_pinned(assignments, &config, 20);
+            assert!((0..20).all(|column| assignments.contains(&column)));
+        };
+
+        let optimal = Hungarian::new(config.clone()).solve(&d_matrix).unwrap();
+        assert_used(&optimal);
+        for assignments in [
+            Lapjv::new(config.clone()).solve(&d_matrix).unwrap(),
+            MinCostFlow::new(config.clone()).solve(&d_matrix).unwrap(),
+        ] {
+            assert_used(&assignments);
+            assert_eq!(
+                total_cost(&d_matrix, &assignments),
+                total_cost(&d_matrix, &optimal)
+            );
+        }
+        for assignments in [
+            Greedy::new(config.clone()).solve(&d_matrix).unwrap(),
+            Auction::new(1, config.clone()).solve(&d_matrix).unwrap(),
+        ] {
+            assert_used(&assignments);
+            assert!(total_cost(&d_matrix, &assignments) >= total_cost(&d_matrix, &optimal));
+        }
+    }
+
+    #[test]
+    fn test_min_occurrences_infeasible() {
+        let d_matrix = distance_matrix(10, 6);
+        for (max_tile_occurrences, min_tile_occurrences, pinned) in [
+            // 6 tiles used twice need more than 10 cells
+            (2, 2, vec![]),
+            (1, 2, vec![]),
+            // Tile 0 pinned 3 times leaves 7 cells for the 5 other tiles used twice
+            (3, 2, vec![(0, 0), (1, 0), (2, 0)]),
+        ] {
+            let config = SolverConfig {
+                max_tile_occurrences,
+                min_tile_occurrences,
+                pinned,
+                ..Default::default()
+            };
+            assert!(check_capacity(&d_matrix, &config).is_err());
+        }
+    }
+
+    #[test]
+    fn test_invalid_pins() {
+        let d_matrix = distance_matrix(4, 4);
+        for pinned in [
+            vec![(4, 0)],
+            vec![(0, 4)],
+            vec![(0, 1), (0, 2)],
+            vec![(0, 1), (1, 1)],
+        ] {
+            let config = SolverConfig {
+                pinned,
+                ..Default::default()
+            };
+            assert!(check_capacity(&d_matrix, &config).is_err());
+        }
+    }
+
+    #[test]
+    fn test_cancelled() {
+        let d_matrix = distance_matrix(30, 20);
+        let config = SolverConfig {
+            max_tile_occurrences: 2,
+            monitor: Monitor::silent(),
+            ..Default::default()
+        };
+        config.monitor.cancellation.cancel();
+
+        let results = [
+            Greedy::new(config.clone()).solve(&d_matrix),
+            Hungarian::new(config.clone()).solve(&d_matrix),
+            Auction::new(1, config.clone()).solve(&d_matrix),
+            Lapjv::new(config.clone()).solve(&d_matrix),
+            MinCostFlow::new(config).solve(&d_matrix),
+        ];
+        for result in results {
+            assert!(matches!(result, Err(PhomoError::Cancelled)));
+        }
+    }
+
+    #[test]
+    fn test_time_budget() {
+        let d_matrix = distance_matrix(30, 20);
+        let config = SolverConfig {
+            max_tile_occurrences: 2,
+            time_budget: Some(Duration::ZERO),
+            ..Default::default()
+        };
+        let mut hungarian = Hungarian::new(config.clone());
+        let mut auction = Auction::new(1, config.clone());
+        let mut lapjv = Lapjv::new(config.clone());
+        let mut min_cost_flow = MinCostFlow::new(config.clone());
+        let results = [
+            (hungarian.solve(&d_matrix), hungarian.is_optimal()),
+            (auction.solve(&d_matrix), auction.is_optimal()),
+            (lapjv.solve(&d_matrix), lapjv.is_optimal()),
+            (min_cost_flow.solve(&d_matrix), min_cost_flow.is_optimal()),
+        ];
+        // The solvers run out of time and fall back to the greedy assignments
+        let expected = Greedy::new(config).solve(&d_matrix).unwrap();
+        for (assignments, optimal) in results {
+            assert_eq!(assignments.unwrap(), expected);
+            assert!(!optimal);
+        }
+
+        let mut hungarian = Hungarian::new(SolverConfig {
+            max_tile_occurrences: 2,
+            time_budget: Some(Duration::from_secs(60)),
+            ..Default::default()
+        });
+        hungarian.solve(&d_matrix).unwrap();
+        assert!(hungarian.is_optimal());
+    }
+
+    #[test]
+    fn test_seed() {
+        // The columns come in pairs of equal costs, each pair can be used in either order
+        let pairs = distance_matrix(12, 8);
+        let d_matrix = DistanceMatrix::new(
+            12,
+            16,
+            (0..12 * 16)
+                .map(|index| pairs.get(index / 16, index % 16 / 2))
+                .collect(),
+        )
+        .unwrap();
+        let solve = |seed: Option<u64>| {
+            let config = SolverConfig {
+                seed,
+                pinned: vec![(3, 5)],
+                ..Default::default()
+            };
+            [
+                Greedy::new(config.clone()).solve(&d_matrix).unwrap(),
+                Hungarian::new(config.clone()).solve(&d_matrix).unwrap(),
+                Auction::new(1, config.clone()).solve(&d_matrix).unwrap(),
+                Lapjv::new(config.clone()).solve(&d_matrix).unwrap(),
+                MinCostFlow::new(config).solve(&d_matrix).unwrap(),
+            ]
+        };
+
+        let unseeded = solve(None);
+        let seeded = solve(Some(1));
+        assert_eq!(seeded, solve(Some(1)));
+        for (assignments, seeded_assignments) in unseeded.iter().zip(seeded.iter()) {
+            assert_eq!(seeded_assignments[3], 5);
+            assert_ne!(assignments, seeded_assignments);
+            assert_eq!(
+                total_cost(&d_matrix, assignments),
+                total_cost(&d_matrix, seeded_assignments)
+            );
+        }
+    }
 }
@@ -0,0 +1,385 @@
+use log::info;
+
+use crate::cost_matrix::Pinned;
+use crate::error::PhomoError;
+use crate::solvers::error::SolverError;
+use crate::solvers::SolverConfig;
+use crate::solvers::{check_capacity, Solve};
+use crate::{CostMatrix, DistanceMatrix};
+
+/// The Spacing struct repairs assignments which repeat a tile within a radius of a cell.
+///
+/// The cells are visited in order, a cell which shares its tile with one of its neighbours is
+/// moved to the cheapest tile which is still available and not used in its neighbourhood. When no
+/// such tile is left, it swaps its tile with the cell which results in the cheapest assignment
+/// without creating new repeats. The pinned cells of the configuration are never moved.
+///
+/// The solvers don't know the positions of the cells, so they don't avoid the repeats themselves,
+/// wrap them in a [`Spaced`] to repair their assignments.
+#[derive(Debug, Clone)]
+pub struct Spacing {
+    config: SolverConfig,
+    radius: u32,
+    grid_width: usize,
+    cell_neighbours: Option<Vec<Vec<usize>>>,
+}
+
+impl Spacing {
+    /// Creates a new instance of the Spacing struct.
+    ///
+    /// # Arguments
+    /// - `config`: The configuration the repaired assignments must respect.
+    /// - `grid_width`: The number of cells in a row of the mosaic grid, the rows of the cost
+    ///   matrix being the cells in row major order.
+    /// - `radius`: The radius, in grid cells, within which a tile can't be repeated. Cells whose
+    ///   horizontal and vertical grid distances are both at most this radius get different
+    ///   tiles. 0 disables the repairs.
+    pub fn new(config: SolverConfig, grid_width: u32, radius: u32) -> Self {
+        Self {
+            config,
+            radius,
+            grid_width: grid_width.max(1) as usize,
+            cell_neighbours: None,
+        }
+    }
+
+    /// Creates a new instance of the Spacing struct, for cells which don't form a uniform grid.
+    ///
+    /// # Arguments
+    /// - `config`: The configuration the repaired assignments must respect.
+    /// - `radius`: The radius within which a tile can't be repeated, 0 disables the repairs.
+    /// - `cell_neighbours`: The cells within the `radius` of each cell, see
+    ///   [`Master::neighbours`](crate::Master::neighbours).
+    pub fn with_neighbours(
+        config: SolverConfig,
+        radius: u32,
+        cell_neighbours: Vec<Vec<usize>>,
+    ) -> Self {
+        Self {
+            config,
+            radius,
+            grid_width: 1,
+            cell_neighbours: Some(cell_neighbours),
+        }
+    }
+
+    /// The cells within the repeat radius of the `row`, excluding the `row` itself.
+    fn neighbours(&self, row: usize, n_rows: usize) -> impl Iterator<Item = usize> + '_ {
+        let listed = self
+            .cell_neighbours
+            .as_ref()
+            .map(|cell_neighbours| cell_neighbours.get(row).into_iter().flatten().copied());
+        let radius = self.radius as usize;
+        let grid_height = n_rows.div_ceil(self.grid_width);
+        let (x, y) = (row % self.grid_width, row / self.grid_width);
+        let xs = x.saturating_sub(radius)..(x + radius + 1).min(self.grid_width);
+        let grid = (y.saturating_sub(radius)..(y + radius + 1).min(grid_height))
+            .flat_map(move |ny| xs.clone().map(move |nx| ny * self.grid_width + nx))
+            .filter(move |&other_row| other_row != row && other_row < n_rows);
+        // Both iterators are chained to unify their types, only one of them yields cells
+        let grid = listed.is_none().then_some(grid).into_iter().flatten();
+        listed
+            .into_iter()
+            .flatten()
+            .filter(move |&other_row| other_row != row && other_row < n_rows)
+            .chain(grid)
+    }
+
+    /// The tiles assigned to the cells within the repeat radius of the `row`.
+    fn neighbour_tiles(&self, row: usize, assignments: &[usize]) -> Vec<usize> {
+        self.neighbours(row, assignments.len())
+            .map(|other_row| assignments[other_row])
+            .collect()
+    }
+
+    /// Repair the `assignments` of the `distance_matrix`, so that no tile is repeated within the
+    /// repeat radius of a cell.
+    ///
+    /// # Arguments
+    /// - `distance_matrix`: The cost matrix of the assignments.
+    /// - `assignments`: The tile index of each cell, as returned by a [`Solve`] implementation.
+    ///
+    /// # Errors
+    /// - [`PhomoError::SolverError`]: The `assignments` don't match the `distance_matrix`, don't
+    ///   respect the configuration, or a repeat could not be removed. Two pinned cells within the
+    ///   radius of each other which share a tile give a [`SolverError::RepeatWithinRadius`].
+    pub fn repair<C: CostMatrix>(
+        &self,
+        distance_matrix: &C,
+        mut assignments: Vec<usize>,
+    ) -> Result<Vec<usize>, PhomoError> {
+        // The pinned rows only have their pinned column as candidate, so they are never moved
+        let distance_matrix = &Pinned::new(distance_matrix, &self.config);
+        let n_rows = distance_matrix.n_rows();
+        let n_columns = distance_matrix.n_columns();
+        let capacity = self.config.max_tile_occurrences;
+        if assignments.len() != n_rows {
+            return Err(SolverError::InvalidAssignment {
+                row: assignments.len().min(n_rows),
+            }
+            .into());
+        }
+
+        let mut counts = vec![0; n_columns];
+        for (row, &column) in assignments.iter().enumerate() {
+            if column >= n_columns || distance_matrix.cost(row, column).is_none() {
+                return Err(SolverError::InvalidAssignment { row }.into());
+            }
+            counts[column] += 1;
+            if counts[column] > capacity {
+                return Err(SolverError::InvalidAssignment { row }.into());
+            }
+        }
+        if self.radius == 0 {
+            return Ok(assignments);
+        }
+        let pins = self.config.pinned_columns(n_rows);
+        // The repeats between pinned cells can't be repaired
+        for (row, pin) in pins.iter().enumerate() {
+            if let Some(column) = pin {
+                if self
+                    .neighbours(row, n_rows)
+                    .any(|other_row| pins[other_row] == Some(*column))
+                {
+                    return Err(SolverError::RepeatWithinRadius {
+                        row,
+                        radius: self.radius,
+                    }
+                    .into());
+                }
+            }
+        }
+
+        let mut n_repairs = 0;
+        // The unpinned neighbours of a pinned cell move away from its tile
+        for row in (0..n_rows).filter(|&row| pins[row].is_none()) {
+            self.config.monitor.check()?;
+            let column = assignments[row];
+            let neighbour_tiles = self.neighbour_tiles(row, &assignments);
+            if !neighbour_tiles.contains(&column) {
+                continue;
+            }
+            n_repairs += 1;
+
+            // Move to the cheapest available tile which isn't in the neighbourhood, unless the
+            // current tile would be used less than its minimum
+            if let Some((new_column, _)) = distance_matrix
+                .row_costs(row)
+                .filter(|&(new_column, _)| {
+                    counts[column] > self.config.min_tile_occurrences
+                        && counts[new_column] < capacity
+                        && !neighbour_tiles.contains(&new_column)
+                })
+                .min_by_key(|&(_, cost)| cost)
+            {
+                counts[column] -= 1;
+                counts[new_column] += 1;
+                assignments[row] = new_column;
+                continue;
+            }
+
+            // Otherwise swap tiles with a cell outside of the neighbourhood
+            let mut best_swap = None;
+            let mut best_cost = i64::MAX;
+            for other_row in 0..n_rows {
+                let other_column = assignments[other_row];
+                if other_column == column
+                    || neighbour_tiles.contains(&other_column)
+                    || self.neighbours(row, n_rows).any(|r| r == other_row)
+                {
+                    continue;
+                }
+                let (Some(cost), Some(other_cost)) = (
+                    distance_matrix.cost(row, other_column),
+                    distance_matrix.cost(other_row, column),
+                ) else {
+                    continue;
+                };
+                if cost + other_cost >= best_cost
+                    || self
+                        .neighbours(other_row, n_rows)
+                        .any(|r| assignments[r] == column)
+                {
+                    continue;
+                }
+                best_cost = cost + other_cost;
+                best_swap = Some(other_row);
+            }
+            let other_row = best_swap.ok_or(SolverError::RepeatWithinRadius {
+                row,
+                radius: self.radius,
+            })?;
+            assignments.swap(row, other_row);
+        }
+
+        info!(
+            "Spacing: repaired {} repeats within a radius of {}",
+            n_repairs, self.radius
+        );
+        Ok(assignments)
+    }
+}
+
+/// The Spaced struct wraps a solver, and repairs its assignments with a [`Spacing`] so that no
+/// tile is repeated within the radius of the spacing.
+#[derive(Debug)]
+pub struct Spaced<S: Solve> {
+    solver: S,
+    spacing: Spacing,
+    repaired: bool,
+}
+
+impl<S: Solve> Spaced<S> {
+    /// Creates a new instance of the Spaced struct.
+    ///
+    /// # Arguments
+    /// - `solver`: The solver computing the initial assignments.
+    /// - `spacing`: The spacing repairing the assignments.
+    pub fn new(solver: S, spacing: Spacing) -> Self {
+        Self {
+            solver,
+            spacing,
+            repaired: false,
+        }
+    }
+}
+
+impl<S: Solve> Solve for Spaced<S> {
+    fn solve(&mut self, distance_matrix: &DistanceMatrix) -> Result<Vec<usize>, PhomoError> {
+        self.solve_cost_matrix(distance_matrix)
+    }
+
+    fn solve_cost_matrix<C: CostMatrix>(
+        &mut self,
+        distance_matrix: &C,
+    ) -> Result<Vec<usize>, PhomoError> {
+        check_capacity(distance_matrix, &self.spacing.config)?;
+        let assignments = self.solver.solve_cost_matrix(distance_matrix)?;
+        let repaired = self.spacing.repair(distance_matrix, assignments.clone())?;
+        self.repaired = repaired != assignments;
+        Ok(repaired)
+    }
+
+    /// The iterations of the wrapped solver.
+    fn iterations(&self) -> Option<usize> {
+        self.solver.iterations()
+    }
+
+    /// The assignments are optimal when those of the wrapped solver are, and had no repeat to
+    /// repair.
+    fn is_optimal(&self) -> bool {
+        self.solver.is_optimal() && !self.repaired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solvers::test_utils::distance_matrix;
+    use crate::{DistanceMatrix, Hungarian};
+
+    fn assert_spaced(assignments: &[usize], grid_width: usize, radius: usize) {
+        for (row, &column) in assignments.iter().enumerate() {
+            for (other_row, &other_column) in assignments.iter().enumerate().skip(row + 1) {
+                let dx = (row % grid_width).abs_diff(other_row % grid_width);
+                let dy = (row / grid_width).abs_diff(other_row / grid_width);
+                assert!(
+                    column != other_column || dx.max(dy) > radius,
+                    "Tile {column} repeated at rows {row} and {other_row}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_repair() {
+        // 8x8 grid, with enough tiles to fill the neighbourhood of a cell
+        for (repeat_radius, columns, max_tile_occurrences) in [(1, 16, 4), (2, 40, 2)] {
+            let d_matrix = distance_matrix(64, columns);
+            let config = SolverConfig {
+                max_tile_occurrences,
+                ..Default::default()
+            };
+            let spacing = Spacing::new(config.clone(), 8, repeat_radius);
+            let assignments = Spaced::new(Hungarian::new(config), spacing)
+                .solve(&d_matrix)
+                .unwrap();
+
+            let mut counts = vec![0; columns];
+            assignments.iter().for_each(|&column| counts[column] += 1);
+            assert!(counts.iter().all(|&count| count <= max_tile_occurrences));
+            assert_spaced(&assignments, 8, repeat_radius as usize);
+        }
+    }
+
+    #[test]
+    fn test_repair_swap() {
+        // Both tiles are used to capacity, the only fix is to swap tiles between cells
+        let d_matrix = DistanceMatrix::new(4, 2, vec![0, 9, 0, 9, 9, 0, 9, 0]).unwrap();
+        let config = SolverConfig {
+            max_tile_occurrences: 2,
+            ..Default::default()
+        };
+        let assignments = Spacing::new(config, 4, 1)
+            .repair(&d_matrix, vec![0, 0, 1, 1])
+            .unwrap();
+        assert_spaced(&assignments, 4, 1);
+    }
+
+    #[test]
+    fn test_repair_with_neighbours() {
+        // A row of 6 cells, each neighbouring the previous and the next cell
+        let d_matrix = distance_matrix(6, 3);
+        let cell_neighbours = (0..6)
+            .map(|row: usize| {
+                (row.saturating_sub(1)..(row + 2).min(6))
+                    .filter(|&other_row| other_row != row)
+                    .collect()
+            })
+            .collect();
+        let config = SolverConfig {
+            max_tile_occurrences: 2,
+            ..Default::default()
+        };
+        let assignments = Spacing::with_neighbours(config, 1, cell_neighbours)
+            .repair(&d_matrix, vec![0, 0, 1, 1, 2, 2])
+            .unwrap();
+        for row in 0..6 {
+            for other_row in row + 1..(row + 2).min(6) {
+                assert_ne!(assignments[row], assignments[other_row]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_repair_infeasible() {
+        // A 2x2 grid where every cell neighbours the others, with only 2 tiles
+        let d_matrix = distance_matrix(4, 2);
+        let config = SolverConfig {
+            max_tile_occurrences: 2,
+            ..Default::default()
+        };
+        assert!(Spacing::new(config, 2, 1)
+            .repair(&d_matrix, vec![0, 0, 1, 1])
+            .is_err());
+    }
+
+    #[test]
+    fn test_repair_pinned_neighbours() {
+        // The two first cells of the row are pinned to the same tile
+        let d_matrix = distance_matrix(4, 4);
+        let config = SolverConfig {
+            max_tile_occurrences: 2,
+            pinned: vec![(0, 1), (1, 1)],
+            ..Default::default()
+        };
+        let result = Spacing::new(config, 4, 1).repair(&d_matrix, vec![1, 1, 0, 2]);
+        assert!(matches!(
+            result,
+            Err(PhomoError::SolverError(SolverError::RepeatWithinRadius {
+                row: 0,
+                radius: 1
+            }))
+        ));
+    }
+}
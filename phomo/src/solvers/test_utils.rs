@@ -0,0 +1,27 @@
+//! Fixtures shared by the tests of the solvers.
+use crate::{CostMatrix, DistanceMatrix};
+
+/// Deterministic pseudo random costs, below `max`.
+pub(crate) fn random_costs(n: usize, max: u64) -> Vec<i64> {
+    let mut seed: u64 = 42;
+    (0..n)
+        .map(|_| {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+            ((seed >> 33) % max) as i64
+        })
+        .collect()
+}
+
+/// Deterministic pseudo random distance matrix, with costs below 1000.
+pub(crate) fn distance_matrix(rows: usize, columns: usize) -> DistanceMatrix {
+    DistanceMatrix::new(rows, columns, random_costs(rows * columns, 1000)).unwrap()
+}
+
+/// The total cost of the `assignments` of the `distance_matrix`.
+pub(crate) fn total_cost<C: CostMatrix>(distance_matrix: &C, assignments: &[usize]) -> i64 {
+    assignments
+        .iter()
+        .enumerate()
+        .map(|(row, &column)| distance_matrix.cost(row, column).unwrap())
+        .sum()
+}
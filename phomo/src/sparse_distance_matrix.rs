@@ -0,0 +1,154 @@
+use crate::cost_matrix::CostMatrix;
+use crate::distance_matrix::solve_timed;
+use crate::error::{DistanceMatrixError, PhomoError};
+use crate::solve_report::{solve_report, SolveReport};
+use crate::solvers::Solve;
+
+/// A sparse distance matrix, which only stores the costs of some candidate tiles of each master
+/// cell, in the compressed sparse row (CSR) format.
+///
+/// The row index is the cell index and the column index is the tile index. Tiles which are not
+/// candidates of a cell can't be assigned to it.
+#[derive(Debug, Clone, Eq, Hash, PartialEq)]
+pub struct SparseDistanceMatrix {
+    pub rows: usize,
+    pub columns: usize,
+    /// The candidates of row `i` are stored in `row_offsets[i]..row_offsets[i + 1]`.
+    row_offsets: Vec<usize>,
+    column_indices: Vec<usize>,
+    data: Vec<i64>,
+}
+
+impl SparseDistanceMatrix {
+    /// Create a new [`SparseDistanceMatrix`] from the candidate columns of each row.
+    ///
+    /// # Arguments
+    /// - `rows`: The number of rows.
+    /// - `columns`: The number of columns.
+    /// - `candidates`: For each row, the candidate column indices and their costs.
+    ///
+    /// # Errors
+    /// - [`PhomoError::DistanceMatrixError`]: The number of candidate rows does not match `rows`,
+    ///   or a candidate column is out of bounds.
+    pub fn new(
+        rows: usize,
+        columns: usize,
+        candidates: Vec<Vec<(usize, i64)>>,
+    ) -> Result<Self, PhomoError> {
+        if rows == 0 {
+            return Err(DistanceMatrixError::EmptyRow.into());
+        }
+        if columns == 0 {
+            return Err(DistanceMatrixError::EmptyCol.into());
+        }
+        if candidates.len() != rows {
+            return Err(DistanceMatrixError::WrongLength.into());
+        }
+
+        let mut row_offsets = Vec::with_capacity(rows + 1);
+        row_offsets.push(0);
+        let mut column_indices = Vec::new();
+        let mut data = Vec::new();
+        for (row, mut row_candidates) in candidates.into_iter().enumerate() {
+            row_candidates.sort_unstable_by_key(|&(column, _)| column);
+            row_candidates.dedup_by_key(|&mut (column, _)| column);
+            for (column, cost) in row_candidates {
+                if column >= columns {
+                    return Err(DistanceMatrixError::ColumnOutOfBounds { row, column }.into());
+                }
+                column_indices.push(column);
+                data.push(cost);
+            }
+            row_offsets.push(column_indices.len());
+        }
+
+        Ok(Self {
+            rows,
+            columns,
+            row_offsets,
+            column_indices,
+            data,
+        })
+    }
+
+    /// The number of stored costs.
+    pub fn n_candidates(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Solve the linear sum assignment problem using the provided `solver`.
+    ///
+    /// # Arguments
+    /// - `solver`: The solver to use to solve the assignment problem. See [`phomo::solvers`](crate::solvers)
+    ///   for structs which implement this trait.
+    ///
+    /// # Errors
+    /// - [`PhomoError::SolverError`]: An error occurred while solving the assignment problem.
+    pub fn assignments<S: Solve>(&self, solver: &mut S) -> Result<Vec<usize>, PhomoError> {
+        solve_timed(self, solver)
+    }
+
+    /// Solve the linear sum assignment problem using the provided `solver`, and report the cost
+    /// of the assignments, the usage of the tiles, and the time taken by the solver.
+    ///
+    /// # Arguments
+    /// - `solver`: The solver to use to solve the assignment problem. See [`phomo::solvers`](crate::solvers)
+    ///   for structs which implement this trait.
+    ///
+    /// # Errors
+    /// - [`PhomoError::SolverError`]: An error occurred while solving the assignment problem.
+    pub fn report<S: Solve>(&self, solver: &mut S) -> Result<SolveReport, PhomoError> {
+        solve_report(self, solver)
+    }
+}
+
+impl CostMatrix for SparseDistanceMatrix {
+    fn n_rows(&self) -> usize {
+        self.rows
+    }
+
+    fn n_columns(&self) -> usize {
+        self.columns
+    }
+
+    fn cost(&self, row: usize, column: usize) -> Option<i64> {
+        let range = self.row_offsets[row]..self.row_offsets[row + 1];
+        self.column_indices[range.clone()]
+            .binary_search(&column)
+            .ok()
+            .map(|i| self.data[range.start + i])
+    }
+
+    fn row_costs(&self, row: usize) -> impl Iterator<Item = (usize, i64)> + '_ {
+        let range = self.row_offsets[row]..self.row_offsets[row + 1];
+        self.column_indices[range.clone()]
+            .iter()
+            .copied()
+            .zip(self.data[range].iter().copied())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new() {
+        let d_matrix =
+            SparseDistanceMatrix::new(2, 3, vec![vec![(2, 3), (0, 1)], vec![(1, 5)]]).unwrap();
+        assert_eq!(d_matrix.n_candidates(), 3);
+        assert_eq!(d_matrix.cost(0, 0), Some(1));
+        assert_eq!(d_matrix.cost(0, 1), None);
+        assert_eq!(d_matrix.cost(1, 1), Some(5));
+        let row = d_matrix.row_costs(0).collect::<Vec<_>>();
+        assert_eq!(row, vec![(0, 1), (2, 3)]);
+    }
+
+    #[test]
+    fn test_new_bad_size() {
+        let d_matrix = SparseDistanceMatrix::new(2, 3, vec![vec![(0, 1)]]);
+        assert!(d_matrix.is_err());
+        let d_matrix = SparseDistanceMatrix::new(1, 3, vec![vec![(3, 1)]]);
+        assert!(d_matrix.is_err());
+    }
+}
@@ -1,10 +1,11 @@
+use std::collections::HashMap;
 use std::path::Path;
 
 extern crate image;
-use image::{GenericImageView, Pixel, RgbImage, SubImage};
+use image::{GenericImageView, ImageBuffer, Pixel, RgbImage, SubImage};
 use log::warn;
 
-use crate::error::PhomoError;
+use crate::error::{MosaicError, PhomoError};
 
 /// Helper function to crop am image to a width and height centered on the image.
 ///
@@ -28,6 +29,40 @@ where
     )
 }
 
+/// Helper function to pad an image to a width and height, with the image centered in the
+/// padded image.
+///
+/// # Arguments
+/// - `img`: The image to pad.
+/// - `width`: The width to pad to.
+/// - `height`: The height to pad to.
+/// - `fill`: The pixel value of the padding, or `None` to extend the edge pixels of the image.
+pub fn pad_centered<P>(
+    img: &ImageBuffer<P, Vec<P::Subpixel>>,
+    width: u32,
+    height: u32,
+    fill: Option<P>,
+) -> ImageBuffer<P, Vec<P::Subpixel>>
+where
+    P: Pixel,
+{
+    if width < img.width() || height < img.height() {
+        warn!("Attempted to pad image to a smaller size than the source image");
+    }
+    let offset_x = (width as i64 - img.width() as i64) / 2;
+    let offset_y = (height as i64 - img.height() as i64) / 2;
+    let clamp = |value: i64, size: u32| value.clamp(0, size as i64 - 1) as u32;
+    ImageBuffer::from_fn(width, height, |x, y| {
+        let (img_x, img_y) = (x as i64 - offset_x, y as i64 - offset_y);
+        let inside =
+            (0..img.width() as i64).contains(&img_x) && (0..img.height() as i64).contains(&img_y);
+        match fill {
+            Some(fill) if !inside => fill,
+            _ => *img.get_pixel(clamp(img_x, img.width()), clamp(img_y, img.height())),
+        }
+    })
+}
+
 /// Helper function to crop an image, preserving aspect ratio, to a width and height.
 ///
 /// # Arguments
@@ -72,7 +107,8 @@ where
     crop_imm_centered(&resized_img, width, height).to_image()
 }
 
-/// Read all images in a directory and returns them in a vector.
+/// Read all images in a directory and returns them in a vector, sorted by file name so that the
+/// tiles are in the same order on every platform.
 ///
 /// # Arguments
 /// - `tile_dir`: The path to the directory containing the tile images.
@@ -81,22 +117,51 @@ where
 /// - An error occurred while reading the directory.
 /// - Failed to open the image.
 pub fn read_images_from_dir<P: AsRef<Path>>(tile_dir: P) -> Result<Vec<RgbImage>, PhomoError> {
-    Ok(tile_dir
+    Ok(read_named_images_from_dir(tile_dir)?
+        .into_iter()
+        .map(|(_, img)| img)
+        .collect::<Vec<_>>())
+}
+
+/// Read all images in a directory and returns them in a vector along with their file names,
+/// sorted by file name like [`read_images_from_dir`]. The files which aren't images are skipped,
+/// so the names match the images, for example to build the
+/// [tile weights](crate::Mosaic::set_tile_weights) from [`read_tile_weights`].
+///
+/// # Arguments
+/// - `tile_dir`: The path to the directory containing the tile images.
+///
+/// # Errors
+/// - An error occurred while reading the directory.
+/// - Failed to open the image.
+pub fn read_named_images_from_dir<P: AsRef<Path>>(
+    tile_dir: P,
+) -> Result<Vec<(String, RgbImage)>, PhomoError> {
+    let mut paths = tile_dir
         .as_ref()
         .read_dir()?
         .filter_map(|entry| match entry {
-            Ok(p) => match image::open(p.path()) {
-                Ok(img) => Some(img.to_rgb8()),
-                Err(e) => {
-                    warn!("Failed to open image at path {:?}: {:?}", p.path(), e);
-                    None
-                }
-            },
+            Ok(entry) => Some(entry.path()),
             Err(e) => {
                 warn!("Failed to read directory entry: {:?}", e);
                 None
             }
         })
+        .collect::<Vec<_>>();
+    paths.sort_unstable_by(|a, b| a.file_name().cmp(&b.file_name()));
+
+    Ok(paths
+        .into_iter()
+        .filter_map(|path| match image::open(&path) {
+            Ok(img) => {
+                let name = path.file_name()?.to_string_lossy().into_owned();
+                Some((name, img.to_rgb8()))
+            }
+            Err(e) => {
+                warn!("Failed to open image at path {:?}: {:?}", path, e);
+                None
+            }
+        })
         .collect::<Vec<_>>())
 }
 
@@ -137,6 +202,49 @@ pub fn read_images_from_dir_resized<P: AsRef<Path>>(
         .collect::<Vec<_>>())
 }
 
+/// Read the tile weights from a CSV file mapping tile file names to weights, see
+/// [`Mosaic::set_tile_weights`](crate::Mosaic::set_tile_weights).
+///
+/// Each line holds a file name and an integer weight separated by a comma, for example
+/// `beach.jpg,-500`. Empty lines are ignored, and so is the first line when it is a header.
+///
+/// # Arguments
+/// - `weights_file`: The path to the CSV file.
+///
+/// # Errors
+/// - [`PhomoError::IoError`]: An error occurred while reading the file.
+/// - [`PhomoError::MosaicError`]: A line isn't a file name followed by an integer weight.
+pub fn read_tile_weights<P: AsRef<Path>>(
+    weights_file: P,
+) -> Result<HashMap<String, i64>, PhomoError> {
+    let content = std::fs::read_to_string(weights_file)?;
+    let mut weights = HashMap::new();
+    for (line_idx, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let weight = line.rsplit_once(',').and_then(|(file_name, weight)| {
+            let file_name = file_name.trim().trim_matches('"');
+            Some((file_name.to_string(), weight.trim().parse::<i64>().ok()?))
+        });
+        match weight {
+            Some((file_name, weight)) => {
+                weights.insert(file_name, weight);
+            }
+            // The header
+            None if line_idx == 0 => continue,
+            None => {
+                return Err(MosaicError::InvalidTileWeight {
+                    line: line_idx + 1,
+                    content: line.to_string(),
+                }
+                .into())
+            }
+        }
+    }
+    Ok(weights)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -176,6 +284,25 @@ mod tests {
         assert_eq!(cropped.dimensions(), (master_dim.0 * 2, master_dim.1 * 2));
     }
 
+    #[test]
+    fn test_pad_centered() {
+        let mut img = image::RgbImage::from_pixel(3, 3, image::Rgb([255, 255, 255]));
+        img.put_pixel(0, 1, image::Rgb([0, 0, 255]));
+
+        let padded = pad_centered(&img, 6, 5, Some(image::Rgb([255, 0, 0])));
+        assert_eq!(padded.dimensions(), (6, 5));
+        assert_eq!(padded.get_pixel(0, 0), &image::Rgb([255, 0, 0]));
+        assert_eq!(padded.get_pixel(5, 2), &image::Rgb([255, 0, 0]));
+        assert_eq!(padded.get_pixel(1, 2), &image::Rgb([0, 0, 255]));
+        assert_eq!(padded.get_pixel(3, 3), &image::Rgb([255, 255, 255]));
+
+        // The edge pixels are extended
+        let padded = pad_centered(&img, 6, 5, None);
+        assert_eq!(padded.get_pixel(0, 2), &image::Rgb([0, 0, 255]));
+        assert_eq!(padded.get_pixel(0, 0), &image::Rgb([255, 255, 255]));
+        assert_eq!(padded.get_pixel(5, 4), &image::Rgb([255, 255, 255]));
+    }
+
     #[test]
     fn test_crop_imm_centered() {
         // create white image with a black pixel centered on the image
@@ -198,6 +325,30 @@ mod tests {
         assert!(images.iter().all(|img| img.dimensions() == (64, 64)));
     }
 
+    #[test]
+    fn test_read_named_images_from_dir() {
+        let tile_dir = std::env::temp_dir().join("phomo_test_named_images");
+        std::fs::create_dir_all(&tile_dir).unwrap();
+        RgbImage::from_pixel(2, 2, image::Rgb([255, 0, 0]))
+            .save(tile_dir.join("b.png"))
+            .unwrap();
+        RgbImage::from_pixel(2, 2, image::Rgb([0, 0, 255]))
+            .save(tile_dir.join("c.png"))
+            .unwrap();
+        // The files which aren't images are skipped without shifting the names
+        std::fs::write(tile_dir.join("a.png"), "not an image").unwrap();
+
+        let images = read_named_images_from_dir(&tile_dir).unwrap();
+        let names = images
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["b.png", "c.png"]);
+        assert_eq!(images[0].1.get_pixel(0, 0).0, [255, 0, 0]);
+        assert_eq!(images[1].1.get_pixel(0, 0).0, [0, 0, 255]);
+        std::fs::remove_dir_all(tile_dir).unwrap();
+    }
+
     #[test]
     fn test_read_images_from_dir_cropped() {
         let (width, height) = (32, 32);
@@ -219,4 +370,22 @@ mod tests {
         assert_eq!(images.len(), 16);
         assert!(images.iter().all(|img| img.dimensions() == (width, height)));
     }
+
+    #[test]
+    fn test_read_tile_weights() {
+        let weights_file = std::env::temp_dir().join("phomo_test_tile_weights.csv");
+        std::fs::write(
+            &weights_file,
+            "file,weight\n\"a, b.png\",-10\n\nc.jpg, 25\n",
+        )
+        .unwrap();
+        let weights = read_tile_weights(&weights_file).unwrap();
+        assert_eq!(weights.len(), 2);
+        assert_eq!(weights["a, b.png"], -10);
+        assert_eq!(weights["c.jpg"], 25);
+
+        std::fs::write(&weights_file, "a.png,-10\nc.jpg,heavy\n").unwrap();
+        assert!(read_tile_weights(&weights_file).is_err());
+        std::fs::remove_file(weights_file).unwrap();
+    }
 }
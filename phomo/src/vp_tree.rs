@@ -0,0 +1,181 @@
+use std::collections::BinaryHeap;
+
+/// A vantage point tree, to search the nearest neighbours of a query in a metric space.
+///
+/// The tree only stores the indices of the items, the distances are computed by the closures
+/// passed to [`VpTree::new`] and [`VpTree::nearest`]. The search is exact as long as the distance
+/// satisfies the triangle inequality.
+///
+/// See:
+///     <https://dl.acm.org/doi/10.5555/313559.313789>
+#[derive(Debug)]
+pub(crate) struct VpTree {
+    nodes: Vec<Node>,
+    root: Option<usize>,
+}
+
+#[derive(Debug)]
+struct Node {
+    /// The index of the vantage point item.
+    item: usize,
+    /// The median distance of the items of the subtrees to the vantage point.
+    threshold: i64,
+    /// The subtree of the items closer than the threshold.
+    inner: Option<usize>,
+    /// The subtree of the items further than the threshold.
+    outer: Option<usize>,
+}
+
+impl VpTree {
+    /// Build the tree over the items `0..n_items`.
+    ///
+    /// # Arguments
+    /// - `n_items`: The number of items.
+    /// - `distance`: Computes the distance between two items.
+    pub(crate) fn new<D: Fn(usize, usize) -> i64>(n_items: usize, distance: D) -> Self {
+        let mut tree = Self {
+            nodes: Vec::with_capacity(n_items),
+            root: None,
+        };
+        let mut items = (0..n_items).collect::<Vec<_>>();
+        tree.root = tree.build(&mut items, &distance);
+        tree
+    }
+
+    fn build<D: Fn(usize, usize) -> i64>(
+        &mut self,
+        items: &mut [usize],
+        distance: &D,
+    ) -> Option<usize> {
+        let (&mut vantage_point, rest) = items.split_first_mut()?;
+        let node_index = self.nodes.len();
+        self.nodes.push(Node {
+            item: vantage_point,
+            threshold: 0,
+            inner: None,
+            outer: None,
+        });
+        if rest.is_empty() {
+            return Some(node_index);
+        }
+
+        let mut distances = rest
+            .iter()
+            .map(|&item| (distance(vantage_point, item), item))
+            .collect::<Vec<_>>();
+        let median = distances.len() / 2;
+        distances.select_nth_unstable(median);
+        let threshold = distances[median].0;
+        for (slot, (_, item)) in rest.iter_mut().zip(distances) {
+            *slot = item;
+        }
+
+        // the items up to and including the median are within the threshold
+        let (inner_items, outer_items) = rest.split_at_mut(median + 1);
+        let inner = self.build(inner_items, distance);
+        let outer = self.build(outer_items, distance);
+        let node = &mut self.nodes[node_index];
+        node.threshold = threshold;
+        node.inner = inner;
+        node.outer = outer;
+        Some(node_index)
+    }
+
+    /// Find the `k` nearest items of a query.
+    ///
+    /// # Arguments
+    /// - `k`: The number of neighbours to find.
+    /// - `distance`: Computes the distance between an item and the query.
+    ///
+    /// # Returns
+    /// The item indices and their distances to the query, sorted by increasing distance.
+    pub(crate) fn nearest<D: Fn(usize) -> i64>(&self, k: usize, distance: D) -> Vec<(usize, i64)> {
+        let mut heap = BinaryHeap::with_capacity(k + 1);
+        if k > 0 {
+            self.search(self.root, k, &distance, &mut heap);
+        }
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|(dist, item)| (item, dist))
+            .collect()
+    }
+
+    fn search<D: Fn(usize) -> i64>(
+        &self,
+        node_index: Option<usize>,
+        k: usize,
+        distance: &D,
+        heap: &mut BinaryHeap<(i64, usize)>,
+    ) {
+        let Some(node) = node_index.map(|i| &self.nodes[i]) else {
+            return;
+        };
+
+        let dist = distance(node.item);
+        if heap.len() < k {
+            heap.push((dist, node.item));
+        } else if heap.peek().is_some_and(|&worst| (dist, node.item) < worst) {
+            heap.pop();
+            heap.push((dist, node.item));
+        }
+
+        // The distance to the current k-th nearest neighbour
+        let tau = |heap: &BinaryHeap<(i64, usize)>| {
+            if heap.len() < k {
+                i64::MAX
+            } else {
+                heap.peek().map_or(i64::MAX, |&(d, _)| d)
+            }
+        };
+
+        if dist <= node.threshold {
+            self.search(node.inner, k, distance, heap);
+            if dist.saturating_add(tau(heap)) >= node.threshold {
+                self.search(node.outer, k, distance, heap);
+            }
+        } else {
+            self.search(node.outer, k, distance, heap);
+            if dist.saturating_sub(tau(heap)) <= node.threshold {
+                self.search(node.inner, k, distance, heap);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nearest() {
+        let points: Vec<i64> = vec![10, 3, 7, 25, 1, 14, 8, 30, 2, 19];
+        let tree = VpTree::new(points.len(), |a, b| (points[a] - points[b]).abs());
+
+        for query in [0, 5, 9, 16, 40] {
+            let mut expected = points
+                .iter()
+                .enumerate()
+                .map(|(i, p)| (i, (p - query).abs()))
+                .collect::<Vec<_>>();
+            expected.sort_by_key(|&(i, d)| (d, i));
+            expected.truncate(3);
+
+            let nearest = tree.nearest(3, |i| (points[i] - query).abs());
+            assert_eq!(nearest, expected);
+        }
+    }
+
+    #[test]
+    fn test_nearest_more_than_items() {
+        let points: Vec<i64> = vec![4, 2];
+        let tree = VpTree::new(points.len(), |a, b| (points[a] - points[b]).abs());
+        let nearest = tree.nearest(5, |i| (points[i] - 3).abs());
+        assert_eq!(nearest, vec![(0, 1), (1, 1)]);
+    }
+
+    #[test]
+    fn test_empty() {
+        let tree = VpTree::new(0, |_, _| 0);
+        assert!(tree.nearest(3, |_| 0).is_empty());
+    }
+}
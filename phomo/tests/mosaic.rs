@@ -6,13 +6,15 @@ extern crate image;
 use image::RgbImage;
 
 use phomo::read_images_from_dir_resized;
-use phomo::solvers::auction::Auction;
+use phomo::solvers::auction::{Auction, Bidding};
 #[cfg(feature = "blueprint")]
 use phomo::Blueprint;
 use phomo::ColorMatch;
 use phomo::Greedy;
 use phomo::Mosaic;
 use phomo::SolverConfig;
+#[cfg(feature = "blueprint")]
+use phomo::{Layout, Master, Shape, Voronoi};
 
 fn test_dir() -> PathBuf {
     PathBuf::from(env!("CARGO_MANIFEST_DIR"))
@@ -73,6 +75,7 @@ fn build_mosaic() {
             d_matrix,
             SolverConfig {
                 max_tile_occurrences: 1,
+                ..Default::default()
             },
         )
         .unwrap();
@@ -100,6 +103,7 @@ fn build_mosaic_greedy() {
             d_matrix,
             Greedy::new(SolverConfig {
                 max_tile_occurrences: 1,
+                ..Default::default()
             }),
         )
         .unwrap();
@@ -129,6 +133,7 @@ fn build_mosaic_auction() {
                 1,
                 SolverConfig {
                     max_tile_occurrences: 1,
+                    ..Default::default()
                 },
             ),
         )
@@ -138,6 +143,42 @@ fn build_mosaic_auction() {
     assert!(kinda_same_imgs(mosaic_img, expected, 2.));
 }
 
+#[test]
+fn build_mosaic_auction_scaling() {
+    let (tile_imgs, master_img) = setup_imgs();
+
+    let result = Mosaic::from_images(master_img, tile_imgs, (16, 16));
+    assert!(result.is_ok());
+    let mosaic = result.unwrap();
+
+    let d_matrix = mosaic.distance_matrix();
+    assert_eq!(
+        d_matrix.data.len(),
+        mosaic.master.cells.len() * mosaic.tiles.len()
+    );
+
+    let mosaic_img = mosaic
+        .build_with_solver(
+            d_matrix,
+            Auction::with_epsilon_scaling(
+                1,
+                4,
+                Bidding::GaussSeidel,
+                SolverConfig {
+                    max_tile_occurrences: 1,
+                    ..Default::default()
+                },
+            ),
+        )
+        .unwrap();
+    assert_eq!(mosaic_img.dimensions(), mosaic.master.img.dimensions());
+    let expected = open_expected(
+        &mosaic_img,
+        test_dir().join("mosaic_16_16_auction_scaling.png"),
+    );
+    assert!(kinda_same_imgs(mosaic_img, expected, 2.));
+}
+
 #[test]
 fn build_mosaic_auction_repeats() {
     let (tile_imgs, master_img) = setup_imgs();
@@ -159,6 +200,7 @@ fn build_mosaic_auction_repeats() {
                 1,
                 SolverConfig {
                     max_tile_occurrences: 2,
+                    ..Default::default()
                 },
             ),
         )
@@ -171,6 +213,42 @@ fn build_mosaic_auction_repeats() {
     assert!(kinda_same_imgs(mosaic_img, expected, 2.));
 }
 
+#[test]
+fn build_mosaic_auction_repeats_scaling() {
+    let (tile_imgs, master_img) = setup_imgs();
+
+    let result = Mosaic::from_images(master_img, tile_imgs, (16, 16));
+    assert!(result.is_ok());
+    let mosaic = result.unwrap();
+
+    let d_matrix = mosaic.distance_matrix();
+    assert_eq!(
+        d_matrix.data.len(),
+        mosaic.master.cells.len() * mosaic.tiles.len()
+    );
+
+    let mosaic_img = mosaic
+        .build_with_solver(
+            d_matrix,
+            Auction::with_epsilon_scaling(
+                1,
+                4,
+                Bidding::GaussSeidel,
+                SolverConfig {
+                    max_tile_occurrences: 2,
+                    ..Default::default()
+                },
+            ),
+        )
+        .unwrap();
+    assert_eq!(mosaic_img.dimensions(), mosaic.master.img.dimensions());
+    let expected = open_expected(
+        &mosaic_img,
+        test_dir().join("mosaic_16_16_auction_repeats_scaling.png"),
+    );
+    assert!(kinda_same_imgs(mosaic_img, expected, 2.));
+}
+
 #[test]
 fn build_mosaic_repeats() {
     let (tile_imgs, master_img) = setup_imgs();
@@ -190,6 +268,7 @@ fn build_mosaic_repeats() {
             d_matrix,
             SolverConfig {
                 max_tile_occurrences: 2,
+                ..Default::default()
             },
         )
         .unwrap();
@@ -217,6 +296,7 @@ fn build_mosaic_greedy_repeats() {
             d_matrix,
             Greedy::new(SolverConfig {
                 max_tile_occurrences: 2,
+                ..Default::default()
             }),
         )
         .unwrap();
@@ -250,6 +330,7 @@ fn build_mosaic_equalized() {
             d_matrix,
             SolverConfig {
                 max_tile_occurrences: 1,
+                ..Default::default()
             },
         )
         .unwrap();
@@ -279,6 +360,7 @@ fn build_mosaic_match_tiles_to_master() {
             d_matrix,
             SolverConfig {
                 max_tile_occurrences: 1,
+                ..Default::default()
             },
         )
         .unwrap();
@@ -313,6 +395,7 @@ fn build_mosaic_match_master_to_tiles() {
             d_matrix,
             SolverConfig {
                 max_tile_occurrences: 1,
+                ..Default::default()
             },
         )
         .unwrap();
@@ -344,6 +427,7 @@ fn build_mosaic_blueprint() {
         d_matrix,
         SolverConfig {
             max_tile_occurrences: 1,
+            ..Default::default()
         },
     );
     assert!(result.is_ok());
@@ -389,6 +473,7 @@ fn build_mosaic_blueprint_greedy() {
         d_matrix,
         Greedy::new(SolverConfig {
             max_tile_occurrences: 1,
+            ..Default::default()
         }),
     );
     assert!(result.is_ok());
@@ -426,3 +511,71 @@ fn kinda_same_imgs(img1: image::RgbImage, img2: image::RgbImage, tol: f64) -> bo
     let diff_sum: u64 = diff.into_iter().map(|a| a as u64).sum();
     diff_sum as f64 / (img1.width() * img1.height() * 3) as f64 <= tol
 }
+
+#[cfg(feature = "blueprint")]
+#[test]
+fn build_mosaic_blueprint_hexagonal() {
+    let (tile_imgs, master_img) = setup_imgs();
+    let master = Master::from_image_with_layout(master_img, (16, 16), Layout::Hexagonal).unwrap();
+    let mosaic = Mosaic::new(master, tile_imgs, (16, 16)).unwrap();
+
+    let d_matrix = mosaic.distance_matrix();
+    let blueprint = mosaic
+        .build_blueprint_with_solver(
+            d_matrix,
+            Greedy::new(SolverConfig {
+                max_tile_occurrences: 1,
+                ..Default::default()
+            }),
+        )
+        .unwrap();
+    // The hexagons are kept through serialization
+    let serialized = serde_json::to_string(&blueprint).unwrap();
+    let deserialized: Blueprint = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(deserialized, blueprint);
+    assert!(deserialized
+        .cells
+        .iter()
+        .all(|cell| cell.shape == Shape::Hexagon));
+
+    let assignments = blueprint.cells.iter().map(|cell| cell.tile_index).collect();
+    assert_eq!(
+        deserialized
+            .render(&mosaic.master.img, &mosaic.tiles)
+            .unwrap(),
+        mosaic.render(assignments).unwrap()
+    );
+}
+
+#[cfg(feature = "blueprint")]
+#[test]
+fn build_mosaic_blueprint_voronoi() {
+    let (tile_imgs, master_img) = setup_imgs();
+    let layout = Layout::Voronoi(Voronoi::default());
+    let master = Master::from_image_with_layout(master_img, (16, 16), layout).unwrap();
+    let mosaic = Mosaic::new(master, tile_imgs, (16, 16)).unwrap();
+
+    let d_matrix = mosaic.distance_matrix();
+    let blueprint = mosaic
+        .build_blueprint_with_solver(d_matrix, Greedy::new(SolverConfig::default()))
+        .unwrap();
+    // The vertices of the polygons are kept through serialization
+    let serialized = serde_json::to_string(&blueprint).unwrap();
+    let deserialized: Blueprint = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(deserialized, blueprint);
+    assert!(deserialized
+        .cells
+        .iter()
+        .zip(mosaic.master.regions.iter())
+        .all(
+            |(cell, region)| matches!(cell.shape, Shape::Polygon(_)) && cell.shape == region.shape
+        ));
+
+    let assignments = blueprint.cells.iter().map(|cell| cell.tile_index).collect();
+    assert_eq!(
+        deserialized
+            .render(&mosaic.master.img, &mosaic.tiles)
+            .unwrap(),
+        mosaic.render(assignments).unwrap()
+    );
+}